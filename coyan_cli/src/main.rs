@@ -1,9 +1,17 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use coyan_fta::cutsets::{self, CutSet, CutSetApproximations, CutSetLimits};
+use coyan_fta::dot::DotOverlay;
 use coyan_fta::fault_tree::FaultTree;
 use coyan_fta::formula::CNFFormat;
+use coyan_fta::montecarlo::{Biasing, MonteCarloConfig};
+use coyan_fta::sensitivity::Sensitivity;
 use coyan_fta::solver::*;
+use coyan_fta::uncertainty::{Sampling, UncertaintyConfig};
 use coyan_rft::rft_generator::{RFTConfig, RFaultTree};
+use itertools::Itertools;
+use rayon::prelude::*;
 use serde_json::json;
+use std::collections::HashMap;
 use std::path::Path;
 use std::time::Instant;
 use std::{fmt::Debug, str::FromStr};
@@ -48,15 +56,76 @@ enum Command {
     Modularize(ModCommand),
     #[clap(about = "Generate a Static Random FT.")]
     Rft(RandomGenerationCommand),
+    #[clap(
+        about = "Writes the FT in another format. The format is given by the extension of the output file."
+    )]
+    Convert(ConvertCommand),
+    #[clap(
+        about = "Draws the FT as a Graphviz DOT graph. Can highlight modules, colour basic events by criticality and annotate gate probabilities."
+    )]
+    Dot(DotCommand),
+    #[clap(
+        about = "Computes the minimal cut sets of the FT, with their probabilities. The cut sets can be truncated by order and by probability."
+    )]
+    Cutsets(CutSetsCommand),
+    #[clap(
+        about = "Propagates the uncertainty of the parameters of the basic events to the TEP, by sampling parameter sets and solving the FT for each one."
+    )]
+    Uncertainty(UncertaintyCommand),
+    #[clap(
+        about = "Computes the derivatives of the TEP with respect to the probability and the failure rate of each BE, with their elasticities."
+    )]
+    Sensitivity(SensitivityCommand),
+    #[clap(
+        about = "Computes the first order and total effect Sobol indices of the uncertain parameters of the basic events, with Saltelli sampling."
+    )]
+    Sobol(SobolCommand),
+}
+
+/// Whether the extension of the file is one of the given ones.
+fn has_extension(filename: &str, extensions: &[&str]) -> bool {
+    Path::new(filename)
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| extensions.contains(&e.to_lowercase().as_str()))
+}
+
+/// Format of the model. If not given, it is taken from the extension of the file.
+fn input_format(model: &InputArgs) -> InputFormat {
+    match model.input_format {
+        InputFormat::Auto if has_extension(&model.input, &["json"]) => InputFormat::Json,
+        InputFormat::Auto if has_extension(&model.input, &["xml", "opsa"]) => InputFormat::Mef,
+        InputFormat::Auto => InputFormat::Galileo,
+        format => format,
+    }
+}
+
+/// Reads the FT from the input model. If the model has errors, prints all of them and exits.
+fn read_ft(model: &InputArgs, simplify: bool, negate_or: bool) -> FaultTree<String> {
+    let filename = model.input.as_str();
+    let parameters: HashMap<String, f64> = model.params.iter().cloned().collect();
+    let ft = match input_format(model) {
+        InputFormat::Json if !parameters.is_empty() => {
+            eprintln!("JSON models have no parameters to replace.");
+            std::process::exit(1)
+        }
+        InputFormat::Json => FaultTree::new_from_json(filename, negate_or),
+        InputFormat::Mef => FaultTree::new_from_mef(filename, simplify, negate_or, &parameters),
+        _ => FaultTree::new_from_file(filename, simplify, negate_or, &parameters),
+    };
+    ft.unwrap_or_else(|errors| {
+        eprintln!("{}", errors);
+        std::process::exit(1)
+    })
 }
 
 /// Outputs relevant information about the FT.
 fn ft_info(command: InfoCommand) {
-    let dft_filename = command.input;
+    let dft_filename = command.model.input.clone();
     let simplify = command.simplify;
     let path = Path::new(dft_filename.as_str());
     let model_name = path.file_name().unwrap();
-    let mut ft = FaultTree::new_from_file(&dft_filename, simplify, false);
+    let mut ft = read_ft(&command.model, simplify, false);
     let top_type = ft.nodes[ft.root_id].gate_type();
     let (num_be, num_gates, num_clauses) = ft.get_info(command.preprocess);
     let num_modules = if command.modularize {
@@ -79,7 +148,7 @@ fn ft_info(command: InfoCommand) {
 
 /// Translates the FT explicit formula to a CNF file.
 fn translate(command: TranslateCommand) {
-    let dft_filename = command.input;
+    let dft_filename = command.model.input.clone();
     let cnf_filename = command.output;
     let w_file = command.w_file;
     let simplify = command.config.simplify;
@@ -89,16 +158,38 @@ fn translate(command: TranslateCommand) {
         CNFFormat::from_str(&command.config.format).expect("Unsupported format. Try MCC or MC21.");
 
     let time_start = Instant::now();
-    let ft = FaultTree::new_from_file(&dft_filename, simplify, command.config.negate_or);
+    let ft = read_ft(&command.model, simplify, command.config.negate_or);
 
-    ft.dump_cnf_to_file(
-        format!("{}.cnf", cnf_filename),
-        format,
-        command.timepoint,
-        w_file,
-        command.config.preprocess,
-        command.unavailability,
-    );
+    match command.timebounds {
+        None => ft.dump_cnf_to_file(
+            format!("{}.cnf", cnf_filename),
+            format,
+            command.timepoint,
+            w_file,
+            command.config.preprocess,
+            command.unavailability,
+        ),
+        // The formula is written once, with a weights file for each timepoint, or in a CNF file
+        // for each timepoint if the weights go in the same file.
+        Some(bounds) => {
+            let formula = ft.dump_cnf_formula(format, command.config.preprocess);
+            let weights = |t: f64| ft.dump_cnf_weights(format, t, command.unavailability);
+            match w_file {
+                Some(w_filename) => {
+                    write_file(&format!("{}.cnf", cnf_filename), &formula);
+                    for t in timepoints(&bounds) {
+                        write_file(&format!("{}_{}.w", w_filename, t), &weights(t));
+                    }
+                }
+                None => {
+                    for t in timepoints(&bounds) {
+                        let text = format!("{}{}", formula, weights(t));
+                        write_file(&format!("{}_{}.cnf", cnf_filename, t), &text);
+                    }
+                }
+            }
+        }
+    }
 
     let duration = time_start.elapsed();
     println!(
@@ -110,63 +201,231 @@ fn translate(command: TranslateCommand) {
     );
 }
 
+/// Timepoints of the range `[start, end, step]` given by `--timebounds`, including the end.
+fn timepoints(bounds: &[f64]) -> Vec<f64> {
+    let &[start, end, step] = bounds else {
+        unreachable!("clap takes exactly 3 values for the time bounds")
+    };
+    if step.is_nan() || step <= 0.0 || end < start {
+        eprintln!("The time bounds must be [start, end, step], with start <= end and step > 0.");
+        std::process::exit(1)
+    }
+    // The tolerance keeps the end when the range is not exact in floating point.
+    let steps = ((end - start) / step + 1e-9).floor() as usize;
+    (0..=steps).map(|i| start + i as f64 * step).collect()
+}
+
+/// Write the text to the file, exiting if it fails.
+fn write_file(filename: &str, text: &str) {
+    std::fs::write(filename, text).unwrap_or_else(|e| {
+        eprintln!("unable to write '{}': {}", filename, e);
+        std::process::exit(1)
+    })
+}
+
+/// Write the output to the file if given, or print it.
+fn write_output(output: Option<String>, text: &str) {
+    match output {
+        Some(filename) => write_file(&filename, text),
+        None => println!("{}", text.trim_end()),
+    }
+}
+
 /// Compute TEP of FT, given a solver and the configuration needed.
 /// Can perform multiple timepoints if range was given and use multi-threading
 /// to handle each run.
 fn compute_tep(command: SolveCommand) {
-    let dft_filename = command.input;
-    let solver_path = command.solver_path;
+    if command.method != Method::Exact {
+        return estimate_tep(command);
+    }
+    let dft_filename = command.model.input.clone();
+    let Some(solver_path) = command.solver_path else {
+        eprintln!("The exact method needs a solver, given by --solver-path.");
+        std::process::exit(1)
+    };
     let verbose = command.config.verb;
     let format =
         CNFFormat::from_str(&command.config.format).expect("Unsupported format. Try MCC or MC21.");
+    let timepoints = command.timebounds.as_deref().map(timepoints);
+
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(command.config.num_threads)
+        .build_global()
+        .unwrap();
 
     let time_start = Instant::now();
-    let ft = FaultTree::new_from_file(
-        &dft_filename,
+    let ft = read_ft(
+        &command.model,
         command.config.simplify,
         command.config.negate_or,
     );
     let mut solver: Box<dyn Solver + Sync> = get_solver_from_path(&solver_path);
-    solver._set_cache_size(command.config.max_cache_size);
 
-    let tep = solver.compute(
-        &ft,
-        format,
-        command.timepoint,
-        command.config.timeout_s,
-        command.config.preprocess,
+    let (timepoints, teps) = match timepoints {
+        Some(timepoints) => {
+            // Distribute cache use for each thread.
+            solver.set_cache_size(command.config.cache_size_mb(command.config.num_threads));
+            let teps = solver.compute_timepoints(
+                &ft,
+                format,
+                &timepoints,
+                command.config.timeout_s,
+                command.config.preprocess,
+                command.config.negate_or,
+                command.unavailability,
+            );
+            (timepoints, teps)
+        }
+        None => {
+            solver.set_cache_size(command.config.cache_size_mb(1));
+            let tep = solver.compute(
+                &ft,
+                format,
+                command.timepoint,
+                command.config.timeout_s,
+                command.config.preprocess,
+                command.config.negate_or,
+                command.unavailability,
+            );
+            (vec![command.timepoint], vec![Ok(tep)])
+        }
+    };
+    let duration = time_start.elapsed();
+
+    let text = match command.output_format {
+        OutputFormat::Csv => {
+            let mut text = String::from("timepoint,TEP\n");
+            // The TEP of the timepoints that failed is left empty, with the error in stderr.
+            for (t, tep) in timepoints.iter().zip(&teps) {
+                match tep {
+                    Ok(tep) => text.push_str(&format!("{},{}\n", t, tep)),
+                    Err(msg) => {
+                        eprintln!("timepoint {}: {}", t, msg);
+                        text.push_str(&format!("{},\n", t));
+                    }
+                }
+            }
+            text
+        }
+        OutputFormat::Json => {
+            let mut output = if command.timebounds.is_none() {
+                json!({
+                    "TEP": teps[0].as_ref().ok(),
+                    "timepoint": command.timepoint
+                })
+            } else {
+                let series = timepoints
+                    .iter()
+                    .zip(&teps)
+                    .map(|(t, tep)| match tep {
+                        Ok(tep) => json!({ "timepoint": t, "TEP": tep }),
+                        Err(msg) => json!({ "timepoint": t, "TEP": null, "error": msg }),
+                    })
+                    .collect_vec();
+                json!({ "series": series })
+            };
+            if verbose {
+                let path = Path::new(dft_filename.as_str());
+                output["model"] = json!(path.file_name().unwrap().to_str());
+                output["duration"] = json!(format!("{:?}", duration));
+            }
+            output.to_string()
+        }
+    };
+    write_output(command.output, &text);
+}
+
+/// Estimate the TEP with a Monte Carlo simulation, using the threads of the configuration.
+/// With importance sampling, the biasing can use the minimal cut sets of the FT.
+fn estimate_tep(command: SolveCommand) {
+    let dft_filename = command.model.input.clone();
+    let args = command.montecarlo;
+    if !(args.confidence > 0.0 && args.confidence < 1.0) {
+        eprintln!("The confidence level must be in (0, 1).");
+        std::process::exit(1)
+    }
+    if command.timebounds.is_some() || command.output_format == OutputFormat::Csv {
+        eprintln!("The time bounds and the CSV output are only supported by the exact method.");
+        std::process::exit(1)
+    }
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(command.config.num_threads)
+        .build_global()
+        .unwrap();
+
+    let time_start = Instant::now();
+    let ft = read_ft(
+        &command.model,
+        command.config.simplify,
         command.config.negate_or,
-        command.unavailability,
     );
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let config = MonteCarloConfig {
+        max_samples: args.samples as usize,
+        target_rel_error: args.target_rel_error,
+        confidence: args.confidence,
+        seed,
+    };
+    let (estimate, biasing) = match command.method {
+        Method::ImportanceSampling => {
+            let biasing = match args.biasing {
+                BiasingMethod::Failure => Biasing::Failure(args.bias),
+                BiasingMethod::CutSets => {
+                    let limits = CutSetLimits {
+                        max_order: args.max_order,
+                        cutoff: args.cutoff,
+                    };
+                    let cut_sets = ft
+                        .minimal_cut_sets(command.timepoint, command.unavailability, limits)
+                        .unwrap_or_else(|msg| {
+                            eprintln!("{}", msg);
+                            std::process::exit(1)
+                        });
+                    Biasing::CutSets(cut_sets)
+                }
+            };
+            let estimate =
+                ft.importance_sampling(command.timepoint, command.unavailability, config, &biasing);
+            let biasing = match biasing {
+                Biasing::Failure(bias) => json!({ "failure": bias }),
+                Biasing::CutSets(cut_sets) => json!({ "cut_sets": cut_sets.len() }),
+            };
+            (estimate, Some(biasing))
+        }
+        _ => (
+            ft.monte_carlo(command.timepoint, command.unavailability, config),
+            None,
+        ),
+    };
     let duration = time_start.elapsed();
-    if !verbose {
-        println!(
-            "{}",
-            json!({
-                "TEP": tep,
-                "timepoint": command.timepoint
-            })
-        )
-    } else {
+
+    let mut output = json!({
+        "TEP": estimate.tep,
+        "timepoint": command.timepoint,
+        "method": command.method.to_possible_value().unwrap().get_name(),
+        "confidence": args.confidence,
+        "confidence_interval": [estimate.ci_low, estimate.ci_high],
+        "std_error": estimate.std_error,
+        "rel_error": estimate.rel_error,
+        "samples": estimate.samples,
+        "seed": seed
+    });
+    if let Some(biasing) = biasing {
+        output["biasing"] = biasing;
+    }
+    if command.config.verb {
         let path = Path::new(dft_filename.as_str());
-        let model_name = path.file_name().unwrap();
-        println!(
-            "{}",
-            json!({
-                "model": model_name.to_str(),
-                "timepoint": command.timepoint,
-                "TEP": tep,
-                "duration": format!("{:?}", duration)
-            })
-        );
-    };
+        output["model"] = json!(path.file_name().unwrap().to_str());
+        output["duration"] = json!(format!("{:?}", duration));
+    }
+    write_output(command.output, &output.to_string());
 }
 
 /// Compute Criticality and Birnbaum Measures for all of the Basic Event in the FT
 /// Can be time consuming.
 /// Future Work: Paralelize with threads
 fn compute_importance_measures(command: ImportanceCommand) {
-    let dft_filename = command.input;
+    let dft_filename = command.model.input.clone();
     let path = Path::new(dft_filename.as_str());
     let model_name = path.file_name().unwrap();
     let format =
@@ -178,11 +437,10 @@ fn compute_importance_measures(command: ImportanceCommand) {
         .build_global()
         .unwrap();
 
-    let max_size = command.config.max_cache_size / command.config.num_threads;
-    solver._set_cache_size(max_size);
+    solver.set_cache_size(command.config.cache_size_mb(command.config.num_threads));
 
-    let ft = FaultTree::new_from_file(
-        &dft_filename,
+    let ft = read_ft(
+        &command.model,
         command.config.simplify,
         command.config.negate_or,
     );
@@ -195,14 +453,84 @@ fn compute_importance_measures(command: ImportanceCommand) {
         );
     }
 
-    let measures = ft.importance_measures(
-        solver.as_ref(),
-        format,
-        command.timepoint,
-        command.config.negate_or,
-    );
+    let structural = command.measures.contains(&ImportanceMeasure::Structural);
+    let measures = match command.mode {
+        ImportanceMode::Compiled => ft.compiled_importance_measures(
+            solver.as_ref(),
+            command.timepoint,
+            command.config.timeout_s,
+            command.unavailability,
+            structural,
+        ),
+        mode => {
+            let importance_measures = if mode == ImportanceMode::Modular {
+                FaultTree::modular_importance_measures
+            } else {
+                FaultTree::importance_measures
+            };
+            importance_measures(
+                &ft,
+                solver.as_ref(),
+                format,
+                command.timepoint,
+                command.config.timeout_s,
+                command.config.preprocess,
+                command.config.negate_or,
+                command.unavailability,
+                structural,
+            )
+        }
+    }
+    .unwrap_or_else(|msg| {
+        eprintln!("Unable to compute the TEP of the FT: {}", msg);
+        std::process::exit(1)
+    });
+    // Fussell-Vesely comes from the minimal cut sets, so it is left empty on non coherent FTs.
+    let fussell_vesely = command
+        .measures
+        .contains(&ImportanceMeasure::FussellVesely)
+        .then(|| {
+            ft.minimal_cut_sets(
+                command.timepoint,
+                command.unavailability,
+                CutSetLimits::default(),
+            )
+        })
+        .and_then(|cut_sets| match cut_sets {
+            Ok(cut_sets) => Some(cutsets::fussell_vesely(&cut_sets)),
+            Err(msg) => {
+                eprintln!("Unable to compute the Fussell-Vesely measure: {}", msg);
+                None
+            }
+        });
     let elapsed = time_start.elapsed();
 
+    let measures = measures
+        .into_iter()
+        .map(|(be_name, mut result)| {
+            if let (Ok(be_measures), Some(fussell_vesely)) = (&mut result, &fussell_vesely) {
+                // The basic events in no cut set do not contribute to the TEP.
+                be_measures.fussell_vesely =
+                    Some(fussell_vesely.get(&be_name).copied().unwrap_or(0.0));
+            }
+            let mut selected = serde_json::Map::new();
+            match result {
+                Ok(be_measures) => {
+                    for m in command.measures.iter().unique() {
+                        let name = m.to_possible_value().unwrap().get_name().to_owned();
+                        selected.insert(name, json!(m.value(&be_measures)));
+                    }
+                    selected.insert(String::from("timed_out"), json!(false));
+                }
+                Err(msg) => {
+                    selected.insert(String::from("timed_out"), json!(msg == TIMEOUT));
+                    selected.insert(String::from("error"), json!(msg));
+                }
+            }
+            (be_name, selected)
+        })
+        .collect::<HashMap<_, _>>();
+
     println!(
         "{}",
         json!({
@@ -215,7 +543,7 @@ fn compute_importance_measures(command: ImportanceCommand) {
 }
 
 fn modularize_ft(command: ModCommand) {
-    let dft_filename = command.input;
+    let dft_filename = command.model.input.clone();
     let format =
         CNFFormat::from_str(&command.config.format).expect("Unsupported format. Try MCC or MC21.");
     let solver_path = command.solver_path;
@@ -229,11 +557,10 @@ fn modularize_ft(command: ModCommand) {
         .unwrap();
 
     // Distribute cache use for each thread.
-    let max_size = command.config.max_cache_size / command.config.num_threads;
-    solver._set_cache_size(max_size);
+    solver.set_cache_size(command.config.cache_size_mb(command.config.num_threads));
 
-    let mut ft = FaultTree::new_from_file(
-        &dft_filename,
+    let mut ft = read_ft(
+        &command.model,
         command.config.simplify,
         command.config.negate_or,
     );
@@ -261,7 +588,7 @@ fn modularize_ft(command: ModCommand) {
     let time_modularisation = time_start.elapsed();
     let info_post = ft.get_info(None);
     // Reset Cache max size for the last execution.
-    solver._set_cache_size(command.config.max_cache_size);
+    solver.set_cache_size(command.config.cache_size_mb(1));
 
     if command.config.display {
         println!(
@@ -295,6 +622,426 @@ fn modularize_ft(command: ModCommand) {
     );
 }
 
+/// Writes the FT in another format, given by the extension of the output file.
+fn convert(command: ConvertCommand) {
+    let ft = read_ft(&command.model, command.simplify, false);
+    let path = Path::new(command.output.as_str());
+    let model_name = path.file_stem().unwrap().to_str().unwrap();
+
+    if has_extension(&command.output, &["xml", "opsa"]) {
+        ft.save_to_mef(&command.output, model_name);
+    } else if has_extension(&command.output, &["dft"]) {
+        ft.save_to_dft(&command.output);
+    } else if has_extension(&command.output, &["json"]) {
+        ft.save_to_json(&command.output);
+    } else {
+        eprintln!(
+            "Unsupported output format. Try .dft for GALILEO, .xml or .opsa for Open-PSA MEF, or .json."
+        );
+        std::process::exit(1)
+    }
+}
+
+/// Draws the FT in DOT format, with the requested analysis results on top.
+fn draw_ft(command: DotCommand) {
+    let mut ft = read_ft(
+        &command.model,
+        command.config.simplify,
+        command.config.negate_or,
+    );
+    let mut overlay = DotOverlay::default();
+    if command.modularize {
+        overlay.modules = ft.modularize_ft();
+    }
+
+    if let Some(solver_path) = &command.solver_path {
+        let format = CNFFormat::from_str(&command.config.format)
+            .expect("Unsupported format. Try MCC or MC21.");
+        let mut solver: Box<dyn Solver + Sync> = get_solver_from_path(solver_path);
+
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(command.config.num_threads)
+            .build_global()
+            .unwrap();
+        solver.set_cache_size(command.config.cache_size_mb(command.config.num_threads));
+
+        if command.importance {
+            overlay.criticality = ft
+                .importance_measures(
+                    solver.as_ref(),
+                    format,
+                    command.timepoint,
+                    command.config.timeout_s,
+                    command.config.preprocess.clone(),
+                    command.config.negate_or,
+                    false,
+                    false,
+                )
+                .unwrap_or_else(|msg| {
+                    eprintln!("Unable to compute the TEP of the FT: {}", msg);
+                    std::process::exit(1)
+                })
+                .into_iter()
+                .filter_map(|(be_name, result)| Some((be_name, result.ok()?.criticality)))
+                .collect();
+        }
+
+        if command.probabilities {
+            let gate_ids = ft
+                .nodes
+                .iter_enumerated()
+                .filter_map(|(nid, n)| if n.is_gate() { Some(nid) } else { None })
+                .collect_vec();
+            // The gates where the solver fails, like on a timeout, are drawn without probability.
+            overlay.probabilities = gate_ids
+                .par_iter()
+                .filter_map(|&nid| {
+                    let sub_ft = ft.subtree_with_root(nid);
+                    let tep = solver.try_compute(
+                        &sub_ft,
+                        format,
+                        command.timepoint,
+                        command.config.timeout_s,
+                        None,
+                        command.config.negate_or,
+                        false,
+                    );
+                    Some((nid, tep.ok()?))
+                })
+                .collect();
+        }
+    }
+
+    ft.save_to_dot(&command.output, &overlay);
+}
+
+/// Writes the cut sets as CSV, one row per cut set with its basic events separated by spaces.
+fn cut_sets_to_csv(cut_sets: &[CutSet]) -> String {
+    let mut text = String::from("rank,order,probability,events\n");
+    for (i, cs) in cut_sets.iter().enumerate() {
+        text.push_str(&format!(
+            "{},{},{},{}\n",
+            i + 1,
+            cs.order(),
+            cs.probability,
+            cs.events.join(" ")
+        ));
+    }
+    text
+}
+
+/// Value of an approximation of the TEP, with its error if the exact TEP is known.
+/// The relative error is null when the exact TEP is 0.
+fn approximation_json(value: f64, tep: Option<f64>) -> serde_json::Value {
+    match tep {
+        Some(tep) => json!({
+            "value": value,
+            "abs_error": value - tep,
+            "rel_error": (tep != 0.0).then(|| (value - tep) / tep),
+        }),
+        None => json!({ "value": value }),
+    }
+}
+
+/// Computes the minimal cut sets of the FT and reports the most probable ones, together with
+/// the approximations of the TEP. If a solver is given, the approximations are compared with the exact TEP.
+fn cut_sets(command: CutSetsCommand) {
+    let dft_filename = command.model.input.clone();
+    let path = Path::new(dft_filename.as_str());
+    let model_name = path.file_name().unwrap();
+    let ft = read_ft(
+        &command.model,
+        command.config.simplify,
+        command.config.negate_or,
+    );
+    let limits = CutSetLimits {
+        max_order: command.max_order,
+        cutoff: command.cutoff,
+    };
+
+    let time_start = Instant::now();
+    let cut_sets = ft
+        .minimal_cut_sets(command.timepoint, command.unavailability, limits)
+        .unwrap_or_else(|msg| {
+            eprintln!("{}", msg);
+            std::process::exit(1)
+        });
+    let approximations = CutSetApproximations::from_cut_sets(&cut_sets);
+    let duration = time_start.elapsed();
+    let num_cut_sets = cut_sets.len();
+    let top = &cut_sets[..command.top.unwrap_or(num_cut_sets).min(num_cut_sets)];
+
+    let text = match command.output_format {
+        OutputFormat::Json => {
+            let tep = command.solver_path.as_ref().map(|solver_path| {
+                let format = CNFFormat::from_str(&command.config.format)
+                    .expect("Unsupported format. Try MCC or MC21.");
+                let mut solver: Box<dyn Solver + Sync> = get_solver_from_path(solver_path);
+                solver.set_cache_size(command.config.cache_size_mb(1));
+                solver.compute(
+                    &ft,
+                    format,
+                    command.timepoint,
+                    command.config.timeout_s,
+                    command.config.preprocess.clone(),
+                    command.config.negate_or,
+                    command.unavailability,
+                )
+            });
+            json!({
+                "model": model_name.to_str(),
+                "timepoint": command.timepoint,
+                "TEP": tep,
+                "rare_event": approximation_json(approximations.rare_event, tep),
+                "min_cut_upper_bound": approximation_json(approximations.min_cut_upper_bound, tep),
+                "num_cut_sets": num_cut_sets,
+                "cut_sets": top,
+                "duration": format!("{:?}", duration)
+            })
+            .to_string()
+        }
+        OutputFormat::Csv => cut_sets_to_csv(top),
+    };
+    write_output(command.output, &text);
+}
+
+/// Propagate the uncertainty of the parameters of the basic events to the TEP.
+fn propagate_uncertainty(command: UncertaintyCommand) {
+    let dft_filename = command.model.input.clone();
+    let path = Path::new(dft_filename.as_str());
+    let model_name = path.file_name().unwrap();
+    let format =
+        CNFFormat::from_str(&command.config.format).expect("Unsupported format. Try MCC or MC21.");
+    let mut solver: Box<dyn Solver + Sync> = get_solver_from_path(&command.solver_path);
+
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(command.config.num_threads)
+        .build_global()
+        .unwrap();
+
+    // Distribute cache use for each thread.
+    solver.set_cache_size(command.config.cache_size_mb(command.config.num_threads));
+
+    let ft = read_ft(
+        &command.model,
+        command.config.simplify,
+        command.config.negate_or,
+    );
+    let time_start = Instant::now();
+    let seed = command.seed.unwrap_or_else(rand::random);
+    let config = UncertaintyConfig {
+        samples: command.samples as usize,
+        sampling: sampling(command.sampling),
+        seed,
+        bins: command.bins as usize,
+    };
+    let point_estimate = solver
+        .try_compute(
+            &ft,
+            format,
+            command.timepoint,
+            command.config.timeout_s,
+            command.config.preprocess.clone(),
+            command.config.negate_or,
+            command.unavailability,
+        )
+        .unwrap_or_else(|msg| {
+            eprintln!("Unable to compute the TEP of the FT: {}", msg);
+            std::process::exit(1)
+        });
+    let result = ft
+        .propagate_uncertainty(
+            solver.as_ref(),
+            format,
+            command.timepoint,
+            command.config.timeout_s,
+            command.config.preprocess,
+            command.config.negate_or,
+            command.unavailability,
+            config,
+        )
+        .unwrap_or_else(|msg| {
+            eprintln!("{}", msg);
+            std::process::exit(1)
+        });
+    let duration = time_start.elapsed();
+
+    let output = json!({
+        "model": model_name.to_str(),
+        "timepoint": command.timepoint,
+        "point_estimate": point_estimate,
+        "sampling": command.sampling.to_possible_value().unwrap().get_name(),
+        "seed": seed,
+        "uncertain_events": ft.uncertain_events().len(),
+        "samples": result.samples,
+        "mean": result.mean,
+        "std_dev": result.std_dev,
+        "median": result.median,
+        "p5": result.p5,
+        "p95": result.p95,
+        "min": result.min,
+        "max": result.max,
+        "histogram": result.histogram,
+        "duration": format!("{:?}", duration),
+    });
+    write_output(command.output, &output.to_string());
+}
+
+fn sampling(method: SamplingMethod) -> Sampling {
+    match method {
+        SamplingMethod::Random => Sampling::Random,
+        SamplingMethod::Lhs => Sampling::LatinHypercube,
+    }
+}
+
+/// Compute the Sobol indices of the uncertain parameters of the basic events.
+fn compute_sobol_indices(command: SobolCommand) {
+    let dft_filename = command.model.input.clone();
+    let path = Path::new(dft_filename.as_str());
+    let model_name = path.file_name().unwrap();
+    let format =
+        CNFFormat::from_str(&command.config.format).expect("Unsupported format. Try MCC or MC21.");
+    let mut solver: Box<dyn Solver + Sync> = get_solver_from_path(&command.solver_path);
+
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(command.config.num_threads)
+        .build_global()
+        .unwrap();
+
+    // Distribute cache use for each thread.
+    solver.set_cache_size(command.config.cache_size_mb(command.config.num_threads));
+
+    let ft = read_ft(
+        &command.model,
+        command.config.simplify,
+        command.config.negate_or,
+    );
+    let time_start = Instant::now();
+    let seed = command.seed.unwrap_or_else(rand::random);
+    let config = UncertaintyConfig {
+        samples: command.samples as usize,
+        sampling: sampling(command.sampling),
+        seed,
+        bins: 1,
+    };
+    let result = ft
+        .sobol_indices(
+            solver.as_ref(),
+            format,
+            command.timepoint,
+            command.config.timeout_s,
+            command.config.preprocess,
+            command.config.negate_or,
+            command.unavailability,
+            config,
+        )
+        .unwrap_or_else(|msg| {
+            eprintln!("{}", msg);
+            std::process::exit(1)
+        });
+    let duration = time_start.elapsed();
+
+    let indices = result
+        .indices
+        .iter()
+        .enumerate()
+        .map(|(i, index)| {
+            json!({
+                "rank": i + 1,
+                "event": index.event,
+                "first_order": index.first_order,
+                "total_effect": index.total_effect,
+            })
+        })
+        .collect_vec();
+    let output = json!({
+        "model": model_name.to_str(),
+        "timepoint": command.timepoint,
+        "sampling": command.sampling.to_possible_value().unwrap().get_name(),
+        "seed": seed,
+        "samples": command.samples,
+        "evaluations": result.evaluations,
+        "mean": result.mean,
+        "variance": result.variance,
+        "indices": indices,
+        "duration": format!("{:?}", duration),
+    });
+    write_output(command.output, &output.to_string());
+}
+
+/// Compute the local sensitivity of the TEP to the parameters of each basic event.
+fn compute_sensitivities(command: SensitivityCommand) {
+    let dft_filename = command.model.input.clone();
+    let path = Path::new(dft_filename.as_str());
+    let model_name = path.file_name().unwrap();
+    let format =
+        CNFFormat::from_str(&command.config.format).expect("Unsupported format. Try MCC or MC21.");
+    let mut solver: Box<dyn Solver + Sync> = get_solver_from_path(&command.solver_path);
+
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(command.config.num_threads)
+        .build_global()
+        .unwrap();
+
+    // Distribute cache use for each thread.
+    solver.set_cache_size(command.config.cache_size_mb(command.config.num_threads));
+
+    let ft = read_ft(
+        &command.model,
+        command.config.simplify,
+        command.config.negate_or,
+    );
+    let time_start = Instant::now();
+    let (tep, sensitivities) = ft
+        .sensitivities(
+            solver.as_ref(),
+            format,
+            command.timepoint,
+            command.config.timeout_s,
+            command.config.preprocess,
+            command.config.negate_or,
+            command.unavailability,
+        )
+        .unwrap_or_else(|msg| {
+            eprintln!("Unable to compute the sensitivities of the FT: {}", msg);
+            std::process::exit(1)
+        });
+    let duration = time_start.elapsed();
+
+    let text = match command.output_format {
+        OutputFormat::Json => json!({
+            "model": model_name.to_str(),
+            "timepoint": command.timepoint,
+            "TEP": tep,
+            "sensitivities": sensitivities,
+            "duration": format!("{:?}", duration),
+        })
+        .to_string(),
+        OutputFormat::Csv => sensitivities_to_csv(&sensitivities),
+    };
+    write_output(command.output, &text);
+}
+
+fn sensitivities_to_csv(sensitivities: &[Sensitivity]) -> String {
+    let optional = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or_default();
+    let mut text = String::from(
+        "event,probability,d_tep_d_p,elasticity_p,rate,d_tep_d_lambda,elasticity_lambda\n",
+    );
+    for s in sensitivities {
+        text.push_str(&format!(
+            "{},{},{},{},{},{},{}\n",
+            s.event,
+            s.probability,
+            s.d_tep_d_p,
+            s.elasticity_p,
+            optional(s.rate),
+            optional(s.d_tep_d_lambda),
+            optional(s.elasticity_lambda)
+        ));
+    }
+    text
+}
+
 fn random_ft(comm: RandomGenerationCommand) {
     let n_nodes = comm.n_nodes;
     let rates = vec![comm.rate_be, comm.rate_and, comm.rate_or, comm.rate_vot];
@@ -366,5 +1113,11 @@ fn main() {
         Command::Importance(command) => compute_importance_measures(command),
         Command::Modularize(command) => modularize_ft(command),
         Command::Rft(command) => random_ft(command),
+        Command::Convert(command) => convert(command),
+        Command::Dot(command) => draw_ft(command),
+        Command::Cutsets(command) => cut_sets(command),
+        Command::Uncertainty(command) => propagate_uncertainty(command),
+        Command::Sensitivity(command) => compute_sensitivities(command),
+        Command::Sobol(command) => compute_sobol_indices(command),
     }
 }
@@ -1,10 +1,157 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use coyan_fta::fault_tree::ImpMeasures;
 
+/// Format of the input model.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// Given by the extension of the file: .json for JSON, .xml or .opsa for Open-PSA MEF, GALILEO otherwise.
+    Auto,
+    Galileo,
+    Mef,
+    Json,
+}
+
+/// Format of the output of the analyses that write a table.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    Csv,
+}
+
+/// Importance measure of the basic events.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImportanceMeasure {
+    /// Birnbaum Measure.
+    Birnbaum,
+    /// Improvement Potential, `TEP(p=1) - TEP`.
+    ImprovementPotential,
+    /// Criticality Measure.
+    Criticality,
+    /// Fussell-Vesely, from the minimal cut sets. Only for coherent FTs. Not computed by default,
+    /// as it enumerates every minimal cut set.
+    FussellVesely,
+    /// Risk Achievement Worth.
+    Raw,
+    /// Risk Reduction Worth.
+    Rrw,
+    /// Birnbaum structural importance. Doubles the number of solver calls.
+    Structural,
+}
+
+impl ImportanceMeasure {
+    pub fn value(&self, measures: &ImpMeasures) -> Option<f64> {
+        match self {
+            ImportanceMeasure::Birnbaum => Some(measures.birnbaum),
+            ImportanceMeasure::ImprovementPotential => Some(measures.improvement_potential),
+            ImportanceMeasure::Criticality => Some(measures.criticality),
+            ImportanceMeasure::FussellVesely => measures.fussell_vesely,
+            ImportanceMeasure::Raw => Some(measures.risk_achievement_worth),
+            ImportanceMeasure::Rrw => Some(measures.risk_reduction_worth),
+            ImportanceMeasure::Structural => measures.structural,
+        }
+    }
+}
+
+/// Engine of the importance measures.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportanceMode {
+    /// Solve the FT with each basic event conditioned to fail and to not fail.
+    Conditioning,
+    /// Solve each module once, and for each basic event only the modules that contain it.
+    Modular,
+    /// Compile the FT once and get the Birnbaum Measures from the derivatives of the TEP.
+    /// Only for the bdd, d4 and c2d solvers.
+    Compiled,
+}
+
+/// Method to compute the TEP.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// Exact TEP, given by the solver.
+    Exact,
+    /// Estimate of the TEP by Monte Carlo simulation, with a confidence interval.
+    Montecarlo,
+    /// Estimate of the TEP by Monte Carlo simulation with importance sampling, for rare top events.
+    ImportanceSampling,
+}
+
+/// Biasing of the basic events for importance sampling.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BiasingMethod {
+    /// Sample each basic event failing with probability at least `--bias`.
+    Failure,
+    /// Force the failure of one minimal cut set in each sample.
+    CutSets,
+}
+
+/// Sampling of the uncertain parameters of the basic events.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SamplingMethod {
+    /// Simple random sampling.
+    Random,
+    /// Latin hypercube sampling.
+    Lhs,
+}
+
+/// Parameters of the Monte Carlo simulation.
 #[derive(Parser, Debug, Clone)]
-pub struct InfoCommand {
-    /// Input file containing the fault tree in GALILEO format.
+pub struct MonteCarloArgs {
+    /// Maximum number of samples of the simulation.
+    #[arg(long, default_value_t = 1_000_000, value_parser = clap::value_parser!(u64).range(1..))]
+    pub samples: u64,
+    /// Stop the simulation when the half width of the confidence interval relative to the estimate is below this value.
+    #[arg(long)]
+    pub target_rel_error: Option<f64>,
+    /// Confidence level of the interval. [default: 0.95]
+    #[arg(long, default_value_t = 0.95)]
+    pub confidence: f64,
+    /// Seed of the random generator. If not given, a random one is used and reported.
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Biasing of the basic events for importance sampling. [default: failure]
+    #[arg(long, value_enum, default_value_t = BiasingMethod::Failure)]
+    pub biasing: BiasingMethod,
+    /// Minimum probability of failure of the basic events with failure biasing. [default: 0.1]
+    #[arg(long, default_value_t = 0.1)]
+    pub bias: f64,
+    /// Maximum number of basic events of the cut sets used for the biasing.
+    #[arg(long)]
+    pub max_order: Option<usize>,
+    /// Drop the cut sets used for the biasing with a probability lower than this value.
+    #[arg(long)]
+    pub cutoff: Option<f64>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct InputArgs {
+    /// Input file containing the fault tree in GALILEO, Open-PSA MEF or JSON format.
     #[arg(short, long, required = true)]
     pub input: String,
+    /// Format of the input file. [default: auto]
+    #[arg(long, value_enum, default_value_t = InputFormat::Auto)]
+    pub input_format: InputFormat,
+    /// Replace the value of a parameter of the model, as `<NAME>=<VALUE>`. Can be given several times.
+    #[arg(long = "param", value_name = "NAME=VALUE", value_parser = parse_parameter)]
+    pub params: Vec<(String, f64)>,
+}
+
+/// Parse a `<NAME>=<VALUE>` parameter given in the command line.
+fn parse_parameter(s: &str) -> Result<(String, f64), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected <NAME>=<VALUE>, found '{s}'"))?;
+    let value = value
+        .trim()
+        .parse::<f64>()
+        .map_err(|e| format!("invalid value '{value}': {e}"))?;
+    Ok((name.trim().to_owned(), value))
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct InfoCommand {
+    /// Input model.
+    #[command(flatten)]
+    pub model: InputArgs,
     /// Simplify the FT by removing one children gates. [default: false]
     #[arg(short, long, default_value_t = false)]
     pub simplify: bool,
@@ -17,19 +164,35 @@ pub struct InfoCommand {
 }
 #[derive(Parser, Debug, Clone)]
 pub struct SolveCommand {
-    /// Input file containing the fault tree in GALILEO format.
-    #[arg(short, long, required = true)]
-    pub input: String,
-    /// Solver path and arguments.
+    /// Input model.
+    #[command(flatten)]
+    pub model: InputArgs,
+    /// Solver path and arguments. Required by the exact method.
     #[arg(short, long)]
-    pub solver_path: String,
+    pub solver_path: Option<String>,
+    /// Method to compute the TEP. [default: exact]
+    #[arg(long, value_enum, default_value_t = Method::Exact)]
+    pub method: Method,
+    /// Parameters of the Monte Carlo simulation.
+    #[command(flatten)]
+    pub montecarlo: MonteCarloArgs,
+    /// Compute the TEP at each timepoint of the range `[start, end, step]`, including the end.
+    #[arg(short = 't', long, num_args = 3, value_names = ["START", "END", "STEP"], conflicts_with = "timepoint")]
+    pub timebounds: Option<Vec<f64>>,
     /// Compute TEP at a specific timepoint
-    #[arg(short, long, default_value_t = 1.0)]
+    #[arg(long, default_value_t = 1.0)]
     pub timepoint: f64,
-    /// Computes the unavailability.
+    /// Computes the unavailability, using the repairs of the basic events: monitored (`repair=`)
+    /// or periodically tested (`interval=` and `repair_time=`).
     /// See Table XI-2 of the Fault Tree Handbook. U.S. Nuclear Regulatory Commission (1981) for more information.
     #[arg(long, default_value_t = false)]
     pub unavailability: bool,
+    /// Format of the output, a row for each timepoint. Only the exact method supports CSV. [default: json]
+    #[arg(long, value_enum, default_value_t = OutputFormat::Json)]
+    pub output_format: OutputFormat,
+    /// Output file. If not given, the result is printed.
+    #[arg(short, long)]
+    pub output: Option<String>,
     /// Execution configuration parameters.
     #[command(flatten)]
     pub config: ExtraArgs,
@@ -37,19 +200,24 @@ pub struct SolveCommand {
 
 #[derive(Parser, Debug, Clone)]
 pub struct TranslateCommand {
-    /// Input file containing the fault tree in GALILEO format.
-    #[arg(short, long, required = true)]
-    pub input: String,
+    /// Input model.
+    #[command(flatten)]
+    pub model: InputArgs,
     /// Output file, writes a .wcnf file.
     #[arg(short, long)]
     pub output: String,
     /// If provided, the weights will be written to a separate file.
     #[arg(long)]
     pub w_file: Option<String>,
+    /// Write the weights of each timepoint of the range `[start, end, step]`, including the end.
+    /// The formula is the same for all of them.
+    #[arg(short = 't', long, num_args = 3, value_names = ["START", "END", "STEP"], conflicts_with = "timepoint")]
+    pub timebounds: Option<Vec<f64>>,
     /// Compute TEP of the FT a given timepoint.
-    #[arg(short, long, default_value_t = 1.0)]
+    #[arg(long, default_value_t = 1.0)]
     pub timepoint: f64,
-    /// Computes the unavailability.
+    /// Computes the unavailability, using the repairs of the basic events: monitored (`repair=`)
+    /// or periodically tested (`interval=` and `repair_time=`).
     /// See Table XI-2 of the Fault Tree Handbook. U.S. Nuclear Regulatory Commission (1981) for more information.
     #[arg(long, default_value_t = false)]
     pub unavailability: bool,
@@ -60,9 +228,9 @@ pub struct TranslateCommand {
 
 #[derive(Parser, Debug, Clone)]
 pub struct ModCommand {
-    /// Input file containing the fault tree in GALILEO format.
-    #[arg(short, long, required = true)]
-    pub input: String,
+    /// Input model.
+    #[command(flatten)]
+    pub model: InputArgs,
     /// Solver path and arguments.
     #[arg(short, long)]
     pub solver_path: String,
@@ -76,15 +244,201 @@ pub struct ModCommand {
 
 #[derive(Parser, Debug, Clone)]
 pub struct ImportanceCommand {
-    /// Input file containing the fault tree in GALILEO format.
-    #[arg(short, long, required = true)]
-    pub input: String,
+    /// Input model.
+    #[command(flatten)]
+    pub model: InputArgs,
     /// Solver path and arguments.
     #[arg(short, long)]
     pub solver_path: String,
     /// Timepoint to compute the true TEP and the measures for each basic event.
     #[arg(short, long, default_value_t = 1.0)]
     pub timepoint: f64,
+    /// Use the unavailability of the basic events instead of the unreliability.
+    #[arg(long, default_value_t = false)]
+    pub unavailability: bool,
+    /// How the conditioned TEPs of the basic events are computed. [default: conditioning]
+    #[arg(long, value_enum, default_value_t = ImportanceMode::Conditioning)]
+    pub mode: ImportanceMode,
+    /// Measures to output, separated by commas.
+    #[arg(
+        short,
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = [
+            ImportanceMeasure::Birnbaum,
+            ImportanceMeasure::ImprovementPotential,
+            ImportanceMeasure::Criticality,
+            ImportanceMeasure::Raw,
+            ImportanceMeasure::Rrw,
+        ]
+    )]
+    pub measures: Vec<ImportanceMeasure>,
+    /// Execution configuration parameters.
+    #[command(flatten)]
+    pub config: ExtraArgs,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct DotCommand {
+    /// Input model.
+    #[command(flatten)]
+    pub model: InputArgs,
+    /// Output file, writes a .dot file.
+    #[arg(short, long, required = true)]
+    pub output: String,
+    /// Highlight the gates that are the root of a module. [default: false]
+    #[arg(short, long, default_value_t = false)]
+    pub modularize: bool,
+    /// Colour the basic events by their Criticality Measure. Requires a solver. [default: false]
+    #[arg(long, default_value_t = false, requires = "solver_path")]
+    pub importance: bool,
+    /// Annotate each gate with its probability of failure. Requires a solver. [default: false]
+    #[arg(long, default_value_t = false, requires = "solver_path")]
+    pub probabilities: bool,
+    /// Solver path, used to compute the importance measures and the gate probabilities.
+    #[arg(short, long)]
+    pub solver_path: Option<String>,
+    /// Timepoint to compute the importance measures and the gate probabilities.
+    #[arg(short, long, default_value_t = 1.0)]
+    pub timepoint: f64,
+    /// Execution configuration parameters.
+    #[command(flatten)]
+    pub config: ExtraArgs,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct ConvertCommand {
+    /// Input model.
+    #[command(flatten)]
+    pub model: InputArgs,
+    /// Output file. Use the extension .dft for GALILEO, .xml or .opsa for Open-PSA MEF, or .json.
+    #[arg(short, long, required = true)]
+    pub output: String,
+    /// Simplify the FT by removing one children gates. [default: false]
+    #[arg(short, long, default_value_t = false)]
+    pub simplify: bool,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct CutSetsCommand {
+    /// Input model.
+    #[command(flatten)]
+    pub model: InputArgs,
+    /// Timepoint to compute the probability of the cut sets.
+    #[arg(short, long, default_value_t = 1.0)]
+    pub timepoint: f64,
+    /// Use the unavailability of the basic events instead of the unreliability.
+    #[arg(long, default_value_t = false)]
+    pub unavailability: bool,
+    /// Maximum number of basic events in a cut set.
+    #[arg(long)]
+    pub max_order: Option<usize>,
+    /// Drop the cut sets with a probability lower than this value.
+    #[arg(long)]
+    pub cutoff: Option<f64>,
+    /// Only report the N most probable cut sets.
+    #[arg(long, value_name = "N")]
+    pub top: Option<usize>,
+    /// Format of the output. The approximations of the TEP are only given in JSON. [default: json]
+    #[arg(long, value_enum, default_value_t = OutputFormat::Json)]
+    pub output_format: OutputFormat,
+    /// Output file. If not given, the cut sets are printed.
+    #[arg(short, long)]
+    pub output: Option<String>,
+    /// Solver path, to compare the rare-event approximation and the min-cut upper bound with the exact TEP.
+    #[arg(short, long)]
+    pub solver_path: Option<String>,
+    /// Execution configuration parameters.
+    #[command(flatten)]
+    pub config: ExtraArgs,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct UncertaintyCommand {
+    /// Input model.
+    #[command(flatten)]
+    pub model: InputArgs,
+    /// Solver path and arguments.
+    #[arg(short, long)]
+    pub solver_path: String,
+    /// Timepoint to compute the TEP of each sample.
+    #[arg(short, long, default_value_t = 1.0)]
+    pub timepoint: f64,
+    /// Computes the unavailability instead of the unreliability.
+    #[arg(long, default_value_t = false)]
+    pub unavailability: bool,
+    /// Number of parameter sets sampled. [default: 1000]
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))]
+    pub samples: u64,
+    /// Sampling of the uncertain parameters. [default: lhs]
+    #[arg(long, value_enum, default_value_t = SamplingMethod::Lhs)]
+    pub sampling: SamplingMethod,
+    /// Seed of the random generator. If not given, a random one is used and reported.
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Number of bins of the histogram of the TEP. [default: 20]
+    #[arg(long, default_value_t = 20, value_parser = clap::value_parser!(u64).range(1..))]
+    pub bins: u64,
+    /// Output file. If not given, the result is printed.
+    #[arg(short, long)]
+    pub output: Option<String>,
+    /// Execution configuration parameters.
+    #[command(flatten)]
+    pub config: ExtraArgs,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct SobolCommand {
+    /// Input model.
+    #[command(flatten)]
+    pub model: InputArgs,
+    /// Solver path and arguments.
+    #[arg(short, long)]
+    pub solver_path: String,
+    /// Timepoint to compute the TEP of each sample.
+    #[arg(short, long, default_value_t = 1.0)]
+    pub timepoint: f64,
+    /// Computes the unavailability instead of the unreliability.
+    #[arg(long, default_value_t = false)]
+    pub unavailability: bool,
+    /// Number of rows of each Saltelli matrix. The TEP is computed samples * (uncertain events + 2) times. [default: 1000]
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u64).range(2..))]
+    pub samples: u64,
+    /// Sampling of the uncertain parameters. [default: lhs]
+    #[arg(long, value_enum, default_value_t = SamplingMethod::Lhs)]
+    pub sampling: SamplingMethod,
+    /// Seed of the random generator. If not given, a random one is used and reported.
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Output file. If not given, the result is printed.
+    #[arg(short, long)]
+    pub output: Option<String>,
+    /// Execution configuration parameters.
+    #[command(flatten)]
+    pub config: ExtraArgs,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct SensitivityCommand {
+    /// Input model.
+    #[command(flatten)]
+    pub model: InputArgs,
+    /// Solver path and arguments.
+    #[arg(short, long)]
+    pub solver_path: String,
+    /// Timepoint to compute the TEP and its derivatives.
+    #[arg(short, long, default_value_t = 1.0)]
+    pub timepoint: f64,
+    /// Use the unavailability of the basic events instead of the unreliability.
+    #[arg(long, default_value_t = false)]
+    pub unavailability: bool,
+    /// Format of the output. [default: json]
+    #[arg(long, value_enum, default_value_t = OutputFormat::Json)]
+    pub output_format: OutputFormat,
+    /// Output file. If not given, the sensitivities are printed.
+    #[arg(short, long)]
+    pub output: Option<String>,
     /// Execution configuration parameters.
     #[command(flatten)]
     pub config: ExtraArgs,
@@ -92,8 +446,8 @@ pub struct ImportanceCommand {
 
 #[derive(Parser, Debug, Clone)]
 pub struct ExtraArgs {
-    /// Max cache size to distribute between the threads in KB. [default: 3500]
-    #[arg(long, default_value_t = 3500)]
+    /// Max cache size to distribute between the threads in KB, 3500 MB by default. [default: 3584000]
+    #[arg(long, default_value_t = 3500 * 1024)]
     pub max_cache_size: usize,
     /// Negate top gate if is an OR, to favor UnitPropagation. [default: false]
     #[arg(short, long, default_value_t = false)]
@@ -121,6 +475,13 @@ pub struct ExtraArgs {
     pub preprocess: Option<String>,
 }
 
+impl ExtraArgs {
+    /// Cache size of each of the `threads` in MB, the unit of the solvers.
+    pub fn cache_size_mb(&self, threads: usize) -> usize {
+        (self.max_cache_size / 1024 / threads).max(1)
+    }
+}
+
 /// CMD Arguments
 #[derive(Parser, Debug, Clone)]
 pub struct RandomGenerationCommand {
@@ -0,0 +1,364 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+use crate::fault_tree::FaultTree;
+use crate::nodes::{Node, NodeId};
+use crate::solver::TIMEOUT;
+
+/// Reference to a node of a BDD. The terminals are [`FALSE`] and [`TRUE`].
+pub type BddRef = usize;
+
+pub const FALSE: BddRef = 0;
+pub const TRUE: BddRef = 1;
+
+/// Level of the terminals, below every variable.
+const TERMINAL_LEVEL: usize = usize::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Op {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+/// Heuristic to order the variables of the BDD, both start with a DFS from the top event
+/// and give the basic events their level in the order they are first visited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VariableOrdering {
+    /// Visit the children of each gate in the order they are given.
+    #[default]
+    Dfs,
+    /// Visit first the children with less basic events below them, so the small subtrees
+    /// get their variables close together.
+    Weight,
+}
+
+/// Reduced Ordered Binary Decision Diagram of the structure function of a FT.
+/// Each variable is a basic event, and level `i` is the basic event `vars[i]`.
+#[derive(Debug, Clone)]
+pub struct Bdd {
+    /// Level, low child and high child of each node. Children are always created before their parents.
+    nodes: Vec<(usize, BddRef, BddRef)>,
+    unique: HashMap<(usize, BddRef, BddRef), BddRef>,
+    cache: HashMap<(Op, BddRef, BddRef), BddRef>,
+    pub vars: Vec<NodeId>,
+    pub root: BddRef,
+}
+
+/// Number of different basic events below each node reachable from `nid`.
+fn weights(ft: &FaultTree<String>, nid: NodeId, memo: &mut HashMap<NodeId, HashSet<NodeId>>) {
+    if memo.contains_key(&nid) {
+        return;
+    }
+    let bes = match &ft.nodes[nid] {
+        Node::BasicEvent(_, _) => HashSet::from([nid]),
+        node => {
+            let mut bes = HashSet::new();
+            for child in node.children() {
+                weights(ft, child, memo);
+                bes.extend(memo[&child].iter().copied());
+            }
+            bes
+        }
+    };
+    memo.insert(nid, bes);
+}
+
+impl Bdd {
+    fn new(vars: Vec<NodeId>) -> Self {
+        Bdd {
+            nodes: vec![(TERMINAL_LEVEL, FALSE, FALSE), (TERMINAL_LEVEL, TRUE, TRUE)],
+            unique: HashMap::new(),
+            cache: HashMap::new(),
+            vars,
+            root: FALSE,
+        }
+    }
+
+    /// Order the basic events reachable from the top event of the FT.
+    pub fn variable_order(ft: &FaultTree<String>, ordering: VariableOrdering) -> Vec<NodeId> {
+        let mut memo = HashMap::new();
+        if ordering == VariableOrdering::Weight {
+            weights(ft, ft.root_id, &mut memo);
+        }
+        let mut order = vec![];
+        let mut seen = HashSet::new();
+        let mut to_visit = vec![ft.root_id];
+        while let Some(nid) = to_visit.pop() {
+            if !seen.insert(nid) {
+                continue;
+            }
+            let node = &ft.nodes[nid];
+            if let Node::BasicEvent(_, _) = node {
+                order.push(nid);
+                continue;
+            }
+            let mut children = node.children();
+            if ordering == VariableOrdering::Weight {
+                children.sort_by_key(|c| memo[c].len());
+            }
+            // Reversed so the first child is visited first.
+            to_visit.extend(children.into_iter().rev());
+        }
+        order
+    }
+
+    /// Build the BDD of the top event of the FT. Returns an error if the deadline is reached.
+    pub fn from_fault_tree(
+        ft: &FaultTree<String>,
+        ordering: VariableOrdering,
+        deadline: Option<Instant>,
+    ) -> Result<Self, &'static str> {
+        let vars = Bdd::variable_order(ft, ordering);
+        let levels: HashMap<NodeId, usize> =
+            vars.iter().enumerate().map(|(i, nid)| (*nid, i)).collect();
+        let mut bdd = Bdd::new(vars);
+        let mut built: HashMap<NodeId, BddRef> = HashMap::new();
+
+        // Post order traversal, so the children are built before their gate.
+        let mut to_visit = vec![(ft.root_id, false)];
+        while let Some((nid, expanded)) = to_visit.pop() {
+            if built.contains_key(&nid) {
+                continue;
+            }
+            let node = &ft.nodes[nid];
+            if !expanded && node.is_gate() {
+                to_visit.push((nid, true));
+                to_visit.extend(node.children().into_iter().map(|c| (c, false)));
+                continue;
+            }
+            if deadline.is_some_and(|d| Instant::now() > d) {
+                return Err(TIMEOUT);
+            }
+            let args = node.children().iter().map(|c| built[c]).collect::<Vec<_>>();
+            let f = match node {
+                Node::BasicEvent(_, _) => bdd.mk(levels[&nid], FALSE, TRUE),
+                Node::Not(_) => bdd.not(args[0]),
+                Node::And(_) => args.into_iter().fold(TRUE, |acc, a| bdd.and(acc, a)),
+                Node::Or(_) => args.into_iter().fold(FALSE, |acc, a| bdd.or(acc, a)),
+                Node::Xor(_) => args.into_iter().fold(FALSE, |acc, a| bdd.xor(acc, a)),
+                Node::Vot(k, _) => bdd.at_least(*k as usize, &args),
+                Node::PlaceHolder(_, _, _) => panic!("Cant build a BDD of a placeholder node."),
+            };
+            built.insert(nid, f);
+        }
+        bdd.root = built[&ft.root_id];
+        bdd.cache.clear();
+        Ok(bdd)
+    }
+
+    /// Number of nodes created, including the terminals and the intermediate results.
+    pub fn size(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Level, low child and high child of a node.
+    pub fn node(&self, f: BddRef) -> (usize, BddRef, BddRef) {
+        self.nodes[f]
+    }
+
+    fn level(&self, f: BddRef) -> usize {
+        self.nodes[f].0
+    }
+
+    /// Get or create the node `ite(level, high, low)`, keeping the BDD reduced.
+    fn mk(&mut self, level: usize, low: BddRef, high: BddRef) -> BddRef {
+        if low == high {
+            return low;
+        }
+        if let Some(f) = self.unique.get(&(level, low, high)) {
+            return *f;
+        }
+        let f = self.nodes.len();
+        self.nodes.push((level, low, high));
+        self.unique.insert((level, low, high), f);
+        f
+    }
+
+    /// Cofactors of `f` with respect to the variable at `level`.
+    fn cofactors(&self, f: BddRef, level: usize) -> (BddRef, BddRef) {
+        let (l, low, high) = self.nodes[f];
+        if l == level { (low, high) } else { (f, f) }
+    }
+
+    pub fn not(&mut self, f: BddRef) -> BddRef {
+        match f {
+            FALSE => return TRUE,
+            TRUE => return FALSE,
+            _ => {}
+        }
+        if let Some(r) = self.cache.get(&(Op::Not, f, f)) {
+            return *r;
+        }
+        let (level, low, high) = self.nodes[f];
+        let (low, high) = (self.not(low), self.not(high));
+        let r = self.mk(level, low, high);
+        self.cache.insert((Op::Not, f, f), r);
+        r
+    }
+
+    pub fn and(&mut self, f: BddRef, g: BddRef) -> BddRef {
+        self.apply(Op::And, f, g)
+    }
+
+    pub fn or(&mut self, f: BddRef, g: BddRef) -> BddRef {
+        self.apply(Op::Or, f, g)
+    }
+
+    pub fn xor(&mut self, f: BddRef, g: BddRef) -> BddRef {
+        self.apply(Op::Xor, f, g)
+    }
+
+    /// BDD that is true when at least `k` of the arguments are true.
+    pub fn at_least(&mut self, k: usize, args: &[BddRef]) -> BddRef {
+        // at_least[j] holds for the arguments processed so far.
+        let mut at_least = vec![FALSE; k + 1];
+        at_least[0] = TRUE;
+        for a in args {
+            for j in (1..=k).rev() {
+                let with_a = self.and(*a, at_least[j - 1]);
+                at_least[j] = self.or(at_least[j], with_a);
+            }
+        }
+        at_least[k]
+    }
+
+    fn apply(&mut self, op: Op, f: BddRef, g: BddRef) -> BddRef {
+        match (op, f, g) {
+            (Op::And, FALSE, _) | (Op::And, _, FALSE) => return FALSE,
+            (Op::And, TRUE, x) | (Op::And, x, TRUE) => return x,
+            (Op::Or, TRUE, _) | (Op::Or, _, TRUE) => return TRUE,
+            (Op::Or, FALSE, x) | (Op::Or, x, FALSE) => return x,
+            (Op::Xor, FALSE, x) | (Op::Xor, x, FALSE) => return x,
+            (Op::Xor, TRUE, x) | (Op::Xor, x, TRUE) => return self.not(x),
+            (Op::Xor, _, _) if f == g => return FALSE,
+            (_, _, _) if f == g => return f,
+            _ => {}
+        }
+        // All the operations are commutative.
+        let key = (op, f.min(g), f.max(g));
+        if let Some(r) = self.cache.get(&key) {
+            return *r;
+        }
+        let level = self.level(f).min(self.level(g));
+        let (f_low, f_high) = self.cofactors(f, level);
+        let (g_low, g_high) = self.cofactors(g, level);
+        let low = self.apply(op, f_low, g_low);
+        let high = self.apply(op, f_high, g_high);
+        let r = self.mk(level, low, high);
+        self.cache.insert(key, r);
+        r
+    }
+
+    /// Probability of each node, by Shannon expansion: `P(f) = p * P(high) + (1 - p) * P(low)`,
+    /// where `probs[i]` is the probability of the variable at level `i`.
+    pub fn node_probabilities(&self, probs: &[f64]) -> Vec<f64> {
+        let mut p = vec![0.0; self.nodes.len()];
+        p[TRUE] = 1.0;
+        for (f, (level, low, high)) in self.nodes.iter().enumerate().skip(2) {
+            let q = probs[*level];
+            p[f] = q * p[*high] + (1.0 - q) * p[*low];
+        }
+        p
+    }
+
+    /// Probability of the top event.
+    pub fn probability(&self, probs: &[f64]) -> f64 {
+        self.node_probabilities(probs)[self.root]
+    }
+
+    /// Derivative of the probability of the top event with respect to the probability of the
+    /// variable at each level, in a single backward pass from the root. The derivative with
+    /// respect to the probability of each node is accumulated from its parents, and the node adds
+    /// `d(f) * (P(high) - P(low))` to the derivative of its variable.
+    pub fn derivatives(&self, probs: &[f64]) -> Vec<f64> {
+        let p = self.node_probabilities(probs);
+        let mut d = vec![0.0; self.nodes.len()];
+        d[self.root] = 1.0;
+        let mut derivatives = vec![0.0; self.vars.len()];
+        // Children are created before their parents, so the parents are visited first.
+        for f in (2..=self.root).rev() {
+            if d[f] == 0.0 {
+                continue;
+            }
+            let (level, low, high) = self.nodes[f];
+            let q = probs[level];
+            derivatives[level] += d[f] * (p[high] - p[low]);
+            d[high] += d[f] * q;
+            d[low] += d[f] * (1.0 - q);
+        }
+        derivatives
+    }
+
+    /// Probability of failure of each variable at the given timepoint.
+    pub fn var_probabilities(
+        &self,
+        ft: &FaultTree<String>,
+        timepoint: f64,
+        unav: bool,
+    ) -> Vec<f64> {
+        self.vars
+            .iter()
+            .map(|nid| {
+                let node = &ft.nodes[*nid];
+                if unav {
+                    node.unavailability(timepoint)
+                } else {
+                    node.unreliability(timepoint)
+                }
+                .expect("Variables of the BDD are basic events")
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::ft_from_dft;
+
+    /// TEP of the 2of3 gate, that contains the AND: `ab + ac + bc - 2abc = 0.098`.
+    const VOTING: &str = r#"toplevel "T";
+"T" or "G" "H";
+"G" and "A" "B";
+"H" 2of3 "A" "B" "C";
+"A" prob=0.1;
+"B" prob=0.2;
+"C" prob=0.3;
+"#;
+
+    #[test]
+    fn probability_of_voting_gate() {
+        let ft = ft_from_dft("coyan_bdd_voting.dft", VOTING);
+        for ordering in [VariableOrdering::Dfs, VariableOrdering::Weight] {
+            let bdd = Bdd::from_fault_tree(&ft, ordering, None).unwrap();
+            let probs = bdd.var_probabilities(&ft, 1.0, false);
+            assert!((bdd.probability(&probs) - 0.098).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn derivatives_are_birnbaum_measures() {
+        let ft = ft_from_dft("coyan_bdd_derivatives.dft", VOTING);
+        let bdd = Bdd::from_fault_tree(&ft, VariableOrdering::Dfs, None).unwrap();
+        let derivatives = bdd.derivatives(&bdd.var_probabilities(&ft, 1.0, false));
+        // dTEP/da = b + c - 2bc, and the same for the others.
+        let expected = HashMap::from([("A", 0.38), ("B", 0.34), ("C", 0.26)]);
+        for (nid, d) in bdd.vars.iter().zip(derivatives) {
+            assert!((expected[ft.name_of(*nid).as_str()] - d).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn probability_with_xor_and_not() {
+        // P(A xor not B) = ab + (1 - a)(1 - b) = 0.74.
+        let ft = ft_from_dft(
+            "coyan_bdd_xor.dft",
+            "toplevel \"T\";\n\"T\" xor \"A\" \"N\";\n\"N\" not \"B\";\n\"A\" prob=0.1;\n\"B\" prob=0.2;\n",
+        );
+        let bdd = Bdd::from_fault_tree(&ft, VariableOrdering::Dfs, None).unwrap();
+        let probs = bdd.var_probabilities(&ft, 1.0, false);
+        assert!((bdd.probability(&probs) - 0.74).abs() < 1e-12);
+    }
+}
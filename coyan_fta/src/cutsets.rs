@@ -0,0 +1,432 @@
+use itertools::Itertools;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::fault_tree::FaultTree;
+use crate::nodes::{Node, NodeId};
+
+/// Minimal cut set of the FT: a minimal set of basic events whose failure makes the top event fail.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CutSet {
+    /// Names of the basic events, sorted.
+    pub events: Vec<String>,
+    /// Probability that all the basic events of the cut set fail.
+    pub probability: f64,
+}
+
+impl CutSet {
+    /// Number of basic events of the cut set.
+    pub fn order(&self) -> usize {
+        self.events.len()
+    }
+}
+
+/// Approximations of the TEP from the minimal cut sets, that need no model counter.
+/// Both are upper bounds of the TEP of a coherent FT when all the cut sets are given,
+/// and are close to it when the probabilities of the cut sets are small.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct CutSetApproximations {
+    /// Rare-event approximation, the sum of the probabilities of the cut sets.
+    pub rare_event: f64,
+    /// Esary-Proschan min-cut upper bound, `1 - prod(1 - P(C))` over the cut sets.
+    pub min_cut_upper_bound: f64,
+}
+
+impl CutSetApproximations {
+    pub fn from_cut_sets(cut_sets: &[CutSet]) -> Self {
+        let rare_event = cut_sets.iter().map(|cs| cs.probability).sum();
+        // Computed in log scale, to not lose the small probabilities.
+        let log_none_fails: f64 = cut_sets.iter().map(|cs| (-cs.probability).ln_1p()).sum();
+        CutSetApproximations {
+            rare_event,
+            min_cut_upper_bound: -log_none_fails.exp_m1(),
+        }
+    }
+}
+
+/// Fussell-Vesely importance of each basic event: the probability that at least one of the
+/// cut sets that contain it fails, over the probability that any cut set fails. Both are given
+/// by the min-cut upper bound, so the measure is at most 1. Note that `(TEP - TEP(p=0)) / TEP`
+/// is not used, as it is the same as the Criticality Measure.
+pub fn fussell_vesely(cut_sets: &[CutSet]) -> HashMap<String, f64> {
+    let top = CutSetApproximations::from_cut_sets(cut_sets).min_cut_upper_bound;
+    let mut containing: HashMap<String, Vec<CutSet>> = HashMap::new();
+    for cs in cut_sets {
+        for event in &cs.events {
+            containing
+                .entry(event.to_owned())
+                .or_default()
+                .push(cs.clone());
+        }
+    }
+    containing
+        .into_iter()
+        .map(|(event, cut_sets)| {
+            let upper_bound = CutSetApproximations::from_cut_sets(&cut_sets).min_cut_upper_bound;
+            (event, upper_bound / top)
+        })
+        .collect()
+}
+
+/// Truncation of the minimal cut sets. Each cut set of a gate is contained in the cut sets
+/// of the top event that use it, which have a higher order and a lower probability,
+/// so the truncation can be applied at every gate.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CutSetLimits {
+    /// Maximum number of basic events of a cut set.
+    pub max_order: Option<usize>,
+    /// Cut sets with a lower probability are dropped.
+    pub cutoff: Option<f64>,
+}
+
+/// Set of basic events, sorted, with the probability of all of them failing.
+type Family = Vec<(Vec<NodeId>, f64)>;
+
+/// Helper struct to compute the cut sets of each gate, from the basic events up.
+struct CutSetBuilder<'a> {
+    probs: &'a HashMap<NodeId, f64>,
+    limits: CutSetLimits,
+}
+
+impl CutSetBuilder<'_> {
+    fn keep(&self, order: usize, prob: f64) -> bool {
+        self.limits.max_order.is_none_or(|max| order <= max)
+            && self.limits.cutoff.is_none_or(|cutoff| prob >= cutoff)
+    }
+
+    /// Cut sets of an OR gate.
+    fn union(&self, families: Vec<Family>) -> Family {
+        minimize(families.into_iter().flatten().collect())
+    }
+
+    /// Cut sets of an AND gate of two arguments. A cut set that contains one of the other
+    /// argument is a cut set of the product, and its unions with the rest contain it, so they
+    /// are not built. The limits are checked before the other unions are built.
+    fn product(&self, lhs: &Family, rhs: &Family) -> Family {
+        let (lhs_absorbing, lhs_rest): (Vec<_>, Vec<_>) = lhs
+            .iter()
+            .partition(|(l, _)| rhs.iter().any(|(r, _)| is_subset(r, l)));
+        let (rhs_absorbing, rhs_rest): (Vec<_>, Vec<_>) = rhs
+            .iter()
+            .partition(|(r, _)| lhs.iter().any(|(l, _)| is_subset(l, r)));
+        let mut family = lhs_absorbing
+            .into_iter()
+            .chain(rhs_absorbing)
+            .cloned()
+            .collect_vec();
+        let mut seen: HashSet<Vec<NodeId>> = HashSet::new();
+        for (l, _) in &lhs_rest {
+            for (r, _) in &rhs_rest {
+                let (order, prob) = l
+                    .iter()
+                    .merge(r)
+                    .dedup()
+                    .fold((0, 1.0), |(order, prob), e| {
+                        (order + 1, prob * self.probs[e])
+                    });
+                if !self.keep(order, prob) {
+                    continue;
+                }
+                let events = l.iter().merge(r).dedup().copied().collect_vec();
+                if seen.insert(events.clone()) {
+                    family.push((events, prob));
+                }
+            }
+        }
+        minimize(family)
+    }
+
+    /// Cut sets of a VOT gate, where at least `k` of the arguments fail.
+    fn at_least(&self, k: usize, args: &[Family]) -> Family {
+        // at_least[j] holds for the arguments processed so far.
+        let mut at_least: Vec<Family> = vec![vec![]; k + 1];
+        at_least[0] = vec![(vec![], 1.0)];
+        for arg in args {
+            for j in (1..=k).rev() {
+                let with_arg = self.product(&at_least[j - 1], arg);
+                at_least[j] = self.union(vec![std::mem::take(&mut at_least[j]), with_arg]);
+            }
+        }
+        std::mem::take(&mut at_least[k])
+    }
+}
+
+/// Whether the sorted basic events of `small` are all in `big`.
+fn is_subset(small: &[NodeId], big: &[NodeId]) -> bool {
+    small.len() <= big.len() && small.iter().all(|e| big.binary_search(e).is_ok())
+}
+
+/// Remove the repeated cut sets and the ones that contain another cut set.
+fn minimize(mut family: Family) -> Family {
+    family.sort_by(|(a, _), (b, _)| a.len().cmp(&b.len()).then(a.cmp(b)));
+    family.dedup_by(|(a, _), (b, _)| a == b);
+    if family.first().is_some_and(|(events, _)| events.is_empty()) {
+        // The empty cut set is contained in every other one.
+        family.truncate(1);
+        return family;
+    }
+    // Positions in `minimal` of the cut sets by their least frequent basic event, as a cut set
+    // can only contain the ones whose least frequent basic event is one of its own.
+    let mut frequency: HashMap<NodeId, usize> = HashMap::new();
+    for e in family.iter().flat_map(|(events, _)| events) {
+        *frequency.entry(*e).or_default() += 1;
+    }
+    let mut by_rarest: HashMap<NodeId, Vec<usize>> = HashMap::new();
+    let mut minimal: Family = vec![];
+    for (events, prob) in family {
+        let contains_other = events.iter().any(|e| {
+            by_rarest
+                .get(e)
+                .is_some_and(|others| others.iter().any(|&i| is_subset(&minimal[i].0, &events)))
+        });
+        if !contains_other {
+            let rarest = events.iter().min_by_key(|e| frequency[e]).unwrap();
+            by_rarest.entry(*rarest).or_default().push(minimal.len());
+            minimal.push((events, prob));
+        }
+    }
+    minimal
+}
+
+impl FaultTree<String> {
+    /// Compute the minimal cut sets of the top event, sorted from the most to the least probable.
+    /// The cut sets are built from the basic events up, in the style of MOCUS, and truncated at
+    /// each gate with the given limits. Only coherent FTs have cut sets, so it fails on NOT and XOR gates.
+    pub fn minimal_cut_sets(
+        &self,
+        timepoint: f64,
+        unav: bool,
+        limits: CutSetLimits,
+    ) -> Result<Vec<CutSet>, String> {
+        let probs: HashMap<NodeId, f64> = self
+            .nodes
+            .iter_enumerated()
+            .filter_map(|(nid, n)| {
+                if unav {
+                    n.unavailability(timepoint)
+                } else {
+                    n.unreliability(timepoint)
+                }
+                .filter(|_| !n.is_gate())
+                .map(|p| (nid, p))
+            })
+            .collect();
+        let builder = CutSetBuilder {
+            probs: &probs,
+            limits,
+        };
+
+        // Post order traversal, so the children are computed before their gate.
+        let mut families: HashMap<NodeId, Family> = HashMap::new();
+        let mut expanded: HashSet<NodeId> = HashSet::new();
+        let mut to_visit = vec![self.root_id];
+        while let Some(nid) = to_visit.pop() {
+            if families.contains_key(&nid) {
+                continue;
+            }
+            let node = &self.nodes[nid];
+            if node.is_gate() && expanded.insert(nid) {
+                to_visit.push(nid);
+                to_visit.extend(node.children());
+                continue;
+            }
+            let args = node
+                .children()
+                .iter()
+                .map(|c| families[c].clone())
+                .collect_vec();
+            let family = match node {
+                Node::BasicEvent(_, _) => {
+                    let prob = probs[&nid];
+                    if builder.keep(1, prob) {
+                        vec![(vec![nid], prob)]
+                    } else {
+                        vec![]
+                    }
+                }
+                Node::Or(_) => builder.union(args),
+                Node::And(_) => args
+                    .iter()
+                    .fold(vec![(vec![], 1.0)], |acc, arg| builder.product(&acc, arg)),
+                Node::Vot(k, _) => builder.at_least(*k as usize, &args),
+                Node::Not(_) | Node::Xor(_) => {
+                    return Err(format!(
+                        "minimal cut sets are only defined for coherent FTs, found the {} gate '{}'",
+                        node.gate_type(),
+                        self.name_of(nid)
+                    ));
+                }
+                Node::PlaceHolder(_, _, _) => {
+                    panic!("Cant compute cut sets of a placeholder node.")
+                }
+            };
+            families.insert(nid, family);
+        }
+
+        let cut_sets = families
+            .remove(&self.root_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(events, probability)| CutSet {
+                events: events
+                    .iter()
+                    .map(|nid| match &self.nodes[*nid] {
+                        Node::BasicEvent(name, _) => name.to_owned(),
+                        _ => self.name_of(*nid),
+                    })
+                    .sorted()
+                    .collect(),
+                probability,
+            })
+            .sorted_by(|a, b| {
+                b.probability
+                    .total_cmp(&a.probability)
+                    .then(a.order().cmp(&b.order()))
+                    .then(a.events.cmp(&b.events))
+            })
+            .collect();
+        Ok(cut_sets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::ft_from_dft;
+
+    const VOTING: &str = r#"toplevel "T";
+"T" or "G" "H";
+"G" and "A" "B";
+"H" 2of3 "A" "B" "C";
+"A" prob=0.1;
+"B" prob=0.2;
+"C" prob=0.3;
+"#;
+
+    fn events(cut_sets: &[CutSet]) -> Vec<Vec<&str>> {
+        cut_sets
+            .iter()
+            .map(|cs| cs.events.iter().map(String::as_str).collect())
+            .collect()
+    }
+
+    #[test]
+    fn minimal_cut_sets_of_voting_gate() {
+        let ft = ft_from_dft("coyan_cutsets_voting.dft", VOTING);
+        let cut_sets = ft
+            .minimal_cut_sets(1.0, false, CutSetLimits::default())
+            .unwrap();
+        // The cut set of the AND gate is not minimal, it is also one of the VOT gate.
+        assert_eq!(
+            events(&cut_sets),
+            vec![vec!["B", "C"], vec!["A", "C"], vec!["A", "B"]]
+        );
+        let probabilities = cut_sets.iter().map(|cs| cs.probability).collect_vec();
+        for (p, expected) in probabilities.into_iter().zip([0.06, 0.03, 0.02]) {
+            assert!((p - expected).abs() < 1e-12);
+        }
+
+        let approximations = CutSetApproximations::from_cut_sets(&cut_sets);
+        assert!((approximations.rare_event - 0.11).abs() < 1e-12);
+        // 1 - 0.94 * 0.97 * 0.98, above the TEP 0.098.
+        assert!((approximations.min_cut_upper_bound - 0.106436).abs() < 1e-12);
+    }
+
+    #[test]
+    fn truncated_cut_sets() {
+        let ft = ft_from_dft("coyan_cutsets_truncated.dft", VOTING);
+        let by_order = CutSetLimits {
+            max_order: Some(1),
+            cutoff: None,
+        };
+        assert!(
+            ft.minimal_cut_sets(1.0, false, by_order)
+                .unwrap()
+                .is_empty()
+        );
+        let by_probability = CutSetLimits {
+            max_order: None,
+            cutoff: Some(0.025),
+        };
+        let cut_sets = ft.minimal_cut_sets(1.0, false, by_probability).unwrap();
+        assert_eq!(events(&cut_sets), vec![vec!["B", "C"], vec!["A", "C"]]);
+    }
+
+    #[test]
+    fn no_cut_sets_of_non_coherent_ft() {
+        let ft = ft_from_dft(
+            "coyan_cutsets_xor.dft",
+            "toplevel \"T\";\n\"T\" xor \"A\" \"B\";\n\"A\" prob=0.1;\n\"B\" prob=0.2;\n",
+        );
+        assert!(
+            ft.minimal_cut_sets(1.0, false, CutSetLimits::default())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn fussell_vesely_of_voting_gate() {
+        let ft = ft_from_dft("coyan_cutsets_fussell_vesely.dft", VOTING);
+        let cut_sets = ft
+            .minimal_cut_sets(1.0, false, CutSetLimits::default())
+            .unwrap();
+        let measures = fussell_vesely(&cut_sets);
+        // A is in {A, B} and {A, C}: (1 - 0.98 * 0.97) / (1 - 0.94 * 0.97 * 0.98).
+        let expected = [("A", 0.0494), ("B", 0.0788), ("C", 0.0882)];
+        for (event, numerator) in expected {
+            assert!((measures[event] - numerator / 0.106436).abs() < 1e-12);
+        }
+    }
+
+    /// T = or(P, R) with P = and(O1, O2, O3) and R = 2of3(O1, O2, O3), where each Oi is an OR
+    /// gate of `n` basic events. Every cut set of P contains one of R, so the minimal cut sets
+    /// are the `3 * n * n` pairs of basic events of different Oi.
+    fn shared_ors(n: usize) -> String {
+        let mut model = String::from("toplevel \"T\";\n\"T\" or \"P\" \"R\";\n");
+        model.push_str("\"P\" and \"O1\" \"O2\" \"O3\";\n");
+        model.push_str("\"R\" 2of3 \"O1\" \"O2\" \"O3\";\n");
+        for i in 1..=3 {
+            model.push_str(&format!("\"O{i}\" or"));
+            for j in 0..n {
+                model.push_str(&format!(" \"E{i}_{j}\""));
+            }
+            model.push_str(";\n");
+        }
+        for i in 1..=3 {
+            for j in 0..n {
+                model.push_str(&format!("\"E{i}_{j}\" prob={};\n", 0.001 * (j + 1) as f64));
+            }
+        }
+        model
+    }
+
+    #[test]
+    fn cut_sets_of_shared_gates() {
+        let n = 20;
+        let ft = ft_from_dft("coyan_cutsets_shared.dft", &shared_ors(n));
+        let cut_sets = ft
+            .minimal_cut_sets(1.0, false, CutSetLimits::default())
+            .unwrap();
+        assert_eq!(cut_sets.len(), 3 * n * n);
+        for cs in &cut_sets {
+            assert_eq!(cs.order(), 2);
+            let gates = cs.events.iter().map(|e| &e[..2]).collect_vec();
+            assert_ne!(gates[0], gates[1]);
+        }
+
+        // The same cut sets are found when the larger ones are dropped at every gate.
+        let by_order = CutSetLimits {
+            max_order: Some(2),
+            cutoff: None,
+        };
+        assert_eq!(ft.minimal_cut_sets(1.0, false, by_order).unwrap(), cut_sets);
+        let by_probability = CutSetLimits {
+            max_order: None,
+            cutoff: Some(1e-4),
+        };
+        let truncated = ft.minimal_cut_sets(1.0, false, by_probability).unwrap();
+        assert!(!truncated.is_empty() && truncated.len() < cut_sets.len());
+        assert!(truncated.iter().all(|cs| cs.probability >= 1e-4));
+        assert_eq!(truncated[..], cut_sets[..truncated.len()]);
+    }
+}
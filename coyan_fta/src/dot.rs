@@ -0,0 +1,110 @@
+use itertools::Itertools;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Write;
+
+use crate::fault_tree::FaultTree;
+use crate::nodes::{Node, NodeId};
+
+/// Analysis results that can be drawn on top of the FT.
+#[derive(Debug, Clone, Default)]
+pub struct DotOverlay {
+    /// Gates that are the root of a module. They are drawn with a bold blue border.
+    pub modules: Vec<NodeId>,
+    /// Criticality of each basic event, by name. Basic events are filled in red,
+    /// darker for the more critical ones.
+    pub criticality: HashMap<String, f64>,
+    /// Probability of failure of the gates, written in their label.
+    pub probabilities: HashMap<NodeId, f64>,
+}
+
+/// Escape the characters that are not allowed in a DOT string.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Shape and label of each type of gate.
+fn gate_style(node: &Node<String>) -> (&'static str, String) {
+    match node {
+        Node::And(_) => ("invhouse", String::from("AND")),
+        Node::Or(_) => ("invtriangle", String::from("OR")),
+        Node::Xor(_) => ("diamond", String::from("XOR")),
+        Node::Not(_) => ("invtrapezium", String::from("NOT")),
+        Node::Vot(k, args) => ("hexagon", format!("VOT {}/{}", k, args.len())),
+        Node::BasicEvent(_, _) => ("ellipse", String::new()),
+        Node::PlaceHolder(_, _, _) => panic!("Cant draw placeholder node."),
+    }
+}
+
+impl FaultTree<String> {
+    /// Write the FT in the DOT language of Graphviz. Only the nodes reachable from the top event are drawn.
+    pub fn to_dot(&self, overlay: &DotOverlay) -> String {
+        let modules: HashSet<&NodeId> = overlay.modules.iter().collect();
+        let max_criticality = overlay
+            .criticality
+            .values()
+            .filter(|c| c.is_finite())
+            .fold(0.0_f64, |acc, c| acc.max(*c));
+
+        let mut nodes = vec![];
+        let mut edges = vec![];
+        let mut seen = HashSet::from([self.root_id]);
+        let mut to_process = vec![self.root_id];
+        while let Some(nid) = to_process.pop() {
+            let node = &self.nodes[nid];
+            let name = escape(&self.name_of(nid));
+            let mut attrs = vec![];
+            match node {
+                Node::BasicEvent(_, be) => {
+                    let mut label = format!("{}\\n{}", name, be);
+                    if let Some(c) = overlay.criticality.get(&self.name_of(nid)) {
+                        label.push_str(&format!("\\nIC={:.3e}", c));
+                        let saturation = if max_criticality > 0.0 && c.is_finite() {
+                            c / max_criticality
+                        } else {
+                            0.0
+                        };
+                        attrs.push(String::from("style=filled"));
+                        attrs.push(format!("fillcolor=\"0.0 {:.3} 1.0\"", saturation));
+                    }
+                    attrs.push(String::from("shape=ellipse"));
+                    attrs.push(format!("label=\"{}\"", label));
+                }
+                _ => {
+                    let (shape, gate_type) = gate_style(node);
+                    let mut label = format!("{}\\n{}", name, gate_type);
+                    if let Some(p) = overlay.probabilities.get(&nid) {
+                        label.push_str(&format!("\\nP={:.3e}", p));
+                    }
+                    if modules.contains(&nid) {
+                        attrs.push(String::from("color=blue"));
+                        attrs.push(String::from("penwidth=3"));
+                    }
+                    attrs.push(format!("shape={}", shape));
+                    attrs.push(format!("label=\"{}\"", label));
+                }
+            }
+            nodes.push(format!("  n{} [{}];", nid, attrs.join(", ")));
+
+            for child in node.children() {
+                edges.push(format!("  n{} -> n{};", nid, child));
+                if seen.insert(child) {
+                    to_process.push(child);
+                }
+            }
+        }
+
+        format!(
+            "digraph FaultTree {{\n  rankdir=TB;\n  node [fontsize=10];\n{}\n{}\n}}\n",
+            nodes.iter().join("\n"),
+            edges.iter().join("\n")
+        )
+    }
+
+    /// Save the FT as a DOT file.
+    pub fn save_to_dot(&self, filename: &str, overlay: &DotOverlay) {
+        let mut f = File::create(filename).expect("unable to create file");
+        f.write_all(self.to_dot(overlay).as_bytes())
+            .expect("Error writing the DOT graph to file");
+    }
+}
@@ -0,0 +1,151 @@
+use itertools::Itertools;
+use std::fmt::{self, Display};
+
+/// Position of a token inside a model file. Both values start at 1.
+/// A zero position means that the element was not read from a file (e.g. generated trees).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(line: usize, column: usize) -> Self {
+        Span { line, column }
+    }
+}
+
+/// The different problems that can be found while reading a model.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    /// The file could not be read.
+    Io(String),
+    /// The statement does not follow the expected syntax.
+    Syntax(String),
+    /// Gate type unknown to the parser.
+    UnknownGate(String),
+    /// Gate type that is known, but not supported in a static FT.
+    UnsupportedGate(String),
+    /// Attribute of a basic event that is not supported.
+    UnknownAttribute(String),
+    /// Attribute of a basic event with a value that is not valid, or missing the attributes it depends on.
+    InvalidAttribute(String),
+    /// Attribute of a basic event that is known, but whose value has no meaning in a static FT.
+    UnsupportedAttribute(String),
+    /// Value that could not be read as a number.
+    InvalidNumber(String),
+    /// Name already used by another gate or basic event.
+    DuplicateName(String),
+    /// Reference to a gate or basic event that is never defined.
+    UndefinedReference(String),
+    /// Wrong specification of a `<K>of<N>` gate.
+    MalformedVot(String),
+    /// Wrong number of arguments for a gate.
+    Arity(String),
+    /// Included file that can not be read, or that includes itself.
+    InvalidInclude(String),
+    /// Functional dependency that can not be rewritten as a static gate.
+    InvalidDependency(String),
+    /// Basic event without a probability or a failure rate.
+    MissingDistribution(String),
+    /// Expression of a parameter or basic event that can not be evaluated.
+    UnsupportedExpression(String),
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorKind::Io(msg) => write!(f, "could not read file: {msg}"),
+            ParseErrorKind::Syntax(msg) => write!(f, "syntax error: {msg}"),
+            ParseErrorKind::UnknownGate(op) => write!(f, "unknown gate type '{op}'"),
+            ParseErrorKind::UnsupportedGate(op) => {
+                write!(f, "unsupported type of gate '{op}'. Is this a Static FT?")
+            }
+            ParseErrorKind::UnknownAttribute(key) => {
+                write!(f, "unknown basic event attribute '{key}'")
+            }
+            ParseErrorKind::InvalidAttribute(msg) => {
+                write!(f, "invalid basic event attribute: {msg}")
+            }
+            ParseErrorKind::UnsupportedAttribute(msg) => {
+                write!(f, "unsupported basic event attribute: {msg}")
+            }
+            ParseErrorKind::InvalidNumber(value) => write!(f, "could not parse number '{value}'"),
+            ParseErrorKind::DuplicateName(name) => write!(f, "name '{name}' already in use"),
+            ParseErrorKind::UndefinedReference(name) => {
+                write!(f, "'{name}' is not defined in the model")
+            }
+            ParseErrorKind::MalformedVot(msg) => write!(f, "malformed VOT gate: {msg}"),
+            ParseErrorKind::Arity(msg) => write!(f, "wrong number of arguments: {msg}"),
+            ParseErrorKind::InvalidInclude(msg) => write!(f, "invalid include: {msg}"),
+            ParseErrorKind::InvalidDependency(msg) => {
+                write!(f, "invalid functional dependency: {msg}")
+            }
+            ParseErrorKind::MissingDistribution(name) => write!(
+                f,
+                "basic event '{name}' must have either a discrete or continuous distribution function"
+            ),
+            ParseErrorKind::UnsupportedExpression(msg) => {
+                write!(f, "unsupported expression: {msg}")
+            }
+        }
+    }
+}
+
+/// Error found while reading a model, with the location of the offending token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub token: String,
+    pub kind: ParseErrorKind,
+}
+
+impl ParseError {
+    pub fn new(file: &str, span: Span, token: &str, kind: ParseErrorKind) -> Self {
+        ParseError {
+            file: file.to_owned(),
+            line: span.line,
+            column: span.column,
+            token: token.to_owned(),
+            kind,
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {} (at '{}')",
+            self.file, self.line, self.column, self.kind, self.token
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// All the errors collected during one pass over a model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseErrors(pub Vec<ParseError>);
+
+impl ParseErrors {
+    pub fn errors(&self) -> &[ParseError] {
+        &self.0
+    }
+}
+
+impl From<ParseError> for ParseErrors {
+    fn from(err: ParseError) -> Self {
+        ParseErrors(vec![err])
+    }
+}
+
+impl Display for ParseErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.iter().map(|e| e.to_string()).join("\n"))
+    }
+}
+
+impl std::error::Error for ParseErrors {}
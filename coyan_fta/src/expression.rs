@@ -0,0 +1,225 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// Arithmetic expression over numbers and named parameters, used for the attributes
+/// of the basic events and the `param` declarations of GALILEO models.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Number(f64),
+    Parameter(String),
+    Neg(Box<Expression>),
+    Add(Box<Expression>, Box<Expression>),
+    Sub(Box<Expression>, Box<Expression>),
+    Mul(Box<Expression>, Box<Expression>),
+    Div(Box<Expression>, Box<Expression>),
+    Exp(Box<Expression>),
+    Ln(Box<Expression>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ExprToken {
+    Number(f64),
+    Ident(String),
+    Symbol(char),
+}
+
+impl Display for ExprToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExprToken::Number(n) => write!(f, "{n}"),
+            ExprToken::Ident(name) => write!(f, "{name}"),
+            ExprToken::Symbol(c) => write!(f, "{c}"),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<ExprToken>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // Exponent of the scientific notation, e.g. 1.5e-3.
+            if i < chars.len() && matches!(chars[i], 'e' | 'E') {
+                let sign = usize::from(matches!(chars.get(i + 1), Some('+' | '-')));
+                if chars.get(i + 1 + sign).is_some_and(|d| d.is_ascii_digit()) {
+                    i += 1 + sign;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let number: String = chars[start..i].iter().collect();
+            let value = number
+                .parse::<f64>()
+                .map_err(|_| format!("invalid number '{number}'"))?;
+            tokens.push(ExprToken::Number(value));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '.')) {
+                i += 1;
+            }
+            tokens.push(ExprToken::Ident(chars[start..i].iter().collect()));
+        } else if "+-*/()".contains(c) {
+            tokens.push(ExprToken::Symbol(c));
+            i += 1;
+        } else {
+            return Err(format!("unexpected character '{c}'"));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser, with the usual precedence of the operators.
+struct Parser {
+    tokens: Vec<ExprToken>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&ExprToken> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<ExprToken> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), String> {
+        match self.next() {
+            Some(ExprToken::Symbol(c)) if c == symbol => Ok(()),
+            Some(t) => Err(format!("expected '{symbol}', found '{t}'")),
+            None => Err(format!("expected '{symbol}' at the end of the expression")),
+        }
+    }
+
+    /// `sum := product (('+' | '-') product)*`
+    fn sum(&mut self) -> Result<Expression, String> {
+        let mut lhs = self.product()?;
+        while let Some(ExprToken::Symbol(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.pos += 1;
+            let rhs = Box::new(self.product()?);
+            lhs = match op {
+                '+' => Expression::Add(Box::new(lhs), rhs),
+                _ => Expression::Sub(Box::new(lhs), rhs),
+            };
+        }
+        Ok(lhs)
+    }
+
+    /// `product := unary (('*' | '/') unary)*`
+    fn product(&mut self) -> Result<Expression, String> {
+        let mut lhs = self.unary()?;
+        while let Some(ExprToken::Symbol(op @ ('*' | '/'))) = self.peek().cloned() {
+            self.pos += 1;
+            let rhs = Box::new(self.unary()?);
+            lhs = match op {
+                '*' => Expression::Mul(Box::new(lhs), rhs),
+                _ => Expression::Div(Box::new(lhs), rhs),
+            };
+        }
+        Ok(lhs)
+    }
+
+    /// `unary := ('-' | '+') unary | atom`
+    fn unary(&mut self) -> Result<Expression, String> {
+        match self.peek() {
+            Some(ExprToken::Symbol('-')) => {
+                self.pos += 1;
+                Ok(Expression::Neg(Box::new(self.unary()?)))
+            }
+            Some(ExprToken::Symbol('+')) => {
+                self.pos += 1;
+                self.unary()
+            }
+            _ => self.atom(),
+        }
+    }
+
+    /// `atom := number | name | ('exp' | 'ln') '(' sum ')' | '(' sum ')'`
+    fn atom(&mut self) -> Result<Expression, String> {
+        match self.next() {
+            Some(ExprToken::Number(n)) => Ok(Expression::Number(n)),
+            Some(ExprToken::Ident(name))
+                if matches!(name.as_str(), "exp" | "ln")
+                    && self.peek() == Some(&ExprToken::Symbol('(')) =>
+            {
+                self.pos += 1;
+                let arg = Box::new(self.sum()?);
+                self.expect(')')?;
+                Ok(match name.as_str() {
+                    "exp" => Expression::Exp(arg),
+                    _ => Expression::Ln(arg),
+                })
+            }
+            Some(ExprToken::Ident(name)) => Ok(Expression::Parameter(name)),
+            Some(ExprToken::Symbol('(')) => {
+                let inner = self.sum()?;
+                self.expect(')')?;
+                Ok(inner)
+            }
+            Some(t) => Err(format!("unexpected '{t}'")),
+            None => Err(String::from("unexpected end of the expression")),
+        }
+    }
+}
+
+impl FromStr for Expression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+        };
+        let expr = parser.sum()?;
+        match parser.next() {
+            None => Ok(expr),
+            Some(t) => Err(format!("unexpected '{t}'")),
+        }
+    }
+}
+
+impl Expression {
+    /// Names of the parameters used in the expression.
+    pub fn parameters(&self) -> Vec<&str> {
+        match self {
+            Expression::Number(_) => vec![],
+            Expression::Parameter(name) => vec![name.as_str()],
+            Expression::Neg(e) | Expression::Exp(e) | Expression::Ln(e) => e.parameters(),
+            Expression::Add(l, r)
+            | Expression::Sub(l, r)
+            | Expression::Mul(l, r)
+            | Expression::Div(l, r) => {
+                let mut params = l.parameters();
+                params.extend(r.parameters());
+                params
+            }
+        }
+    }
+
+    /// Evaluate the expression, where `lookup` gives the value of each parameter.
+    /// Returns the name of the first parameter without a value as error.
+    pub fn eval(&self, lookup: &dyn Fn(&str) -> Option<f64>) -> Result<f64, String> {
+        Ok(match self {
+            Expression::Number(n) => *n,
+            Expression::Parameter(name) => lookup(name).ok_or(name.to_owned())?,
+            Expression::Neg(e) => -e.eval(lookup)?,
+            Expression::Add(l, r) => l.eval(lookup)? + r.eval(lookup)?,
+            Expression::Sub(l, r) => l.eval(lookup)? - r.eval(lookup)?,
+            Expression::Mul(l, r) => l.eval(lookup)? * r.eval(lookup)?,
+            Expression::Div(l, r) => l.eval(lookup)? / r.eval(lookup)?,
+            Expression::Exp(e) => e.eval(lookup)?.exp(),
+            Expression::Ln(e) => e.eval(lookup)?.ln(),
+        })
+    }
+}
@@ -3,27 +3,73 @@ use indicatif::ParallelProgressIterator;
 use itertools::Itertools;
 use nodes::{Node, NodeId};
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use std::collections::HashMap;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::Write;
 use std::sync::atomic::AtomicUsize;
 
+use crate::errors::ParseErrors;
 use crate::fault_tree_normalizer::FaultTreeNormalizer;
 use crate::formula::{CNFFormat, Formula};
 use crate::modularizer::get_modules;
-use crate::nodes::{self, BasicEvent};
+use crate::nodes::{self, BasicEvent, vot_clauses};
 use crate::preproc::*;
 use crate::solver::Solver;
 
-type ImpMeasures = (f64, f64, f64);
+/// Importance measures of a basic event, where `TEP(p=1)` and `TEP(p=0)` are the TEPs of the FT
+/// with the basic event conditioned to fail and to not fail.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ImpMeasures {
+    /// Birnbaum Measure, `TEP(p=1) - TEP(p=0)`.
+    pub birnbaum: f64,
+    /// Improvement Potential, `TEP(p=1) - TEP`.
+    pub improvement_potential: f64,
+    /// Criticality Measure, `birnbaum * p / TEP`.
+    pub criticality: f64,
+    /// Fussell-Vesely, the fraction of the TEP given by the minimal cut sets that contain the
+    /// basic event. Only defined for coherent FTs, see [crate::cutsets::fussell_vesely].
+    pub fussell_vesely: Option<f64>,
+    /// Risk Achievement Worth, `TEP(p=1) / TEP`.
+    pub risk_achievement_worth: f64,
+    /// Risk Reduction Worth, `TEP / TEP(p=0)`. Infinite if the TEP is 0 with a perfect component.
+    pub risk_reduction_worth: f64,
+    /// Birnbaum structural importance, the Birnbaum Measure with every probability set to 0.5:
+    /// the fraction of the states of the other basic events where this one is critical.
+    pub structural: Option<f64>,
+}
+
+impl ImpMeasures {
+    /// Measures given the TEP of the FT, the conditioned TEPs and the probability of the basic event.
+    /// The structural importance is left empty, as it needs other TEPs, and so is the
+    /// Fussell-Vesely, as it needs the minimal cut sets.
+    pub fn from_teps(tep: f64, pos_tep: f64, neg_tep: f64, prob: f64) -> Self {
+        let birnbaum = pos_tep - neg_tep;
+        ImpMeasures {
+            birnbaum,
+            improvement_potential: pos_tep - tep,
+            criticality: birnbaum * prob / tep,
+            fussell_vesely: None,
+            risk_achievement_worth: pos_tep / tep,
+            risk_reduction_worth: tep / neg_tep,
+            structural: None,
+        }
+    }
+}
 
 impl<T> From<FaultTreeNormalizer<T>> for FaultTree<T> {
     fn from(ft_norm: FaultTreeNormalizer<T>) -> Self {
+        let names = ft_norm
+            .lookup_table
+            .into_iter()
+            .map(|(name, nid)| (nid, name))
+            .collect();
         FaultTree::<T> {
             nodes: ft_norm.nodes,
             root_id: ft_norm.root_id,
             node_counter: ft_norm.node_counter,
             negate_or: false,
+            names,
         }
     }
 }
@@ -40,6 +86,7 @@ where
                 self.node_counter.load(std::sync::atomic::Ordering::Relaxed),
             ),
             negate_or: self.negate_or,
+            names: self.names.clone(),
         }
     }
 }
@@ -48,14 +95,15 @@ where
 /// Can be created empty or read from a file using FT normalizer.
 /// They have some extra details:
 /// - Handle the logic of the Tseitin Encoding
-/// - Do not have information about names of nodes
+/// - Only keep the names of the nodes to write the tree back, if it was read from a file
 /// - Do not have VOT gates
 /// - Do not have negations in arguments, but in separated gates.
 pub struct FaultTree<T> {
     pub nodes: IndexVec<NodeId, Node<T>>,
     pub root_id: NodeId,
-    node_counter: AtomicUsize,
-    negate_or: bool,
+    pub(crate) node_counter: AtomicUsize,
+    pub(crate) negate_or: bool,
+    pub(crate) names: HashMap<NodeId, String>,
 }
 
 impl FaultTree<String> {
@@ -65,16 +113,83 @@ impl FaultTree<String> {
             root_id: NodeId::new(0),
             node_counter: AtomicUsize::new(0),
             negate_or: false,
+            names: HashMap::new(),
         }
     }
 
-    /// Generate a FT from a dft file.
-    pub fn new_from_file(filename: &str, simplify: bool, negate_or: bool) -> Self {
+    /// Name of a node. Gates without a name are named by their id.
+    pub fn name_of(&self, nid: NodeId) -> String {
+        match (&self.nodes[nid], self.names.get(&nid)) {
+            (Node::BasicEvent(name, _), _) => name.to_owned(),
+            (_, Some(name)) => name.to_owned(),
+            (_, None) => format!("G{}", nid),
+        }
+    }
+
+    /// Generate a FT from a dft file. The values in `parameters` replace the ones in the file.
+    /// Returns all the errors found in the file if it can not be read.
+    pub fn new_from_file(
+        filename: &str,
+        simplify: bool,
+        negate_or: bool,
+        parameters: &HashMap<String, f64>,
+    ) -> Result<Self, ParseErrors> {
         let mut ft_norm = FaultTreeNormalizer::default();
-        ft_norm.read_from_file(filename, simplify);
+        ft_norm.set_parameters(parameters.to_owned());
+        ft_norm.read_from_file(filename, simplify)?;
         let mut ft = FaultTree::from(ft_norm);
         ft.negate_or = negate_or;
-        ft
+        Ok(ft)
+    }
+
+    /// Generate a FT from a file in the Open-PSA Model Exchange Format.
+    /// The values in `parameters` replace the ones in the file.
+    pub fn new_from_mef(
+        filename: &str,
+        simplify: bool,
+        negate_or: bool,
+        parameters: &HashMap<String, f64>,
+    ) -> Result<Self, ParseErrors> {
+        let mut ft_norm = FaultTreeNormalizer::default();
+        ft_norm.set_parameters(parameters.to_owned());
+        ft_norm.read_from_mef(filename, simplify)?;
+        let mut ft = FaultTree::from(ft_norm);
+        ft.negate_or = negate_or;
+        Ok(ft)
+    }
+
+    /// Replace the VOT gates by an AND of OR gates, in the same way as when reading a file.
+    pub fn expand_vot(&mut self) {
+        let vot_ids = self
+            .nodes
+            .iter_enumerated()
+            .filter_map(|(nid, n)| match n {
+                Node::Vot(_, _) => Some(nid),
+                _ => None,
+            })
+            .collect_vec();
+
+        for nid in vot_ids {
+            let Node::Vot(k, args) = self.nodes[nid].clone() else {
+                continue;
+            };
+            let k = k as usize;
+            let node = if k == args.len() {
+                Node::And(args)
+            } else if k == 1 {
+                Node::Or(args)
+            } else {
+                let mut aux_ids = vec![];
+                for clause in vot_clauses(k, &args) {
+                    let aux_gid = self.new_id();
+                    aux_ids.push(aux_gid);
+                    self.names.insert(aux_gid, format!("aux_gate_{}", aux_gid));
+                    self.add_node(Node::Or(clause), aux_gid);
+                }
+                Node::And(aux_ids)
+            };
+            self.update_root(node, nid);
+        }
     }
 
     /// Internal method, changes the id of the root node.
@@ -103,8 +218,20 @@ impl FaultTree<String> {
     /// Creates a new Fault Tree that is a submodule from the orignal one.
     /// It only contains the nodes that are used in the subtree
     pub fn subtree_with_root(&self, new_root_id: NodeId) -> FaultTree<String> {
+        self.subtree_with_overrides(new_root_id, &HashMap::new())
+    }
+
+    /// Same as [FaultTree::subtree_with_root], but the nodes in `overrides` are replaced by basic
+    /// events that fail with the given probability, so their descendants are left out.
+    /// The subtree negates its top OR gate if the FT does, as the solvers get the same flag for both.
+    pub fn subtree_with_overrides(
+        &self,
+        new_root_id: NodeId,
+        overrides: &HashMap<NodeId, f64>,
+    ) -> FaultTree<String> {
         // Create New Fault Tree
         let mut sub_ft = FaultTree::empty();
+        sub_ft.negate_or = self.negate_or;
 
         // Create a mapper to relate the old NodeIds to the new ones.
         let mut new_id_mapper = HashMap::new();
@@ -112,11 +239,17 @@ impl FaultTree<String> {
         // Create new ids for each children and add them to the subtree.
         let mut to_process = vec![new_root_id];
         while let Some(nid) = to_process.pop() {
-            let node = self.nodes[nid].clone();
+            let node = match overrides.get(&nid) {
+                Some(prob) => Node::BasicEvent(self.name_of(nid), BasicEvent::new_with_prob(*prob)),
+                None => self.nodes[nid].clone(),
+            };
             let children = node.children();
             let new_nid = sub_ft.new_id();
 
             new_id_mapper.insert(nid, new_nid);
+            if let Some(name) = self.names.get(&nid) {
+                sub_ft.names.insert(new_nid, name.to_owned());
+            }
             sub_ft.add_node(node, new_nid);
             to_process.append(
                 &mut children
@@ -288,6 +421,15 @@ impl FaultTree<String> {
         preprocess: Option<String>,
         unav: bool,
     ) -> (String, String) {
+        (
+            self.dump_cnf_formula(format, preprocess),
+            self.dump_cnf_weights(format, timepoint, unav),
+        )
+    }
+
+    /// Dump the CNF of the implicit formula, without the weights. The formula does not depend
+    /// on the timepoint, so it can be reused with the weights of several timepoints.
+    pub fn dump_cnf_formula(&self, format: CNFFormat, preprocess: Option<String>) -> String {
         let cnf_formula = self.apply_tseitin();
         let text_formula = cnf_formula.to_text();
         let n_vars = self.get_count();
@@ -295,21 +437,11 @@ impl FaultTree<String> {
             .num_clauses()
             .expect("Top gate must be an AND to translate to CNF.");
 
-        let (problem_line, weight_start) = match format {
-            CNFFormat::MC21 => (
-                format!("p cnf {} {}\n", n_vars, n_clauses),
-                String::from("c p weight"),
-            ),
-            CNFFormat::MCC => (
-                format!("p wcnf {} {}\n", n_vars, n_clauses),
-                String::from("w"),
-            ),
+        let problem_line = match format {
+            CNFFormat::MC21 => format!("p cnf {} {}\n", n_vars, n_clauses),
+            CNFFormat::MCC => format!("p wcnf {} {}\n", n_vars, n_clauses),
         };
 
-        let (gate_weights, be_weights) = self.get_weights(weight_start, timepoint, unav);
-        let be_weights = be_weights.join("\n");
-        let gate_weights = gate_weights.join("\n");
-
         let mut formula_str = text_formula
             .replace(" ∧ ", " 0 \n")
             .replace(" V ", " ")
@@ -326,18 +458,24 @@ impl FaultTree<String> {
             None => format!("{}\n{}\n", problem_line, formula_str),
         };
 
-        let formula_cnf = if formula_cnf
+        if formula_cnf
             .split("\n")
             .any(|l| l.starts_with("c Solved by preprocessing"))
         {
             format!("{}\n{}\n", problem_line, formula_str)
         } else {
             formula_cnf
-        };
-
-        let weights = format!("{}\n{}", be_weights, gate_weights);
+        }
+    }
 
-        (formula_cnf, weights)
+    /// Dump the weights of the literals of the CNF at the given timepoint, in the specified format.
+    pub fn dump_cnf_weights(&self, format: CNFFormat, timepoint: f64, unav: bool) -> String {
+        let weight_start = match format {
+            CNFFormat::MC21 => String::from("c p weight"),
+            CNFFormat::MCC => String::from("w"),
+        };
+        let (gate_weights, be_weights) = self.get_weights(weight_start, timepoint, unav);
+        format!("{}\n{}", be_weights.join("\n"), gate_weights.join("\n"))
     }
 
     /// Dump the implicit formula in CNF format to a String.
@@ -354,6 +492,24 @@ impl FaultTree<String> {
         formula_cnf
     }
 
+    /// Weights of the positive and negative literal of each variable of the CNF, where the
+    /// variable `i + 1` is the node with NodeId `i`. Gates have weight 1 for both literals.
+    pub fn literal_weights(&self, timepoint: f64, unav: bool) -> Vec<(f64, f64)> {
+        (0..self.get_count())
+            .map(|i| match self.nodes.get(NodeId::new(i)) {
+                Some(Node::BasicEvent(_, be)) => {
+                    let weight: f64 = if !unav {
+                        be.unreliability(timepoint)
+                    } else {
+                        be.unavailability(timepoint)
+                    };
+                    (weight, 1.0 - weight)
+                }
+                _ => (1.0, 1.0),
+            })
+            .collect()
+    }
+
     /// Gives the weights in DIMACS format for the Gates and of the BE respectively.
     fn get_weights(
         &self,
@@ -361,73 +517,61 @@ impl FaultTree<String> {
         timepoint: f64,
         unav: bool,
     ) -> (Vec<String>, Vec<String>) {
-        let n_vars = self.get_count();
-        // We see which NodeId do not have the weights and set them to 1.
-        // Only the BasicEvents Nodes have a fixed weight.
-        let used_ids = self
-            .nodes
-            .iter()
-            .enumerate()
-            .filter_map(|(i, n)| match n {
-                Node::BasicEvent(_, _) => Some(i),
-                _ => None,
-            })
-            .collect_vec();
-
-        // Obtain weights of the gates
-        let gate_weights: Vec<String> = (0..n_vars)
-            .filter_map(|i| {
-                if !used_ids.contains(&i) {
-                    Some(format!(
-                        "{} {} 1 0\n{} -{} 1 0",
-                        weight_start,
-                        i + 1,
-                        weight_start,
-                        i + 1
-                    ))
-                } else {
-                    None
-                }
-            })
-            .collect_vec();
-
-        // Obtain weights of the basic Events.
-        let be_weights = self
-            .nodes
-            .iter()
+        let (be_weights, gate_weights): (Vec<_>, Vec<_>) = self
+            .literal_weights(timepoint, unav)
+            .into_iter()
             .enumerate()
-            .filter_map(|(i, n)| match &n {
-                Node::BasicEvent(_, be) => {
-                    let weight: f64 = if !unav {
-                        be.unreliability(timepoint)
-                    } else {
-                        be.unavailability(timepoint)
-                    };
-                    Some(format!(
+            .partition(|(i, _)| {
+                matches!(
+                    self.nodes.get(NodeId::new(*i)),
+                    Some(Node::BasicEvent(_, _))
+                )
+            });
+        let to_dimacs = |weights: Vec<(usize, (f64, f64))>| {
+            weights
+                .into_iter()
+                .map(|(i, (pos, neg))| {
+                    format!(
                         "{} {} {} 0\n{} -{} {} 0",
                         weight_start,
                         i + 1,
-                        weight,
+                        pos,
                         weight_start,
                         i + 1,
-                        1.0 - weight,
-                    ))
-                }
-                _ => None,
-            })
-            .collect_vec();
-        (gate_weights, be_weights)
+                        neg
+                    )
+                })
+                .collect_vec()
+        };
+        (to_dimacs(gate_weights), to_dimacs(be_weights))
     }
 
-    /// Compute the Importance measures: the Birnbaum Measure, the Improvement Potential and the Criticality Measure.
+    /// Compute the Importance measures of every basic event, from the TEPs of the FT with the
+    /// basic event conditioned to fail and to not fail. If `structural` is true, also compute the
+    /// Birnbaum structural importance, which conditions the FT with all the probabilities set to 0.5.
+    /// The result of each basic event is the error of the solver if it failed, like a timeout.
+    /// Fails if the solver fails to compute the TEP of the FT.
+    #[allow(clippy::too_many_arguments)]
     pub fn importance_measures(
         &self,
         solver: &(dyn Solver + Sync),
         format: CNFFormat,
         timepoint: f64,
+        timeout_s: u64,
+        preprocess: Option<String>,
         negate_or: bool,
-    ) -> HashMap<String, (f64, f64, f64)> {
-        let true_tep = solver.compute(self, format, timepoint, 300, None, negate_or, false);
+        unav: bool,
+        structural: bool,
+    ) -> Result<HashMap<String, Result<ImpMeasures, &'static str>>, &'static str> {
+        let true_tep = solver.try_compute(
+            self,
+            format,
+            timepoint,
+            timeout_s,
+            preprocess.clone(),
+            negate_or,
+            unav,
+        )?;
 
         let be_lookup_table: HashMap<String, NodeId> = self
             .nodes
@@ -438,34 +582,44 @@ impl FaultTree<String> {
             })
             .collect::<HashMap<String, NodeId>>();
 
-        be_lookup_table
+        let half_ft = structural.then(|| self.with_half_probabilities());
+
+        let measures = be_lookup_table
             .keys()
             .cloned()
             .collect_vec()
             .par_iter()
             .map(|be_name| {
-                let mut ft = self.clone();
-                (
-                    be_name.to_owned(),
-                    ft.measure_be(
+                let measure = |ft: &FaultTree<String>| {
+                    ft.clone().measure_be(
                         String::from(be_name),
                         solver,
                         &be_lookup_table,
                         format,
                         timepoint,
+                        timeout_s,
+                        preprocess.clone(),
                         negate_or,
-                    ),
-                )
-            })
-            .collect::<HashMap<String, ImpMeasures>>()
-            .into_iter()
-            .map(|(be_name, (ib, perf_tep, prob))| {
-                (be_name, (ib, perf_tep - true_tep, ib * (prob / true_tep)))
+                        unav,
+                    )
+                };
+                let result = measure(self).and_then(|(pos_tep, neg_tep, prob)| {
+                    let mut measures = ImpMeasures::from_teps(true_tep, pos_tep, neg_tep, prob);
+                    if let Some(half_ft) = &half_ft {
+                        let (pos_tep, neg_tep, _) = measure(half_ft)?;
+                        measures.structural = Some(pos_tep - neg_tep);
+                    }
+                    Ok(measures)
+                });
+                (be_name.to_owned(), result)
             })
-            .collect::<HashMap<String, ImpMeasures>>()
+            .collect();
+        Ok(measures)
     }
 
-    /// Method called by [self] in the importance_measures method to compute each measure for a specific basic event.
+    /// Method called by [self] in the importance_measures method to compute, for a specific basic event,
+    /// the TEPs with the basic event conditioned to fail and to not fail, and its probability of failure.
+    #[allow(clippy::too_many_arguments)]
     fn measure_be(
         &mut self,
         comp_name: String,
@@ -473,34 +627,295 @@ impl FaultTree<String> {
         lookup_table: &HashMap<String, NodeId>,
         format: CNFFormat,
         timepoint: f64,
+        timeout_s: u64,
+        preprocess: Option<String>,
         negate_or: bool,
-    ) -> ImpMeasures {
+        unav: bool,
+    ) -> Result<(f64, f64, f64), &'static str> {
         let nid = *lookup_table
             .get(&comp_name)
             .expect("The name of the component is not a leaf in the Tree");
 
-        let unrel = self
-            .nodes
-            .get(nid)
-            .unwrap()
-            .unreliability(timepoint)
-            .expect("We can only use `unreliability` method for basic events.");
+        let node = self.nodes.get(nid).unwrap();
+        let prob = if unav {
+            node.unavailability(timepoint)
+        } else {
+            node.unreliability(timepoint)
+        }
+        .expect("We can only use `unreliability` method for basic events.");
 
-        let pos_node = Node::BasicEvent(comp_name.to_owned(), BasicEvent::const_true());
-        self.update_root(pos_node, nid);
-        let pos_tep = solver.compute(self, format, timepoint, 300, None, negate_or, false);
+        self.condition_be(nid, true);
+        let pos_tep = solver.try_compute(
+            self,
+            format,
+            timepoint,
+            timeout_s,
+            preprocess.clone(),
+            negate_or,
+            unav,
+        )?;
 
-        let neg_node = Node::BasicEvent(comp_name.to_owned(), BasicEvent::const_false());
-        self.update_root(neg_node, nid);
-        let neg_tep = solver.compute(self, format, timepoint, 300, None, negate_or, false);
+        self.condition_be(nid, false);
+        let neg_tep = solver.try_compute(
+            self, format, timepoint, timeout_s, preprocess, negate_or, unav,
+        )?;
 
         // There is no need to revert the changes, because there are different FTs.
-        // let og_node = Node::new(
-        //     NodeType::BasicEvent(comp_name.to_owned(), method.to_owned(), og_prob),
-        // );
-        // self.update_roots(og_node, nid);
+        Ok((pos_tep, neg_tep, prob))
+    }
 
-        (pos_tep - neg_tep, pos_tep, unrel)
+    /// Same FT, where every basic event fails with probability 0.5.
+    fn with_half_probabilities(&self) -> FaultTree<String> {
+        let mut ft = self.clone();
+        for (nid, node) in self.nodes.iter_enumerated() {
+            if let Node::BasicEvent(name, _) = node {
+                ft.update_root(
+                    Node::BasicEvent(name.to_owned(), BasicEvent::new_with_prob(0.5)),
+                    nid,
+                );
+            }
+        }
+        ft
+    }
+
+    /// Compute the same Importance measures as [FaultTree::importance_measures], reusing the
+    /// modules of the FT. Conditioning a basic event only changes the probability of the modules
+    /// that contain it, so the probability of every module is computed once, replacing its
+    /// submodules by basic events, and for each basic event only the modules from the one
+    /// that contains it up to the top event are solved again.
+    #[allow(clippy::too_many_arguments)]
+    pub fn modular_importance_measures(
+        &self,
+        solver: &(dyn Solver + Sync),
+        format: CNFFormat,
+        timepoint: f64,
+        timeout_s: u64,
+        preprocess: Option<String>,
+        negate_or: bool,
+        unav: bool,
+        structural: bool,
+    ) -> Result<HashMap<String, Result<ImpMeasures, &'static str>>, &'static str> {
+        if !self.nodes[self.root_id].is_gate() {
+            return self.importance_measures(
+                solver, format, timepoint, timeout_s, preprocess, negate_or, unav, structural,
+            );
+        }
+        let solve = |ft: &FaultTree<String>| {
+            solver.try_compute(
+                ft,
+                format,
+                timepoint,
+                timeout_s,
+                preprocess.clone(),
+                negate_or,
+                unav,
+            )
+        };
+        let (true_tep, conditioned) = self.modular_conditioned_teps(timepoint, unav, &solve)?;
+        let half_conditioned = if structural {
+            let half_ft = self.with_half_probabilities();
+            Some(half_ft.modular_conditioned_teps(timepoint, unav, &solve)?.1)
+        } else {
+            None
+        };
+
+        let measures = conditioned
+            .into_iter()
+            .map(|(be_name, result)| {
+                let result = result.and_then(|(pos_tep, neg_tep, prob)| {
+                    let mut measures = ImpMeasures::from_teps(true_tep, pos_tep, neg_tep, prob);
+                    if let Some(half_conditioned) = &half_conditioned {
+                        let (pos_tep, neg_tep, _) = half_conditioned[&be_name]?;
+                        measures.structural = Some(pos_tep - neg_tep);
+                    }
+                    Ok(measures)
+                });
+                (be_name, result)
+            })
+            .collect();
+        Ok(measures)
+    }
+
+    /// Compute the TEP and, for each basic event, the TEPs with the basic event conditioned to
+    /// fail and to not fail, and its probability of failure, solving the modules of the FT with `solve`.
+    #[allow(clippy::type_complexity)]
+    fn modular_conditioned_teps(
+        &self,
+        timepoint: f64,
+        unav: bool,
+        solve: &(dyn Fn(&FaultTree<String>) -> Result<f64, &'static str> + Sync),
+    ) -> Result<(f64, HashMap<String, Result<(f64, f64, f64), &'static str>>), &'static str> {
+        let mut modules: HashSet<NodeId> = self.clone().modularize_ft().into_iter().collect();
+        modules.insert(self.root_id);
+
+        // Submodules of each module, and the module that contains each node, stopping the
+        // traversal at the submodules.
+        let mut submodules: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        let mut parent: HashMap<NodeId, NodeId> = HashMap::new();
+        for &mod_id in modules.iter() {
+            let mut subs = vec![];
+            let mut visited = HashSet::new();
+            let mut to_visit = self.nodes[mod_id].children();
+            while let Some(nid) = to_visit.pop() {
+                if !visited.insert(nid) {
+                    continue;
+                }
+                parent.insert(nid, mod_id);
+                if modules.contains(&nid) {
+                    subs.push(nid);
+                } else {
+                    to_visit.extend(self.nodes[nid].children());
+                }
+            }
+            submodules.insert(mod_id, subs);
+        }
+
+        // Height of each module in the hierarchy, so the modules of the same height are solved in parallel.
+        let mut height: HashMap<NodeId, usize> = HashMap::new();
+        let mut to_visit = vec![(self.root_id, false)];
+        while let Some((mod_id, expanded)) = to_visit.pop() {
+            if height.contains_key(&mod_id) {
+                continue;
+            }
+            if !expanded {
+                to_visit.push((mod_id, true));
+                to_visit.extend(submodules[&mod_id].iter().map(|m| (*m, false)));
+                continue;
+            }
+            let h = submodules[&mod_id]
+                .iter()
+                .map(|m| height[m] + 1)
+                .max()
+                .unwrap_or(0);
+            height.insert(mod_id, h);
+        }
+
+        // Probability of each module, with its submodules replaced by their probabilities.
+        let submodule_probs = |mod_id: NodeId, probs: &HashMap<NodeId, f64>| {
+            submodules[&mod_id]
+                .iter()
+                .map(|m| (*m, probs[m]))
+                .collect::<HashMap<NodeId, f64>>()
+        };
+        let mut probs: HashMap<NodeId, f64> = HashMap::new();
+        let by_height = height.iter().into_group_map_by(|(_, h)| **h);
+        for h in by_height.keys().sorted() {
+            let level = by_height[h]
+                .par_iter()
+                .map(|(mod_id, _)| {
+                    let overrides = submodule_probs(**mod_id, &probs);
+                    let tep = solve(&self.subtree_with_overrides(**mod_id, &overrides))?;
+                    Ok((**mod_id, tep))
+                })
+                .collect::<Result<Vec<(NodeId, f64)>, &'static str>>()?;
+            probs.extend(level);
+        }
+        let true_tep = probs[&self.root_id];
+
+        // Propagate the conditioned probability of the basic event up to the top event.
+        // The basic events that are not in the FT do not change the TEP.
+        let conditioned_tep = |nid: NodeId, prob: f64| {
+            if !parent.contains_key(&nid) {
+                return Ok(true_tep);
+            }
+            let (mut node, mut prob) = (nid, prob);
+            while let Some(&mod_id) = parent.get(&node) {
+                let mut overrides = submodule_probs(mod_id, &probs);
+                overrides.insert(node, prob);
+                prob = solve(&self.subtree_with_overrides(mod_id, &overrides))?;
+                node = mod_id;
+            }
+            Ok(prob)
+        };
+        let conditioned = self
+            .nodes
+            .iter_enumerated()
+            .filter(|(_, n)| matches!(n, Node::BasicEvent(_, _)))
+            .collect_vec()
+            .par_iter()
+            .map(|(nid, node)| {
+                let prob = if unav {
+                    node.unavailability(timepoint)
+                } else {
+                    node.unreliability(timepoint)
+                }
+                .expect("We can only use `unreliability` method for basic events.");
+                let result = conditioned_tep(*nid, 1.0)
+                    .and_then(|pos_tep| Ok((pos_tep, conditioned_tep(*nid, 0.0)?, prob)));
+                (self.name_of(*nid), result)
+            })
+            .collect();
+        Ok((true_tep, conditioned))
+    }
+
+    /// Compute the same Importance measures as [FaultTree::importance_measures] with a single
+    /// compilation of the FT, instead of solving it twice for each basic event. The solver gives
+    /// the Birnbaum Measure of every basic event as the derivative of the TEP, and since the TEP
+    /// is linear in each probability, the conditioned TEPs are `TEP(p=1) = TEP + (1 - p) * birnbaum`
+    /// and `TEP(p=0) = TEP - p * birnbaum`. Only for the solvers that compile the FT.
+    pub fn compiled_importance_measures(
+        &self,
+        solver: &(dyn Solver + Sync),
+        timepoint: f64,
+        timeout_s: u64,
+        unav: bool,
+        structural: bool,
+    ) -> Result<HashMap<String, Result<ImpMeasures, &'static str>>, &'static str> {
+        let (true_tep, birnbaum) = solver.birnbaum_measures(self, timepoint, timeout_s, unav)?;
+        let half_birnbaum = if structural {
+            let half_ft = self.with_half_probabilities();
+            Some(
+                solver
+                    .birnbaum_measures(&half_ft, timepoint, timeout_s, unav)?
+                    .1,
+            )
+        } else {
+            None
+        };
+
+        let measures = self
+            .nodes
+            .iter_enumerated()
+            .filter_map(|(nid, node)| match node {
+                Node::BasicEvent(name, be) => {
+                    let prob = if unav {
+                        be.unavailability(timepoint)
+                    } else {
+                        be.unreliability(timepoint)
+                    };
+                    // The basic events that are not in the FT do not change the TEP.
+                    let ib = birnbaum.get(&nid).copied().unwrap_or(0.0);
+                    // Clamped, as the rounding errors can take them out of [0, 1].
+                    let mut measures = ImpMeasures::from_teps(
+                        true_tep,
+                        (true_tep + (1.0 - prob) * ib).min(1.0),
+                        (true_tep - prob * ib).max(0.0),
+                        prob,
+                    );
+                    measures.structural = half_birnbaum
+                        .as_ref()
+                        .map(|half| half.get(&nid).copied().unwrap_or(0.0));
+                    Some((name.to_owned(), Ok(measures)))
+                }
+                _ => None,
+            })
+            .collect();
+        Ok(measures)
+    }
+
+    /// Replace the basic event `nid` by a constant one with the same name, that always fails if
+    /// `failed` is true and never fails otherwise.
+    pub fn condition_be(&mut self, nid: NodeId, failed: bool) {
+        let name = match &self.nodes[nid] {
+            Node::BasicEvent(name, _) => name.to_owned(),
+            _ => panic!("Only basic events can be conditioned."),
+        };
+        let be = if failed {
+            BasicEvent::const_true()
+        } else {
+            BasicEvent::const_false()
+        };
+        self.update_root(Node::BasicEvent(name, be), nid);
     }
 
     /// Update a Node by replacing it with another one.
@@ -577,3 +992,142 @@ impl FaultTree<String> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::{BddSolver, NativeSolver};
+    use crate::test_utils::ft_from_dft;
+
+    /// Modules `M1` (with `A` shared between its gates) and `M2`, below a top OR gate.
+    const MODULES: &str = r#"toplevel "T";
+"T" or "M1" "M2" "C";
+"M1" and "G1" "G2";
+"G1" or "A" "B";
+"G2" or "A" "D";
+"M2" 2of3 "E" "F" "H";
+"A" prob=0.1;
+"B" lambda=0.2;
+"C" prob=0.05;
+"D" prob=0.3;
+"E" lambda=0.1;
+"F" prob=0.25;
+"H" prob=0.4;
+"#;
+
+    type Measures = HashMap<String, Result<ImpMeasures, &'static str>>;
+
+    fn assert_same_measures(measures: &Measures, expected: &Measures) {
+        assert_eq!(measures.len(), expected.len());
+        for (be_name, expected) in expected {
+            let (m, e) = (measures[be_name].unwrap(), expected.unwrap());
+            let pairs = [
+                (m.birnbaum, e.birnbaum),
+                (m.improvement_potential, e.improvement_potential),
+                (m.criticality, e.criticality),
+                (m.risk_achievement_worth, e.risk_achievement_worth),
+                (m.risk_reduction_worth, e.risk_reduction_worth),
+                (m.structural.unwrap(), e.structural.unwrap()),
+            ];
+            for (value, expected) in pairs {
+                assert!(
+                    (value - expected).abs() < 1e-9,
+                    "{be_name}: {value} != {expected}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn modular_importance_matches_conditioning() {
+        let solver = NativeSolver::new();
+        for negate_or in [false, true] {
+            let mut ft = ft_from_dft("coyan_fault_tree_modular.dft", MODULES);
+            ft.negate_or = negate_or;
+            let conditioning = ft
+                .importance_measures(
+                    &solver,
+                    CNFFormat::MC21,
+                    1.0,
+                    60,
+                    None,
+                    negate_or,
+                    false,
+                    true,
+                )
+                .unwrap();
+            let modular = ft
+                .modular_importance_measures(
+                    &solver,
+                    CNFFormat::MC21,
+                    1.0,
+                    60,
+                    None,
+                    negate_or,
+                    false,
+                    true,
+                )
+                .unwrap();
+            assert_same_measures(&modular, &conditioning);
+        }
+    }
+
+    #[test]
+    fn compiled_importance_matches_conditioning() {
+        let ft = ft_from_dft("coyan_fault_tree_compiled.dft", MODULES);
+        let solver = BddSolver::new("bdd");
+        let conditioning = ft
+            .importance_measures(&solver, CNFFormat::MC21, 1.0, 60, None, false, false, true)
+            .unwrap();
+        let compiled = ft
+            .compiled_importance_measures(&solver, 1.0, 60, false, true)
+            .unwrap();
+        assert_same_measures(&compiled, &conditioning);
+    }
+
+    #[test]
+    fn importance_uses_unavailabilities_with_unav() {
+        // The Birnbaum of each event of an AND gate is the probability of the other one, which
+        // is 0.6 / (1 + 0.6) for A and 0.2 * 2 / 2 + 0.2 * 0.25 for B with repairs.
+        let ft = ft_from_dft(
+            "coyan_fault_tree_unav.dft",
+            "toplevel \"T\";\n\"T\" and \"A\" \"B\";\n\"A\" lambda=0.3 repair=0.5;\n\"B\" lambda=0.2 interval=2 repair_time=0.25;\n",
+        );
+        let solver = BddSolver::new("bdd");
+        for (unav, p_a, p_b) in [
+            (false, 1.0 - (-0.3f64).exp(), 1.0 - (-0.2f64).exp()),
+            (true, 0.375, 0.25),
+        ] {
+            // The three ways of computing the measures follow the same mode.
+            let all_measures = [
+                ft.importance_measures(&solver, CNFFormat::MC21, 1.0, 60, None, false, unav, false),
+                ft.modular_importance_measures(
+                    &solver,
+                    CNFFormat::MC21,
+                    1.0,
+                    60,
+                    None,
+                    false,
+                    unav,
+                    false,
+                ),
+                ft.compiled_importance_measures(&solver, 1.0, 60, unav, false),
+            ];
+            for measures in all_measures {
+                let measures = measures.unwrap();
+                let (a, b) = (measures["A"].unwrap(), measures["B"].unwrap());
+                for (value, expected) in [
+                    (a.birnbaum, p_b),
+                    (b.birnbaum, p_a),
+                    (a.improvement_potential, p_b - p_a * p_b),
+                    (b.risk_achievement_worth, 1.0 / p_b),
+                ] {
+                    assert!(
+                        (value - expected).abs() < 1e-9,
+                        "unav={unav}: {value} != {expected}"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -1,19 +1,58 @@
 use index_vec::IndexVec;
 use itertools::Itertools;
 use nodes::{Node, NodeId};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::AtomicUsize;
 use std::{collections::HashMap, fs::read_to_string};
 
-use crate::nodes::{self, BasicEvent, RepairMode};
+use crate::errors::{ParseError, ParseErrorKind, ParseErrors, Span};
+use crate::expression::Expression;
+use crate::galileo::{Statement, Token, parse_statements};
+use crate::nodes::{self, BasicEvent, RepairMode, Uncertainty, vot_clauses};
 
-/// Helper reader function.
-fn _read_lines(filename: &str) -> Vec<String> {
-    read_to_string(filename)
-        .unwrap()
-        .lines()
-        .map(String::from)
-        .collect()
-}
+/// Gates of Dynamic FTs, recognized but not supported.
+/// FDEP gates are also dynamic, but they are rewritten into static gates when possible.
+const DYNAMIC_GATES: [&str; 5] = ["csp", "wsp", "hsp", "pand", "seq"];
+
+/// Attributes that can be given to a basic event.
+const BE_ATTRIBUTES: [&str; 18] = [
+    "prob",
+    "lambda",
+    "phases",
+    "shape",
+    "scale",
+    "repair",
+    "interval",
+    "test",
+    "repair_time",
+    "mttr",
+    "dorm",
+    "cov",
+    "res",
+    "ef",
+    "gamma",
+    "beta",
+    "low",
+    "high",
+];
+
+/// Alternative names of the attributes of a basic event.
+const BE_ATTRIBUTE_ALIASES: [(&str, &str); 2] = [("test", "interval"), ("mttr", "repair_time")];
+
+/// Location of the definition of a placeholder: the file, the span of the
+/// operator and the span of each argument.
+pub(crate) type PlaceHolderSource = (String, Span, Vec<Span>);
+
+/// FDEP gate as read from the model: the file, its name, the operator and its arguments.
+/// The first argument is the trigger, the rest are the dependent events.
+type FunctionalDependency = (String, Token, Token, Vec<Token>);
+
+/// Statement of a GALILEO model, with the file and the namespace where it is defined.
+type SourcedStatement = (String, String, Statement);
+
+/// Trigger of a dependent event, with the file of its FDEP gate.
+type Trigger<'a> = (&'a String, &'a Token);
 
 /// Normalizer struct for FTs.
 /// It handles all the not so nice parsing and reading of the FT.
@@ -22,6 +61,9 @@ pub struct FaultTreeNormalizer<T> {
     pub nodes: IndexVec<NodeId, Node<T>>,
     pub root_id: NodeId,
     pub node_counter: AtomicUsize,
+    sources: HashMap<NodeId, PlaceHolderSource>,
+    /// Values given by the user to the parameters of the model, replacing their definition.
+    parameters: HashMap<String, f64>,
 }
 
 impl Clone for FaultTreeNormalizer<String> {
@@ -34,6 +76,8 @@ impl Clone for FaultTreeNormalizer<String> {
             nodes: self.nodes.clone(),
             root_id: self.root_id,
             node_counter,
+            sources: self.sources.clone(),
+            parameters: self.parameters.clone(),
         }
     }
 }
@@ -45,36 +89,513 @@ impl Default for FaultTreeNormalizer<String> {
             nodes: IndexVec::new(),
             root_id: NodeId::new(0),
             node_counter: AtomicUsize::new(0),
+            sources: HashMap::new(),
+            parameters: HashMap::new(),
         }
     }
 }
 
-fn parse_basic_event(name: &str, args: &[String]) -> (String, BasicEvent) {
-    let name = name.replace("\"", "").replace(";", "");
+/// Parse the `<K>of<N>` specification of a VOT gate.
+fn parse_vot(op: &str, n_args: usize) -> Result<(usize, usize), ParseErrorKind> {
+    let (k, n) = op
+        .split_once("of")
+        .ok_or_else(|| ParseErrorKind::MalformedVot(format!("expected <K>of<N>, found '{op}'")))?;
+    let k = k
+        .parse::<usize>()
+        .map_err(|e| ParseErrorKind::MalformedVot(format!("invalid K '{k}': {e}")))?;
+    let n = n
+        .parse::<usize>()
+        .map_err(|e| ParseErrorKind::MalformedVot(format!("invalid N '{n}': {e}")))?;
+    if n_args != n {
+        return Err(ParseErrorKind::MalformedVot(format!(
+            "the gate {op} has {n_args} arguments"
+        )));
+    }
+    if k < 1 || k > n {
+        return Err(ParseErrorKind::MalformedVot(format!(
+            "K must be between 1 and N in {op}"
+        )));
+    }
+    Ok((k, n))
+}
+
+/// Check that the operator is a static gate with a valid number of arguments.
+pub(crate) fn check_gate(op: &str, n_args: usize) -> Result<(), ParseErrorKind> {
+    match op {
+        _ if DYNAMIC_GATES.contains(&op) => Err(ParseErrorKind::UnsupportedGate(op.to_owned())),
+        "and" | "or" | "xor" if n_args == 0 => Err(ParseErrorKind::Arity(format!(
+            "gate '{op}' needs at least one argument"
+        ))),
+        "and" | "or" | "xor" => Ok(()),
+        "not" if n_args != 1 => Err(ParseErrorKind::Arity(format!(
+            "gate 'not' needs exactly one argument, found {n_args}"
+        ))),
+        "not" => Ok(()),
+        "fdep" if n_args < 2 => Err(ParseErrorKind::Arity(format!(
+            "gate 'fdep' needs a trigger and at least one dependent event, found {n_args} arguments"
+        ))),
+        "fdep" => Ok(()),
+        _ if op.contains("of") => parse_vot(op, n_args).map(|_| ()),
+        _ => Err(ParseErrorKind::UnknownGate(op.to_owned())),
+    }
+}
+
+/// Build a basic event from its attributes.
+/// The failure time follows one of these distributions:
+///  - `prob=<p>`: constant probability of failure.
+///  - `lambda=<l>`: exponential with rate `l`, or Erlang with rate `l` per phase if `phases=<k>` is given.
+///  - `shape=<k> scale=<s>`: Weibull. Instead of the scale, the rate `lambda=<l>` can be given, with `s = 1/l`.
+///
+/// Exponential basic events can be repaired, used when computing the unavailability:
+///  - `repair=<r>`: monitored, with repair rate `r`.
+///  - `interval=<T> repair_time=<t>`: periodically tested every `T` units of time, with a mean repair time `t`.
+///    The aliases `test` and `mttr` can also be used.
+///
+/// The parameter of the distribution (the probability, the rate, or the scale of a Weibull) can
+/// be uncertain, used to propagate the uncertainty to the TEP:
+///  - `ef=<EF>`: lognormal with the parameter as mean and error factor `EF`.
+///  - `gamma=<k>`: gamma with the parameter as mean and shape `k`.
+///  - `beta=<a>`: beta with the probability as mean and first shape `a`.
+///  - `low=<a> high=<b>`: uniform between `a` and `b`.
+///
+/// The dormancy factor `dorm` only affects spare gates, so it is checked and ignored.
+/// The coverage `cov` and restoration `res` are only accepted with their default values (1 and 0),
+/// any other value adds single point failures, that are not static.
+fn parse_basic_event(
+    file: &str,
+    name: &Token,
+    attrs: &[(Token, Token)],
+    lookup: &dyn Fn(&str) -> Option<f64>,
+) -> Result<BasicEvent, Vec<ParseError>> {
+    let mut errors = vec![];
     let mut params = HashMap::new();
+    let mut keys = HashMap::new();
 
-    for item in args {
-        let (key, value) = item.split("=").collect_tuple().unwrap();
-        let value = value
-            .replace(";", "")
-            .parse::<f64>()
-            .unwrap_or_else(|_| panic!("Could not parse number {value}."));
-        params.insert(key, value);
+    for (key, value) in attrs {
+        let key_name = key.text.to_lowercase();
+        if !BE_ATTRIBUTES.contains(&key_name.as_str()) {
+            errors.push(ParseError::new(
+                file,
+                key.span,
+                &key.text,
+                ParseErrorKind::UnknownAttribute(key.text.to_owned()),
+            ));
+            continue;
+        }
+        let key_name = BE_ATTRIBUTE_ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == key_name)
+            .map_or(key_name.to_owned(), |(_, attr)| attr.to_string());
+        if params.contains_key(&key_name) {
+            errors.push(ParseError::new(
+                file,
+                key.span,
+                &key.text,
+                ParseErrorKind::InvalidAttribute(format!("'{key_name}' is given more than once")),
+            ));
+            continue;
+        }
+        let v = match eval_expression(&value.text, lookup) {
+            Ok(v) => v,
+            Err(kind) => {
+                errors.push(ParseError::new(file, value.span, &value.text, kind));
+                continue;
+            }
+        };
+        let kind = match key_name.as_str() {
+            "phases" if v < 1.0 || v.fract() != 0.0 => Some(ParseErrorKind::InvalidAttribute(
+                format!("'phases' must be a positive integer, found {v}"),
+            )),
+            "prob" | "dorm" | "cov" | "res" if !(0.0..=1.0).contains(&v) => {
+                Some(ParseErrorKind::InvalidAttribute(format!(
+                    "'{key_name}' must be in [0, 1], found {v}"
+                )))
+            }
+            "lambda" if v < 0.0 => Some(ParseErrorKind::InvalidAttribute(format!(
+                "'lambda' can not be negative, found {v}"
+            ))),
+            "repair" | "scale" | "shape" if v <= 0.0 => Some(ParseErrorKind::InvalidAttribute(
+                format!("'{key_name}' must be positive, found {v}"),
+            )),
+            "interval" if v <= 0.0 => Some(ParseErrorKind::InvalidAttribute(format!(
+                "'interval' must be positive, found {v}"
+            ))),
+            "repair_time" if v < 0.0 => Some(ParseErrorKind::InvalidAttribute(format!(
+                "'repair_time' can not be negative, found {v}"
+            ))),
+            "ef" if v < 1.0 => Some(ParseErrorKind::InvalidAttribute(format!(
+                "'ef' must be at least 1, found {v}"
+            ))),
+            "gamma" | "beta" if v <= 0.0 => Some(ParseErrorKind::InvalidAttribute(format!(
+                "'{key_name}' must be positive, found {v}"
+            ))),
+            "low" | "high" if v < 0.0 => Some(ParseErrorKind::InvalidAttribute(format!(
+                "'{key_name}' can not be negative, found {v}"
+            ))),
+            "cov" if v != 1.0 => Some(ParseErrorKind::UnsupportedAttribute(format!(
+                "coverage 'cov={v}' is not supported in a static FT"
+            ))),
+            "res" if v != 0.0 => Some(ParseErrorKind::UnsupportedAttribute(format!(
+                "restoration 'res={v}' is not supported in a static FT"
+            ))),
+            _ => None,
+        };
+        match kind {
+            Some(kind) => errors.push(ParseError::new(file, value.span, &value.text, kind)),
+            None => {
+                keys.insert(key_name.to_owned(), key);
+                params.insert(key_name, v);
+            }
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
     }
 
-    let be = if params.contains_key("prob") {
-        BasicEvent::new_with_prob(*params.get("prob").unwrap())
-    } else {
-        let mut be = BasicEvent::new_with_rate(*params.get("lambda").expect(
-            "Basic Event must have either a discrete or continuous distribution function.",
+    let attr_error = |key: &str, kind: ParseErrorKind| {
+        let token = keys.get(key).copied().unwrap_or(name);
+        vec![ParseError::new(file, token.span, &token.text, kind)]
+    };
+    if params.contains_key("prob")
+        && let Some(key) = [
+            "lambda",
+            "shape",
+            "scale",
+            "phases",
+            "repair",
+            "interval",
+            "repair_time",
+        ]
+        .into_iter()
+        .find(|k| params.contains_key(*k))
+    {
+        return Err(attr_error(
+            key,
+            ParseErrorKind::InvalidAttribute(format!(
+                "'prob' and '{key}' can not be used together"
+            )),
+        ));
+    }
+    let non_exponential =
+        params.contains_key("shape") || params.get("phases").is_some_and(|k| *k > 1.0);
+    if let Some(key) = ["repair", "interval", "repair_time"]
+        .into_iter()
+        .find(|k| params.contains_key(*k))
+        && non_exponential
+    {
+        return Err(attr_error(
+            key,
+            ParseErrorKind::UnsupportedAttribute(format!(
+                "'{key}' is only supported for exponential failure times"
+            )),
+        ));
+    }
+    match (
+        params.contains_key("interval"),
+        params.contains_key("repair_time"),
+    ) {
+        (true, false) => {
+            return Err(attr_error(
+                "interval",
+                ParseErrorKind::InvalidAttribute(String::from(
+                    "'interval' needs the mean repair time 'repair_time'",
+                )),
+            ));
+        }
+        (false, true) => {
+            return Err(attr_error(
+                "repair_time",
+                ParseErrorKind::InvalidAttribute(String::from(
+                    "'repair_time' needs the test 'interval'",
+                )),
+            ));
+        }
+        (true, true) if params.contains_key("repair") => {
+            return Err(attr_error(
+                "repair",
+                ParseErrorKind::InvalidAttribute(String::from(
+                    "a basic event is either monitored ('repair') or periodically tested ('interval'), not both",
+                )),
+            ));
+        }
+        _ => {}
+    }
+    if params.contains_key("phases") && params.contains_key("shape") {
+        return Err(attr_error(
+            "phases",
+            ParseErrorKind::InvalidAttribute(String::from(
+                "'phases' and 'shape' can not be used together",
+            )),
+        ));
+    }
+    if params.contains_key("scale") && !params.contains_key("shape") {
+        return Err(attr_error(
+            "scale",
+            ParseErrorKind::InvalidAttribute(String::from("'scale' needs a 'shape'")),
         ));
-        if params.contains_key("repair") {
-            be.with_repair_mode(RepairMode::Monitored(*params.get("repair").unwrap()));
+    }
+
+    let mut be = if let Some(prob) = params.get("prob") {
+        BasicEvent::new_with_prob(*prob)
+    } else if let Some(shape) = params.get("shape") {
+        let scale = match (params.get("scale"), params.get("lambda")) {
+            (Some(scale), _) => *scale,
+            (None, Some(lambda)) => 1.0 / lambda,
+            (None, None) => {
+                return Err(attr_error(
+                    "shape",
+                    ParseErrorKind::InvalidAttribute(String::from(
+                        "'shape' needs a 'scale' or a 'lambda'",
+                    )),
+                ));
+            }
+        };
+        BasicEvent::new_weibull(*shape, scale)
+    } else if let Some(lambda) = params.get("lambda") {
+        let mut be = match params.get("phases") {
+            Some(phases) if *phases > 1.0 => BasicEvent::new_erlang(*lambda, *phases as usize),
+            _ => BasicEvent::new_with_rate(*lambda),
+        };
+        if let Some(repair) = params.get("repair") {
+            be.with_repair_mode(RepairMode::Monitored(*repair));
+        } else if let (Some(interval), Some(repair_time)) =
+            (params.get("interval"), params.get("repair_time"))
+        {
+            be.with_repair_mode(RepairMode::PeriodicallyTested(*interval, *repair_time));
         }
         be
+    } else if params.contains_key("phases") {
+        return Err(attr_error(
+            "phases",
+            ParseErrorKind::InvalidAttribute(String::from("'phases' needs a 'lambda'")),
+        ));
+    } else {
+        return Err(vec![ParseError::new(
+            file,
+            name.span,
+            &name.text,
+            ParseErrorKind::MissingDistribution(name.text.to_owned()),
+        )]);
+    };
+
+    if let Some(uncertainty) = parse_uncertainty(&params, &be)
+        .map_err(|(key, msg)| attr_error(key, ParseErrorKind::InvalidAttribute(msg)))?
+    {
+        be.with_uncertainty(uncertainty);
+    }
+
+    Ok(be)
+}
+
+/// Read the uncertainty of the parameter of a basic event from its attributes. The error gives
+/// the attribute where it is found.
+fn parse_uncertainty(
+    params: &HashMap<String, f64>,
+    be: &BasicEvent,
+) -> Result<Option<Uncertainty>, (&'static str, String)> {
+    let given = ["ef", "gamma", "beta", "low"]
+        .into_iter()
+        .filter(|k| params.contains_key(*k) || (*k == "low" && params.contains_key("high")))
+        .collect_vec();
+    if given.len() > 1 {
+        return Err((
+            given[1],
+            String::from(
+                "only one of 'ef', 'gamma', 'beta' or 'low' and 'high' can be given to a basic event",
+            ),
+        ));
+    }
+    let value = be.parameter();
+    let is_prob = matches!(be.distribution(), nodes::Distribution::Discrete(_));
+    let uncertainty = match given.first() {
+        None => return Ok(None),
+        Some(&"low") => {
+            let (Some(low), Some(high)) = (params.get("low"), params.get("high")) else {
+                let key = if params.contains_key("low") {
+                    "low"
+                } else {
+                    "high"
+                };
+                return Err((key, String::from("'low' and 'high' must be given together")));
+            };
+            if low > high {
+                return Err((
+                    "low",
+                    format!("'low' must not exceed 'high', found {low} > {high}"),
+                ));
+            }
+            if is_prob && *high > 1.0 {
+                return Err(("high", format!("'high' must be in [0, 1], found {high}")));
+            }
+            Uncertainty::Uniform {
+                low: *low,
+                high: *high,
+            }
+        }
+        Some(&key) => {
+            if value <= 0.0 {
+                return Err((key, format!("'{key}' needs a positive mean, found {value}")));
+            }
+            match key {
+                "ef" => Uncertainty::Lognormal {
+                    error_factor: params["ef"],
+                },
+                "gamma" => Uncertainty::Gamma {
+                    shape: params["gamma"],
+                },
+                _ if !is_prob || value >= 1.0 => {
+                    return Err((
+                        "beta",
+                        String::from("'beta' is only supported for probabilities in (0, 1)"),
+                    ));
+                }
+                _ => Uncertainty::Beta {
+                    alpha: params["beta"],
+                },
+            }
+        }
     };
+    Ok(Some(uncertainty))
+}
+
+/// Parse and evaluate an expression, where `lookup` gives the value of the parameters.
+fn eval_expression(
+    text: &str,
+    lookup: &dyn Fn(&str) -> Option<f64>,
+) -> Result<f64, ParseErrorKind> {
+    let expr = text
+        .parse::<Expression>()
+        .map_err(ParseErrorKind::UnsupportedExpression)?;
+    expr.eval(lookup)
+        .map_err(ParseErrorKind::UndefinedReference)
+}
+
+/// Full name of a parameter used in the namespace `prefix`. It is looked up in the namespace
+/// first, and then in the enclosing ones up to the global namespace.
+fn scoped_name<T>(name: &str, prefix: &str, declared: &HashMap<String, T>) -> Option<String> {
+    prefix
+        .match_indices('.')
+        .map(|(i, _)| &prefix[..=i])
+        .rev()
+        .chain([""])
+        .map(|scope| format!("{scope}{name}"))
+        .find(|full_name| declared.contains_key(full_name))
+}
+
+/// Prefix the name in the token with the namespace.
+fn qualify(prefix: &str, token: Token) -> Token {
+    Token {
+        text: format!("{prefix}{}", token.text),
+        ..token
+    }
+}
+
+/// Read the statements of a GALILEO file, together with the file and namespace of each one.
+/// Each `include "<file>" [as <namespace>];` is replaced by the statements of the included file,
+/// with its names prefixed by `<namespace>.`. The path of the file is relative to the file
+/// that includes it, and the namespace defaults to the file name without extension.
+/// The top level event of an included file can be referenced by the name of its namespace,
+/// like a transfer gate.
+fn read_statements(
+    filename: &str,
+    prefix: &str,
+    include_stack: &mut Vec<PathBuf>,
+    errors: &mut Vec<ParseError>,
+) -> Result<Vec<SourcedStatement>, ParseErrorKind> {
+    let text = read_to_string(filename).map_err(|e| ParseErrorKind::Io(e.to_string()))?;
+    let path = Path::new(filename);
+    include_stack.push(path.canonicalize().unwrap_or(path.to_path_buf()));
+
+    let mut statements = vec![];
+    for statement in parse_statements(filename, &text, errors) {
+        let statement = match statement {
+            Statement::TopLevel { name } => Statement::TopLevel {
+                name: qualify(prefix, name),
+            },
+            Statement::Gate { name, op, args } => Statement::Gate {
+                name: qualify(prefix, name),
+                op,
+                args: args.into_iter().map(|a| qualify(prefix, a)).collect(),
+            },
+            Statement::BasicEvent { name, attrs } => Statement::BasicEvent {
+                name: qualify(prefix, name),
+                attrs,
+            },
+            Statement::Param { name, value } => Statement::Param {
+                name: qualify(prefix, name),
+                value,
+            },
+            Statement::Include {
+                path: include,
+                namespace,
+            } => {
+                let included = path.parent().unwrap_or(Path::new("")).join(&include.text);
+                let canonical = included.canonicalize().unwrap_or(included.to_owned());
+                if let Some(start) = include_stack.iter().position(|p| *p == canonical) {
+                    let cycle = include_stack[start..]
+                        .iter()
+                        .chain([&canonical])
+                        .map(|p| p.display())
+                        .join(" -> ");
+                    errors.push(ParseError::new(
+                        filename,
+                        include.span,
+                        &include.text,
+                        ParseErrorKind::InvalidInclude(format!("include cycle {cycle}")),
+                    ));
+                    continue;
+                }
+                let namespace = match namespace {
+                    Some(ns) => qualify(prefix, ns),
+                    None => Token {
+                        text: format!(
+                            "{prefix}{}",
+                            included.file_stem().unwrap_or_default().to_string_lossy()
+                        ),
+                        ..include.to_owned()
+                    },
+                };
+                let included_name = included.to_string_lossy().to_string();
+                let inner = match read_statements(
+                    &included_name,
+                    &format!("{}.", namespace.text),
+                    include_stack,
+                    errors,
+                ) {
+                    Ok(inner) => inner,
+                    Err(kind) => {
+                        errors.push(ParseError::new(filename, include.span, &include.text, kind));
+                        continue;
+                    }
+                };
+                for (file, inner_prefix, inner_statement) in inner {
+                    match inner_statement {
+                        // The top level of the included file is given the name of the namespace.
+                        Statement::TopLevel { name } => {
+                            let transfer = Statement::Gate {
+                                name: namespace.to_owned(),
+                                op: Token {
+                                    text: String::from("and"),
+                                    ..include.to_owned()
+                                },
+                                args: vec![Token {
+                                    text: name.text,
+                                    ..include.to_owned()
+                                }],
+                            };
+                            statements.push((filename.to_owned(), prefix.to_owned(), transfer));
+                        }
+                        _ => statements.push((file, inner_prefix, inner_statement)),
+                    }
+                }
+                continue;
+            }
+        };
+        statements.push((filename.to_owned(), prefix.to_owned(), statement));
+    }
 
-    (name, be)
+    include_stack.pop();
+    Ok(statements)
 }
 
 impl FaultTreeNormalizer<String> {
@@ -85,115 +606,420 @@ impl FaultTreeNormalizer<String> {
         )
     }
 
+    /// Give values to parameters of the model, that replace their definition in the file.
+    /// The names of parameters in included files are prefixed with their namespace.
+    pub fn set_parameters(&mut self, parameters: HashMap<String, f64>) {
+        self.parameters = parameters;
+    }
+
+    pub(crate) fn parameters(&self) -> &HashMap<String, f64> {
+        &self.parameters
+    }
+
+    /// Evaluate the `param <name> = <expression>;` declarations of the model. Parameters can
+    /// use other parameters, declared before or after them, as long as there are no cycles.
+    /// The values given with `set_parameters` replace the declared expressions.
+    fn eval_parameters(
+        &self,
+        filename: &str,
+        statements: &[SourcedStatement],
+        errors: &mut Vec<ParseError>,
+    ) -> HashMap<String, f64> {
+        let mut declared: HashMap<String, (&String, &String, &Token, Expression)> = HashMap::new();
+        for (file, prefix, statement) in statements {
+            let Statement::Param { name, value } = statement else {
+                continue;
+            };
+            if declared.contains_key(&name.text) {
+                errors.push(ParseError::new(
+                    file,
+                    name.span,
+                    &name.text,
+                    ParseErrorKind::DuplicateName(name.text.to_owned()),
+                ));
+                continue;
+            }
+            let expr = match self.parameters.get(&name.text) {
+                Some(v) => Expression::Number(*v),
+                None => match value.text.parse::<Expression>() {
+                    Ok(expr) => expr,
+                    Err(msg) => {
+                        errors.push(ParseError::new(
+                            file,
+                            value.span,
+                            &value.text,
+                            ParseErrorKind::UnsupportedExpression(msg),
+                        ));
+                        continue;
+                    }
+                },
+            };
+            declared.insert(name.text.to_owned(), (file, prefix, value, expr));
+        }
+        for name in self.parameters.keys().sorted() {
+            if !declared.contains_key(name) {
+                errors.push(ParseError::new(
+                    filename,
+                    Span::default(),
+                    name,
+                    ParseErrorKind::UndefinedReference(name.to_owned()),
+                ));
+            }
+        }
+
+        // Evaluate the parameters whose dependencies already have a value, until none is left.
+        let mut values: HashMap<String, f64> = HashMap::new();
+        let mut pending = declared.keys().sorted().collect_vec();
+        loop {
+            let mut progress = false;
+            pending.retain(|name| {
+                let (file, prefix, value, expr) = &declared[*name];
+                let mut missing = false;
+                for param in expr.parameters() {
+                    match scoped_name(param, prefix, &declared) {
+                        Some(full_name) => missing |= !values.contains_key(&full_name),
+                        None => {
+                            errors.push(ParseError::new(
+                                file,
+                                value.span,
+                                &value.text,
+                                ParseErrorKind::UndefinedReference(param.to_owned()),
+                            ));
+                            return false;
+                        }
+                    }
+                }
+                if missing {
+                    return true;
+                }
+                let lookup =
+                    |param: &str| scoped_name(param, prefix, &declared).map(|p| values[&p]);
+                let v = expr.eval(&lookup).expect("dependencies already evaluated");
+                values.insert(name.to_string(), v);
+                progress = true;
+                false
+            });
+            if !progress {
+                break;
+            }
+        }
+        for name in pending {
+            let (file, _, value, _) = &declared[name];
+            errors.push(ParseError::new(
+                file,
+                value.span,
+                &value.text,
+                ParseErrorKind::UnsupportedExpression(format!(
+                    "parameter '{name}' is defined cyclically"
+                )),
+            ));
+        }
+        values
+    }
+
     /// Method that reads the file, and create a node for each of the lines in the file.
     /// Only create Basic Events and Placeholders.
     /// Keeps track of the gates with only one root (expect NOT), so later it can then be simplified.
-    fn read_file(&mut self, filename: &str, simplify: bool) -> String {
-        let lines = _read_lines(filename);
-        let mut root_name: String = "System".to_owned();
+    /// All the errors found in the file are collected and returned together.
+    fn read_file(&mut self, filename: &str, simplify: bool) -> Result<String, ParseErrors> {
+        let mut errors = vec![];
+        let statements = read_statements(filename, "", &mut vec![], &mut errors)
+            .map_err(|kind| ParseError::new(filename, Span::default(), filename, kind))?;
+
+        let mut root: Option<Token> = None;
         let mut replace_mapper: HashMap<String, String> = HashMap::new();
+        // Every name defined in the file, even if its definition has errors.
+        let mut defined: HashSet<String> = HashSet::new();
+        let mut references: Vec<(String, Token)> = vec![];
+        let mut fdeps: Vec<FunctionalDependency> = vec![];
+        let parameters = self.eval_parameters(filename, &statements, &mut errors);
 
-        for l in lines.clone() {
-            match &l.split_whitespace().map(str::to_string).collect_vec()[..] {
-                [comment, ..] if comment.eq("//") || comment.starts_with("//") => {}
-                [toplevel, name, ..] if toplevel.to_lowercase().as_str() == "toplevel" => {
-                    root_name = name.replace("\"", "").replace(";", "").to_string();
+        for (file, prefix, statement) in statements {
+            let name = match &statement {
+                Statement::TopLevel { name } => {
+                    root = Some(name.to_owned());
+                    continue;
                 }
-                [name, op, args @ ..] if op.as_str().to_lowercase() == "not" => {
-                    let name = name.replace("\"", "").replace(";", "");
-                    if self.lookup_table.contains_key(&name) {
-                        panic!("Name of Gate {} already in use.", name)
+                Statement::Gate { name, .. } | Statement::BasicEvent { name, .. } => name,
+                Statement::Param { .. } => continue,
+                Statement::Include { .. } => unreachable!(),
+            };
+            if !defined.insert(name.text.to_owned()) {
+                errors.push(ParseError::new(
+                    &file,
+                    name.span,
+                    &name.text,
+                    ParseErrorKind::DuplicateName(name.text.to_owned()),
+                ));
+                continue;
+            }
+
+            match statement {
+                Statement::Gate { name, op, args } => {
+                    let op_name = op.text.to_lowercase();
+                    if let Err(kind) = check_gate(&op_name, args.len()) {
+                        errors.push(ParseError::new(&file, op.span, &op.text, kind));
+                        continue;
                     }
-                    let args = args
-                        .iter()
-                        .filter_map(|a| {
-                            if a.eq(";") {
-                                None
-                            } else {
-                                Some(a.replace("\"", "").replace(";", ""))
-                            }
-                        })
-                        .collect_vec();
-                    let nid = self.new_id();
-                    let node =
-                        Node::PlaceHolder(name.to_owned(), op.to_lowercase().to_string(), args);
-                    self.add_node(name.to_string(), node, nid);
-                }
-                [_name, op, _args @ ..]
-                    if op.as_str().to_lowercase() == "csp"
-                        || op.as_str().to_lowercase() == "wsp"
-                        || op.as_str().to_lowercase() == "hsp"
-                        || op.as_str().to_lowercase() == "pand"
-                        || op.as_str().to_lowercase() == "seq"
-                        || op.as_str().to_lowercase() == "fdep" =>
-                {
-                    panic!(
-                        "Unsupported type of gate: {}. Is {} a Static FT?",
-                        op.as_str(),
-                        filename
-                    )
-                }
-                [name, op, args @ ..]
-                    if op.as_str().to_lowercase() == "or"
-                        || op.as_str().to_lowercase() == "and"
-                        || op.as_str().to_lowercase() == "xor"
-                        || op.as_str().contains("of") =>
-                {
-                    let name = name.replace("\"", "").replace(";", "");
-                    if self.lookup_table.contains_key(&name) {
-                        panic!("Name of Gate '{}' already in use.", name)
+                    if op_name == "fdep" {
+                        fdeps.push((file, name, op, args));
+                        continue;
                     }
-                    let args = args
-                        .iter()
-                        .filter_map(|a| {
-                            if a.eq(";") {
-                                None
-                            } else {
-                                Some(a.replace("\"", "").replace(";", ""))
-                            }
-                        })
-                        .collect_vec();
+                    references.extend(args.iter().map(|a| (file.to_owned(), a.to_owned())));
+                    let arg_names = args.iter().map(|a| a.text.to_owned()).collect_vec();
 
-                    if simplify {
-                        if args.len() == 1 {
-                            replace_mapper.insert(name.clone(), args.first().unwrap().to_string());
-                            if root_name == name {
-                                root_name = args.first().unwrap().to_string();
-                            }
-                        } else {
+                    if simplify && op_name != "not" && arg_names.len() == 1 {
+                        replace_mapper.insert(name.text, arg_names[0].to_owned());
+                    } else {
+                        self.add_placeholder(
+                            name.text,
+                            op_name,
+                            arg_names,
+                            (
+                                file.to_owned(),
+                                op.span,
+                                args.iter().map(|a| a.span).collect(),
+                            ),
+                        );
+                    }
+                }
+                Statement::BasicEvent { name, attrs } => {
+                    let lookup = |param: &str| {
+                        scoped_name(param, &prefix, &parameters).map(|p| parameters[&p])
+                    };
+                    match parse_basic_event(&file, &name, &attrs, &lookup) {
+                        Ok(be) => {
                             let nid = self.new_id();
-                            let node = Node::PlaceHolder(
-                                name.to_owned(),
-                                op.to_lowercase().to_string(),
-                                args,
-                            );
-                            self.add_node(name.to_string(), node, nid);
+                            let node = Node::BasicEvent(name.text.to_owned(), be);
+                            self.add_node(name.text, node, nid);
                         }
-                    } else {
-                        let nid = self.new_id();
-                        let node =
-                            Node::PlaceHolder(name.to_owned(), op.to_lowercase().to_string(), args);
-                        self.add_node(name.to_string(), node, nid);
+                        Err(errs) => errors.extend(errs),
                     }
                 }
-                [name, args @ ..] => {
-                    let (name, be) = parse_basic_event(name, args);
-                    let nid = self.new_id();
-                    let node = Node::BasicEvent(name.to_owned(), be);
-                    self.add_node(name.to_string(), node, nid);
+                Statement::TopLevel { .. }
+                | Statement::Param { .. }
+                | Statement::Include { .. } => {
+                    unreachable!()
                 }
-                _ => {}
-            };
+            }
+        }
+
+        let fdep_names: HashSet<&String> = fdeps.iter().map(|(_, name, _, _)| &name.text).collect();
+        let fdep_args = fdeps.iter().flat_map(|(file, _, _, args)| {
+            args.iter().map(move |a| (file.to_owned(), a.to_owned()))
+        });
+        for (file, arg) in references.into_iter().chain(fdep_args) {
+            if fdep_names.contains(&arg.text) {
+                errors.push(ParseError::new(
+                    &file,
+                    arg.span,
+                    &arg.text,
+                    ParseErrorKind::InvalidDependency(format!(
+                        "'{}' is an FDEP gate, it can not be used as an event",
+                        arg.text
+                    )),
+                ));
+            } else if !defined.contains(&arg.text) {
+                errors.push(ParseError::new(
+                    &file,
+                    arg.span,
+                    &arg.text,
+                    ParseErrorKind::UndefinedReference(arg.text.to_owned()),
+                ));
+            }
+        }
+
+        let root = root.unwrap_or(Token {
+            text: String::from("System"),
+            quoted: false,
+            span: Span::default(),
+        });
+        if fdep_names.contains(&root.text) {
+            errors.push(ParseError::new(
+                filename,
+                root.span,
+                &root.text,
+                ParseErrorKind::InvalidDependency(format!(
+                    "'{}' is an FDEP gate, it can not be the top level event",
+                    root.text
+                )),
+            ));
+        } else if !defined.contains(&root.text) {
+            errors.push(ParseError::new(
+                filename,
+                root.span,
+                &root.text,
+                ParseErrorKind::UndefinedReference(root.text.to_owned()),
+            ));
+        }
+
+        // Chains of simplified gates must end in a node.
+        for alias in replace_mapper.keys() {
+            let mut target = alias;
+            let mut steps = 0;
+            while let Some(next) = replace_mapper.get(target) {
+                target = next;
+                steps += 1;
+                if steps > replace_mapper.len() {
+                    errors.push(ParseError::new(
+                        filename,
+                        Span::default(),
+                        alias,
+                        ParseErrorKind::Syntax(format!("cyclic definition of gate '{alias}'")),
+                    ));
+                    break;
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            errors = self.eliminate_fdeps(&fdeps, &replace_mapper, &defined);
+        }
+        if !errors.is_empty() {
+            errors.sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+            return Err(ParseErrors(errors));
+        }
+
+        let mut root_name = root.text;
+        while let Some(next) = replace_mapper.get(&root_name) {
+            root_name = next.to_owned();
         }
         if simplify {
             self.preprocess_placeholders(replace_mapper);
         };
-        root_name
+        Ok(root_name)
+    }
+
+    /// Rewrite the FDEP gates into static gates. Each dependent event `D` becomes the gate
+    /// `D = OR(T_1, ..., T_n, D_own)`, where `T_i` are the triggers of the FDEPs it depends on,
+    /// and `D_own` is the basic event with the own failure of `D`.
+    /// Without spare gates every basic event is active, so `D` fails as soon as a trigger or
+    /// itself fails, and the rewrite is exact. It is not when:
+    ///  - the dependent event is a gate, or a basic event with repairs, which would stay failed
+    ///    after the trigger is repaired.
+    ///  - the trigger depends on the dependent event itself, as the rewrite creates a cycle.
+    ///
+    /// In these cases the FT is not changed and the errors are returned.
+    fn eliminate_fdeps(
+        &mut self,
+        fdeps: &[FunctionalDependency],
+        replace_mapper: &HashMap<String, String>,
+        defined: &HashSet<String>,
+    ) -> Vec<ParseError> {
+        let mut errors = vec![];
+        // Triggers of each dependent event, in the order they appear in the file.
+        let mut dependents: Vec<(&Token, Vec<Trigger>, &Token)> = vec![];
+        for (file, name, op, args) in fdeps {
+            let (trigger, deps) = args.split_first().unwrap();
+            for dep in deps {
+                let node = self
+                    .lookup_table
+                    .get(&dep.text)
+                    .map(|nid| &self.nodes[*nid]);
+                let reason = match node {
+                    Some(Node::BasicEvent(_, be)) if be.repair_mode().is_some() => {
+                        Some("it has repairs")
+                    }
+                    Some(Node::BasicEvent(_, _)) => None,
+                    _ => Some("only basic events can be dependent events"),
+                };
+                if let Some(reason) = reason {
+                    errors.push(ParseError::new(
+                        file,
+                        dep.span,
+                        &dep.text,
+                        ParseErrorKind::InvalidDependency(format!(
+                            "'{}' can not depend on '{}' in FDEP '{}': {reason}",
+                            dep.text, trigger.text, name.text
+                        )),
+                    ));
+                    continue;
+                }
+                match dependents.iter_mut().find(|(d, _, _)| d.text == dep.text) {
+                    Some((_, triggers, _)) => triggers.push((file, trigger)),
+                    None => dependents.push((dep, vec![(file, trigger)], op)),
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return errors;
+        }
+
+        // Graph of the model by name, including the edges added by the rewrite.
+        let mut children: HashMap<&str, Vec<&str>> = HashMap::new();
+        for node in self.nodes.iter() {
+            if let Node::PlaceHolder(name, _, args) = node {
+                children.insert(name, args.iter().map(|a| a.as_str()).collect());
+            }
+        }
+        for (alias, target) in replace_mapper.iter() {
+            children.insert(alias, vec![target]);
+        }
+        for (dep, triggers, _) in dependents.iter() {
+            children.insert(
+                &dep.text,
+                triggers.iter().map(|(_, t)| t.text.as_str()).collect(),
+            );
+        }
+        for (dep, triggers, _) in dependents.iter() {
+            for (file, trigger) in triggers {
+                let mut seen = HashSet::new();
+                let mut to_visit = vec![trigger.text.as_str()];
+                while let Some(current) = to_visit.pop() {
+                    if current == dep.text {
+                        errors.push(ParseError::new(
+                            file,
+                            trigger.span,
+                            &trigger.text,
+                            ParseErrorKind::InvalidDependency(format!(
+                                "the trigger '{}' depends on its dependent event '{}'",
+                                trigger.text, dep.text
+                            )),
+                        ));
+                        break;
+                    }
+                    if seen.insert(current) {
+                        to_visit.extend(children.get(current).into_iter().flatten());
+                    }
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return errors;
+        }
+
+        for (dep, triggers, op) in dependents {
+            let file = triggers[0].0;
+            let mut own = format!("{}_own", dep.text);
+            while defined.contains(&own) || self.lookup_table.contains_key(&own) {
+                own.push('_');
+            }
+            let nid = self.lookup_table.remove(&dep.text).unwrap();
+            if let Node::BasicEvent(_, be) = &self.nodes[nid] {
+                self.nodes[nid] = Node::BasicEvent(own.to_owned(), be.to_owned());
+            }
+            self.lookup_table.insert(own.to_owned(), nid);
+
+            let mut arg_spans = triggers.iter().map(|(_, t)| t.span).collect_vec();
+            arg_spans.push(dep.span);
+            let mut args = triggers
+                .into_iter()
+                .map(|(_, t)| t.text.to_owned())
+                .collect_vec();
+            args.push(own);
+            self.add_placeholder(
+                dep.text.to_owned(),
+                String::from("or"),
+                args,
+                (file.to_owned(), op.span, arg_spans),
+            );
+        }
+        errors
     }
 
     /// Method to make a preprocess of the placeholders, updating the nodes that point to
     /// unnecesary gates.
-    fn preprocess_placeholders(&mut self, mapper: HashMap<String, String>) {
+    pub(crate) fn preprocess_placeholders(&mut self, mapper: HashMap<String, String>) {
         let mut corrected_mapper: HashMap<String, String> = mapper
             .clone()
             .into_iter()
@@ -252,9 +1078,62 @@ impl FaultTreeNormalizer<String> {
             .collect_vec();
     }
 
+    /// Depth-first search over the placeholders, that reports the gates that depend on
+    /// themselves. Each cycle is reported at the argument that closes it.
+    fn find_cycles(&self) -> Vec<ParseError> {
+        // 0: not visited, 1: in the current path, 2: done.
+        let mut state = vec![0u8; self.nodes.len()];
+        let mut errors = vec![];
+        for start in self.nodes.indices() {
+            if state[start.index()] != 0 {
+                continue;
+            }
+            state[start.index()] = 1;
+            // Each node is paired with the position of its next argument to visit.
+            let mut stack = vec![(start, 0)];
+            while let Some((nid, i)) = stack.pop() {
+                let args = match &self.nodes[nid] {
+                    Node::PlaceHolder(_, _, args) => args.as_slice(),
+                    _ => &[],
+                };
+                let Some(arg) = args.get(i) else {
+                    state[nid.index()] = 2;
+                    continue;
+                };
+                stack.push((nid, i + 1));
+                let Some(&c) = self.lookup_table.get(arg) else {
+                    continue;
+                };
+                match state[c.index()] {
+                    0 => {
+                        state[c.index()] = 1;
+                        stack.push((c, 0));
+                    }
+                    1 => {
+                        let (file, span) = match self.sources.get(&nid) {
+                            Some((file, _, arg_spans)) => {
+                                (file.as_str(), arg_spans.get(i).copied().unwrap_or_default())
+                            }
+                            None => ("<generated>", Span::default()),
+                        };
+                        errors.push(ParseError::new(
+                            file,
+                            span,
+                            arg,
+                            ParseErrorKind::Syntax(format!("cyclic definition of gate '{arg}'")),
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        errors
+    }
+
     /// Checks all the placeholders on the nodes Vector, then replaces each one
-    /// with the correct node.
-    pub fn fill_placeholders(&mut self, keep_vot: bool) {
+    /// with the correct node. The gates can not depend on themselves.
+    /// Errors are reported at the position where the placeholder was defined, if known.
+    pub fn fill_placeholders(&mut self, keep_vot: bool) -> Result<(), ParseErrors> {
         let placeholder_nids = self
             .nodes
             .iter_enumerated()
@@ -267,19 +1146,41 @@ impl FaultTreeNormalizer<String> {
             })
             .collect_vec();
 
+        let mut errors = self.find_cycles();
         for nid in placeholder_nids {
+            let (file, op_span, arg_spans) = self
+                .sources
+                .get(&nid)
+                .cloned()
+                .unwrap_or_else(|| (String::from("<generated>"), Span::default(), vec![]));
             let nn = self.nodes.get(nid).unwrap();
             match &nn {
                 Node::PlaceHolder(_name, op, args) => {
+                    let mut missing = false;
                     let args_ids = args
                         .iter()
-                        .map(|a| {
-                            *self
-                                .lookup_table
-                                .get(a)
-                                .unwrap_or_else(|| panic!("Cant find argument {}", a))
+                        .enumerate()
+                        .filter_map(|(i, a)| match self.lookup_table.get(a) {
+                            Some(aid) => Some(*aid),
+                            None => {
+                                missing = true;
+                                errors.push(ParseError::new(
+                                    &file,
+                                    arg_spans.get(i).copied().unwrap_or_default(),
+                                    a,
+                                    ParseErrorKind::UndefinedReference(a.to_owned()),
+                                ));
+                                None
+                            }
                         })
                         .collect_vec();
+                    if missing {
+                        continue;
+                    }
+                    if let Err(kind) = check_gate(op, args_ids.len()) {
+                        errors.push(ParseError::new(&file, op_span, op, kind));
+                        continue;
+                    }
 
                     let node: Node<String> = if op.eq("or") {
                         Node::Or(args_ids)
@@ -288,76 +1189,70 @@ impl FaultTreeNormalizer<String> {
                     } else if op.eq("and") {
                         Node::And(args_ids)
                     } else if op.eq("not") {
-                        assert_eq!(
-                            args_ids.len(),
-                            1,
-                            "Something is wrong with the negated gate"
-                        );
                         Node::Not(args_ids.first().unwrap().to_owned())
-                    } else if op.contains("of") {
-                        let split = op.split("of").collect_vec();
-                        let k = match split[0].parse::<usize>() {
-                            Err(e) => panic!("Error {} on the VOT gate {}.", e, op),
-                            Ok(v) => v,
-                        };
-                        let n = match split[1].parse::<usize>() {
-                            Err(e) => panic!("Error {} on the VOT gate {}.", e, op),
-                            Ok(v) => v,
-                        };
-
-                        if args.len() != n || k < 1 || k > n {
-                            panic!("Error on the VOT gate {}. Check K and N in the definition of the Gate (<K>of<N>).", op)
-                        }
+                    } else {
+                        // Only VOT gates are left after `check_gate`.
+                        let (k, n) = parse_vot(op, args_ids.len())
+                            .expect("VOT gate already validated by check_gate");
 
                         if n == k {
                             Node::And(args_ids)
                         } else if k == 1 {
                             Node::Or(args_ids)
                         } else {
-                            let clause_size = n - k;
-
                             if keep_vot {
                                 Node::Vot(k as i64, args_ids)
                             } else {
-                                let mut roots = args.clone();
                                 let mut aux_ids = vec![];
-                                let mut new_args;
-                                while roots.len() > clause_size {
-                                    let elem = roots.pop().unwrap();
-                                    for subset in roots.iter().combinations(clause_size) {
-                                        new_args = vec![*self.lookup_table.get(&elem).unwrap()];
-                                        new_args.extend(subset.iter().map(|s| {
-                                            *self.lookup_table.get(s.to_owned()).unwrap()
-                                        }));
-                                        let aux_gid = self.new_id();
-                                        aux_ids.push(aux_gid);
-                                        let aux_gate = Node::Or(new_args);
-                                        self.add_node(
-                                            format!("aux_gate_{}", aux_gid),
-                                            aux_gate,
-                                            aux_gid,
-                                        )
-                                    }
+                                for clause in vot_clauses(k, &args_ids) {
+                                    let aux_gid = self.new_id();
+                                    aux_ids.push(aux_gid);
+                                    let aux_gate = Node::Or(clause);
+                                    self.add_node(
+                                        format!("aux_gate_{}", aux_gid),
+                                        aux_gate,
+                                        aux_gid,
+                                    )
                                 }
                                 Node::And(aux_ids)
                             }
                         }
-                    } else {
-                        panic!("Something went wrong while processing the gates.")
                     };
                     self.update_roots(node, nid);
                 }
                 _ => panic!("This should not happen"),
             }
         }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ParseErrors(errors))
+        }
     }
 
     /// Public method to read the FT from the File, apply simplifications and replace the placeholders.
-    pub fn read_from_file(&mut self, filename: &str, simplify: bool) {
-        let root_name: String = self.read_file(filename, simplify);
-        self.fill_placeholders(false);
-        let root_id = self.lookup_table.get(&root_name).unwrap();
-        self.root_id = *root_id;
+    pub fn read_from_file(&mut self, filename: &str, simplify: bool) -> Result<(), ParseErrors> {
+        let root_name: String = self.read_file(filename, simplify)?;
+        self.fill_placeholders(false)?;
+        self.root_id = *self
+            .lookup_table
+            .get(&root_name)
+            .expect("Top level checked while reading the file");
+        Ok(())
+    }
+
+    /// Add a placeholder, keeping track of where it was defined to report errors.
+    pub(crate) fn add_placeholder(
+        &mut self,
+        name: String,
+        op: String,
+        args: Vec<String>,
+        source: PlaceHolderSource,
+    ) {
+        let nid = self.new_id();
+        let node = Node::PlaceHolder(name.to_owned(), op, args);
+        self.sources.insert(nid, source);
+        self.add_node(name, node, nid);
     }
 
     /// Add the node to the fields of the struct.
@@ -0,0 +1,684 @@
+use itertools::Itertools;
+use std::fs::File;
+use std::io::Write;
+
+use crate::errors::{ParseError, ParseErrorKind, Span};
+use crate::fault_tree::FaultTree;
+use crate::nodes::Node;
+
+/// A token of a GALILEO file, with its position.
+/// Quotes are removed from the text of quoted tokens.
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub text: String,
+    pub quoted: bool,
+    pub span: Span,
+}
+
+impl Token {
+    fn is_symbol(&self, symbol: &str) -> bool {
+        !self.quoted && self.text == symbol
+    }
+
+    pub fn is_eq(&self) -> bool {
+        self.is_symbol("=")
+    }
+
+    pub fn is_semicolon(&self) -> bool {
+        self.is_symbol(";")
+    }
+}
+
+/// A statement of a GALILEO file. Statements are separated by `;` or by the end of the line.
+#[derive(Debug, Clone)]
+pub enum Statement {
+    /// `toplevel <name>;`
+    TopLevel { name: Token },
+    /// `param <name> = <expression>;`
+    Param { name: Token, value: Token },
+    /// `include "<file>" [as <namespace>];`
+    Include {
+        path: Token,
+        namespace: Option<Token>,
+    },
+    /// `<name> <op> <args>...;`
+    Gate {
+        name: Token,
+        op: Token,
+        args: Vec<Token>,
+    },
+    /// `<name> <key>=<value>...;`
+    BasicEvent {
+        name: Token,
+        attrs: Vec<(Token, Token)>,
+    },
+}
+
+/// Splits a line into tokens. Everything after `//` (outside of quotes) is a comment.
+fn tokenize_line(
+    file: &str,
+    line: &str,
+    line_number: usize,
+    errors: &mut Vec<ParseError>,
+) -> Vec<Token> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let span = Span::new(line_number, i + 1);
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            break;
+        } else if c == '"' {
+            let start = i + 1;
+            let mut end = start;
+            while end < chars.len() && chars[end] != '"' {
+                end += 1;
+            }
+            let text: String = chars[start..end].iter().collect();
+            if end == chars.len() {
+                errors.push(ParseError::new(
+                    file,
+                    span,
+                    &text,
+                    ParseErrorKind::Syntax(String::from("unterminated quoted name")),
+                ));
+            }
+            tokens.push(Token {
+                text,
+                quoted: true,
+                span,
+            });
+            i = end + 1;
+        } else if c == '=' || c == ';' {
+            tokens.push(Token {
+                text: c.to_string(),
+                quoted: false,
+                span,
+            });
+            i += 1;
+        } else {
+            let start = i;
+            let is_separator = |j: usize| {
+                chars[j].is_whitespace()
+                    || matches!(chars[j], '=' | ';' | '"')
+                    || (chars[j] == '/' && chars.get(j + 1) == Some(&'/'))
+            };
+            while i < chars.len() && !is_separator(i) {
+                i += 1;
+            }
+            tokens.push(Token {
+                text: chars[start..i].iter().collect(),
+                quoted: false,
+                span,
+            });
+        }
+    }
+    tokens
+}
+
+/// Join the tokens of an expression into a single token, at the position of the first one.
+fn join_tokens(tokens: &[Token]) -> Option<Token> {
+    let first = tokens.first()?;
+    Some(Token {
+        text: tokens.iter().map(|t| t.text.as_str()).join(" "),
+        quoted: false,
+        span: first.span,
+    })
+}
+
+/// Parse the `<key>=<value>` pairs of a basic event.
+/// A value can span several tokens, up to the key of the next attribute.
+fn parse_attributes(
+    file: &str,
+    tokens: &[Token],
+    errors: &mut Vec<ParseError>,
+) -> Option<Vec<(Token, Token)>> {
+    let mut attrs = vec![];
+    let mut i = 0;
+    while let Some(key) = tokens.get(i) {
+        if key.is_eq() || !tokens.get(i + 1).is_some_and(|t| t.is_eq()) {
+            errors.push(ParseError::new(
+                file,
+                key.span,
+                &key.text,
+                ParseErrorKind::Syntax(String::from("expected an attribute '<key>=<value>'")),
+            ));
+            return None;
+        }
+        let end = (i + 2..tokens.len())
+            .find(|j| tokens.get(j + 1).is_some_and(|t| t.is_eq()))
+            .unwrap_or(tokens.len());
+        let value = &tokens[i + 2..end];
+        match join_tokens(value) {
+            Some(value_token) if !value.iter().any(|t| t.is_eq()) => {
+                attrs.push((key.to_owned(), value_token))
+            }
+            _ => {
+                errors.push(ParseError::new(
+                    file,
+                    key.span,
+                    &key.text,
+                    ParseErrorKind::Syntax(format!("missing value for attribute '{}'", key.text)),
+                ));
+                return None;
+            }
+        }
+        i = end;
+    }
+    Some(attrs)
+}
+
+/// Build a statement from the tokens between two separators.
+fn parse_statement(
+    file: &str,
+    tokens: &[Token],
+    errors: &mut Vec<ParseError>,
+) -> Option<Statement> {
+    match tokens {
+        [] => None,
+        [toplevel, rest @ ..] if !toplevel.quoted && toplevel.text.to_lowercase() == "toplevel" => {
+            match rest {
+                [name] => Some(Statement::TopLevel {
+                    name: name.to_owned(),
+                }),
+                _ => {
+                    errors.push(ParseError::new(
+                        file,
+                        toplevel.span,
+                        &toplevel.text,
+                        ParseErrorKind::Syntax(String::from("expected 'toplevel <name>'")),
+                    ));
+                    None
+                }
+            }
+        }
+        [param, name, eq, value @ ..]
+            if !param.quoted && param.text.to_lowercase() == "param" && eq.is_eq() =>
+        {
+            match join_tokens(value) {
+                Some(value) => Some(Statement::Param {
+                    name: name.to_owned(),
+                    value,
+                }),
+                None => {
+                    errors.push(ParseError::new(
+                        file,
+                        name.span,
+                        &name.text,
+                        ParseErrorKind::Syntax(format!(
+                            "missing value for parameter '{}'",
+                            name.text
+                        )),
+                    ));
+                    None
+                }
+            }
+        }
+        [include, path, rest @ ..]
+            if !include.quoted && include.text.to_lowercase() == "include" && path.quoted =>
+        {
+            match rest {
+                [] => Some(Statement::Include {
+                    path: path.to_owned(),
+                    namespace: None,
+                }),
+                [kw_as, namespace] if kw_as.is_symbol("as") => Some(Statement::Include {
+                    path: path.to_owned(),
+                    namespace: Some(namespace.to_owned()),
+                }),
+                _ => {
+                    errors.push(ParseError::new(
+                        file,
+                        include.span,
+                        &include.text,
+                        ParseErrorKind::Syntax(String::from(
+                            "expected 'include \"<file>\" [as <namespace>]'",
+                        )),
+                    ));
+                    None
+                }
+            }
+        }
+        [name, ..] if name.is_eq() => {
+            errors.push(ParseError::new(
+                file,
+                name.span,
+                &name.text,
+                ParseErrorKind::Syntax(String::from("expected a name")),
+            ));
+            None
+        }
+        [name, rest @ ..] if rest.get(1).is_some_and(|t| t.is_eq()) => {
+            parse_attributes(file, rest, errors).map(|attrs| Statement::BasicEvent {
+                name: name.to_owned(),
+                attrs,
+            })
+        }
+        [name] => {
+            errors.push(ParseError::new(
+                file,
+                name.span,
+                &name.text,
+                ParseErrorKind::MissingDistribution(name.text.to_owned()),
+            ));
+            None
+        }
+        [name, op, args @ ..] => match args.iter().find(|a| a.is_eq()) {
+            Some(eq) => {
+                errors.push(ParseError::new(
+                    file,
+                    eq.span,
+                    &eq.text,
+                    ParseErrorKind::Syntax(String::from("unexpected '=' in gate definition")),
+                ));
+                None
+            }
+            None => Some(Statement::Gate {
+                name: name.to_owned(),
+                op: op.to_owned(),
+                args: args.to_vec(),
+            }),
+        },
+    }
+}
+
+/// Read the statements of a GALILEO model.
+/// Syntax errors are collected in `errors`, and the statements containing them are skipped.
+pub fn parse_statements(file: &str, text: &str, errors: &mut Vec<ParseError>) -> Vec<Statement> {
+    let mut statements = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let tokens = tokenize_line(file, line, i + 1, errors);
+        for chunk in tokens.split(|t| t.is_semicolon()) {
+            if let Some(statement) = parse_statement(file, chunk, errors) {
+                statements.push(statement);
+            }
+        }
+    }
+    statements
+}
+
+impl FaultTree<String> {
+    /// Write the FT in GALILEO format. Every node is written, with the gates first and the
+    /// basic events after them, keeping the names the nodes had in the input file.
+    pub fn to_dft(&self) -> String {
+        let quote = |nid| format!("\"{}\"", self.name_of(nid));
+        let mut lines = vec![format!("toplevel {};", quote(self.root_id))];
+
+        let gates = self.nodes.iter_enumerated().filter_map(|(nid, n)| {
+            let op = match n {
+                Node::Not(_) => String::from("not"),
+                Node::And(_) => String::from("and"),
+                Node::Or(_) => String::from("or"),
+                Node::Xor(_) => String::from("xor"),
+                Node::Vot(k, args) => format!("{}of{}", k, args.len()),
+                Node::BasicEvent(_, _) => return None,
+                Node::PlaceHolder(_, _, _) => panic!("Cant write placeholder node to GALILEO."),
+            };
+            let args = n.children().into_iter().map(quote).join(" ");
+            Some(format!("{} {} {};", quote(nid), op, args))
+        });
+        lines.extend(gates);
+
+        let basic_events = self.nodes.iter().filter_map(|n| match n {
+            Node::BasicEvent(name, be) => Some(format!("\"{}\" {};", name, be)),
+            _ => None,
+        });
+        lines.extend(basic_events);
+
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    /// Save the FT in GALILEO format.
+    pub fn save_to_dft(&self, filename: &str) {
+        let mut f = File::create(filename).expect("unable to create file");
+        f.write_all(self.to_dft().as_bytes())
+            .expect("Error writing the GALILEO model to file");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formula::CNFFormat;
+    use crate::solver::{BddSolver, Solver};
+    use crate::test_utils::{ft_from_dft, read_model};
+    use std::collections::HashMap;
+
+    const MODEL: &str = r#"toplevel "T";
+"T" or "G1" "G2" "G3" "N";
+"G1" and "A" "B";
+"G2" xor "C" "D";
+"G3" 2of3 "A" "E" "F";
+"N" not "G4";
+"G4" or "H" "W";
+"A" prob=0.1;
+"B" lambda=0.2;
+"C" lambda=0.3 repair=0.5;
+"D" lambda=0.05 interval=2 repair_time=0.25;
+"E" lambda=0.4 phases=3;
+"F" lambda=0.5 shape=2;
+"H" prob=0.9;
+"W" scale=49 shape=0.5;
+"#;
+
+    fn tep(ft: &FaultTree<String>, unav: bool) -> f64 {
+        BddSolver::new("bdd")
+            .try_compute(ft, CNFFormat::MC21, 1.5, 60, None, false, unav)
+            .unwrap()
+    }
+
+    /// Line, column, token and kind of the errors found while reading a GALILEO model.
+    fn read_errors(name: &str, text: &str) -> Vec<(usize, usize, String, ParseErrorKind)> {
+        read_model(name, text, |f| {
+            FaultTree::new_from_file(f, false, false, &HashMap::new())
+        })
+        .err()
+        .expect("the model has errors")
+        .0
+        .into_iter()
+        .map(|e| (e.line, e.column, e.token, e.kind))
+        .collect()
+    }
+
+    /// Write the files of a model split with includes to the directory `dir`, which must be
+    /// unique between tests, and read the first one.
+    fn read_files(
+        dir: &str,
+        files: &[(&str, &str)],
+    ) -> Result<FaultTree<String>, crate::errors::ParseErrors> {
+        let dir = std::env::temp_dir().join(dir);
+        std::fs::create_dir_all(&dir).expect("unable to create the test directory");
+        for (name, text) in files {
+            std::fs::write(dir.join(name), text).expect("unable to write the test model");
+        }
+        let main = dir.join(files[0].0);
+        let ft = FaultTree::new_from_file(main.to_str().unwrap(), false, false, &HashMap::new());
+        let _ = std::fs::remove_dir_all(&dir);
+        ft
+    }
+
+    fn lines(text: &str) -> Vec<&str> {
+        text.lines().sorted().collect()
+    }
+
+    #[test]
+    fn parse_write_parse_keeps_structure_and_tep() {
+        let ft = ft_from_dft("coyan_galileo_roundtrip_1.dft", MODEL);
+        let text = ft.to_dft();
+        let read_back = ft_from_dft("coyan_galileo_roundtrip_2.dft", &text);
+
+        assert_eq!(read_back.to_dft(), text);
+        assert!(text.contains("\"W\" scale=49 shape=0.5;"));
+        for unav in [false, true] {
+            assert!((tep(&ft, unav) - tep(&read_back, unav)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn weibull_is_written_with_its_scale() {
+        let ft = ft_from_dft(
+            "coyan_galileo_weibull.dft",
+            "toplevel \"W\";\n\"W\" shape=2 lambda=0.25;\n",
+        );
+        assert_eq!(ft.to_dft(), "toplevel \"W\";\n\"W\" scale=4 shape=2;\n");
+    }
+
+    #[test]
+    fn errors_are_located_and_collected_in_one_pass() {
+        let text = "toplevel \"T\";\n\"T\" or \"A\" \"B\";\n\"A\" prob=0.1 foo=2;\n\"B\" lambda=0.1 repair=-1;\n\"C\" pand \"A\" \"B\";\n";
+        let errors = read_errors("coyan_galileo_errors.dft", text);
+        let located = errors
+            .iter()
+            .map(|(line, column, token, _)| (*line, *column, token.as_str()))
+            .collect_vec();
+        assert_eq!(located, vec![(3, 14, "foo"), (4, 23, "-1"), (5, 5, "pand")]);
+        assert!(matches!(errors[0].3, ParseErrorKind::UnknownAttribute(_)));
+        assert!(matches!(errors[1].3, ParseErrorKind::InvalidAttribute(_)));
+        assert!(matches!(errors[2].3, ParseErrorKind::UnsupportedGate(_)));
+
+        // The references are checked once every statement is read, and are also collected.
+        let text =
+            "toplevel \"T\";\n\"T\" or \"A\" \"X\";\n\"A\" prob=0.1;\n\"C\" and \"Y\" \"A\";\n";
+        let located = read_errors("coyan_galileo_references.dft", text)
+            .into_iter()
+            .map(|(line, column, token, kind)| {
+                assert_eq!(kind, ParseErrorKind::UndefinedReference(token.clone()));
+                (line, column, token)
+            })
+            .collect_vec();
+        assert_eq!(
+            located,
+            vec![(2, 12, String::from("X")), (4, 9, String::from("Y"))]
+        );
+    }
+
+    #[test]
+    fn cyclic_gates_are_rejected() {
+        let text = "toplevel \"G1\";\n\"G1\" and \"G2\" \"A\";\n\"G2\" or \"G1\" \"B\";\n\"A\" prob=0.1;\n\"B\" prob=0.2;\n";
+        let errors = read_errors("coyan_galileo_cycle.dft", text);
+        assert_eq!(
+            errors,
+            vec![(
+                3,
+                9,
+                String::from("G1"),
+                ParseErrorKind::Syntax(String::from("cyclic definition of gate 'G1'"))
+            )]
+        );
+    }
+
+    #[test]
+    fn fdep_is_rewritten_as_or_of_trigger_and_dependent() {
+        let fdep = ft_from_dft(
+            "coyan_galileo_fdep.dft",
+            "toplevel \"T\";\n\"T\" and \"D\" \"E\";\n\"F\" fdep \"A\" \"D\";\n\"A\" lambda=0.1;\n\"D\" lambda=0.2;\n\"E\" lambda=0.3;\n",
+        );
+        let manual = ft_from_dft(
+            "coyan_galileo_fdep_manual.dft",
+            "toplevel \"T\";\n\"T\" and \"D\" \"E\";\n\"D\" or \"A\" \"D_own\";\n\"A\" lambda=0.1;\n\"D_own\" lambda=0.2;\n\"E\" lambda=0.3;\n",
+        );
+        assert_eq!(fdep.to_dft(), manual.to_dft());
+        let t = 1.5_f64;
+        let (p_a, p_d, p_e) = (
+            1.0 - (-0.1 * t).exp(),
+            1.0 - (-0.2 * t).exp(),
+            1.0 - (-0.3 * t).exp(),
+        );
+        let expected = (1.0 - (1.0 - p_a) * (1.0 - p_d)) * p_e;
+        assert!((tep(&fdep, false) - expected).abs() < 1e-12);
+        assert!((tep(&fdep, false) - tep(&manual, false)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn fdep_is_rejected_when_the_rewrite_is_not_exact() {
+        let text = "toplevel \"T\";\n\"T\" and \"D\" \"G\" \"R\";\n\"F\" fdep \"A\" \"G\" \"R\";\n\"G\" or \"A\" \"E\";\n\"A\" lambda=0.1;\n\"E\" lambda=0.3;\n\"D\" lambda=0.3;\n\"R\" lambda=0.3 repair=1;\n";
+        let errors = read_errors("coyan_galileo_fdep_gate.dft", text);
+        let located = errors
+            .iter()
+            .map(|(line, column, token, kind)| {
+                assert!(matches!(kind, ParseErrorKind::InvalidDependency(_)));
+                (*line, *column, token.as_str())
+            })
+            .collect_vec();
+        assert_eq!(located, vec![(3, 14, "G"), (3, 18, "R")]);
+
+        // The trigger T2 depends on E, so E = OR(T2, E_own) would be a cycle.
+        let text = "toplevel \"T\";\n\"T\" and \"T2\" \"D\";\n\"F\" fdep \"T2\" \"E\";\n\"T2\" or \"E\" \"A\";\n\"A\" lambda=0.1;\n\"E\" lambda=0.3;\n\"D\" lambda=0.3;\n";
+        let errors = read_errors("coyan_galileo_fdep_cycle.dft", text);
+        assert_eq!(
+            errors,
+            vec![(
+                3,
+                10,
+                String::from("T2"),
+                ParseErrorKind::InvalidDependency(String::from(
+                    "the trigger 'T2' depends on its dependent event 'E'"
+                ))
+            )]
+        );
+    }
+
+    const PUMP: &str = "toplevel \"P\";\nparam rate = 0.1;\n\"P\" or \"M\" \"V\";\n\"M\" lambda=rate;\n\"V\" lambda=2*rate;\n";
+
+    #[test]
+    fn includes_are_read_into_their_namespaces() {
+        let main = "toplevel \"T\";\ninclude \"pump.dft\";\ninclude \"pump.dft\" as backup;\n\"T\" or \"M\" \"S\";\n\"S\" and \"pump\" \"backup\";\n\"M\" prob=0.05;\n";
+        let ft = read_files(
+            "coyan_galileo_includes",
+            &[("main.dft", main), ("pump.dft", PUMP)],
+        )
+        .unwrap();
+        // The top level of each included file is a gate named after its namespace.
+        let flat = ft_from_dft(
+            "coyan_galileo_includes_flat.dft",
+            r#"toplevel "T";
+"T" or "M" "S";
+"S" and "pump" "backup";
+"pump" and "pump.P";
+"pump.P" or "pump.M" "pump.V";
+"backup" and "backup.P";
+"backup.P" or "backup.M" "backup.V";
+"M" prob=0.05;
+"pump.M" lambda=0.1;
+"pump.V" lambda=0.2;
+"backup.M" lambda=0.1;
+"backup.V" lambda=0.2;
+"#,
+        );
+        assert_eq!(lines(&ft.to_dft()), lines(&flat.to_dft()));
+        assert!((tep(&ft, false) - tep(&flat, false)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn included_names_can_not_be_redefined() {
+        // Both the names of the included file and its namespace are taken.
+        for (i, (main, token)) in [
+            ("toplevel \"T\";\ninclude \"pump.dft\";\n\"T\" or \"pump\" \"pump.M\";\n\"pump.M\" prob=0.05;\n", "pump.M"),
+            ("toplevel \"T\";\ninclude \"pump.dft\";\n\"T\" or \"pump\" \"M\";\n\"pump\" prob=0.05;\n\"M\" prob=0.05;\n", "pump"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let errors = read_files(
+                &format!("coyan_galileo_collision_{i}"),
+                &[("main.dft", main), ("pump.dft", PUMP)],
+            )
+            .err()
+            .expect("the model has errors");
+            let error = &errors.errors()[0];
+            assert_eq!((error.line, error.column, error.token.as_str()), (4, 1, token));
+            assert_eq!(error.kind, ParseErrorKind::DuplicateName(token.to_owned()));
+        }
+    }
+
+    #[test]
+    fn include_cycles_are_rejected() {
+        let a = "toplevel \"A\";\ninclude \"b.dft\";\n\"A\" or \"b\" \"X\";\n\"X\" prob=0.1;\n";
+        let b = "toplevel \"B\";\ninclude \"a.dft\";\n\"B\" or \"a\" \"Y\";\n\"Y\" prob=0.1;\n";
+        let errors = read_files("coyan_galileo_include_cycle", &[("a.dft", a), ("b.dft", b)])
+            .err()
+            .expect("the model has errors");
+        let error = &errors.errors()[0];
+        assert!(error.file.ends_with("b.dft"));
+        assert_eq!(
+            (error.line, error.column, error.token.as_str()),
+            (2, 9, "a.dft")
+        );
+        assert!(matches!(error.kind, ParseErrorKind::InvalidInclude(_)));
+    }
+
+    const PARAMS: &str = r#"param base = 0.01;
+param pump = 2*base;
+param p = 1/(1+exp(0));
+toplevel "T";
+"T" or "A" "B";
+"A" lambda=pump;
+"B" prob=p/5;
+"#;
+
+    fn read_with_parameters(
+        name: &str,
+        parameters: &[(&str, f64)],
+    ) -> Result<FaultTree<String>, crate::errors::ParseErrors> {
+        let parameters = parameters
+            .iter()
+            .map(|&(name, value)| (name.to_owned(), value))
+            .collect();
+        read_model(name, PARAMS, |f| {
+            FaultTree::new_from_file(f, false, false, &parameters)
+        })
+    }
+
+    #[test]
+    fn parameters_are_evaluated_and_overridden() {
+        let ft = read_with_parameters("coyan_galileo_params.dft", &[]).unwrap();
+        let text = ft.to_dft();
+        assert!(text.contains("\"A\" lambda=0.02;"));
+        assert!(text.contains("\"B\" prob=0.1;"));
+        // The parameters that use an overridden one are evaluated with its new value.
+        let ft = read_with_parameters("coyan_galileo_params_set.dft", &[("base", 0.1)]).unwrap();
+        assert!(ft.to_dft().contains("\"A\" lambda=0.2;"));
+    }
+
+    #[test]
+    fn undeclared_parameters_can_not_be_set() {
+        let errors = read_with_parameters("coyan_galileo_params_unknown.dft", &[("nope", 1.0)])
+            .err()
+            .expect("the model has errors");
+        assert_eq!(
+            errors.errors()[0].kind,
+            ParseErrorKind::UndefinedReference("nope".to_owned())
+        );
+    }
+
+    #[test]
+    fn parameter_errors_are_located() {
+        let errors = read_errors(
+            "coyan_galileo_param_errors.dft",
+            r#"param a = b;
+param b = a;
+param c = 2*;
+toplevel "T";
+"T" or "A" "B";
+"A" lambda=q;
+"B" prob=a;
+"#,
+        );
+        let cyclic = |name: &str| {
+            ParseErrorKind::UnsupportedExpression(format!(
+                "parameter '{name}' is defined cyclically"
+            ))
+        };
+        assert_eq!(
+            errors,
+            vec![
+                (1, 11, "b".to_owned(), cyclic("a")),
+                (2, 11, "a".to_owned(), cyclic("b")),
+                (
+                    3,
+                    11,
+                    "2*".to_owned(),
+                    ParseErrorKind::UnsupportedExpression(
+                        "unexpected end of the expression".to_owned()
+                    )
+                ),
+                (
+                    6,
+                    12,
+                    "q".to_owned(),
+                    ParseErrorKind::UndefinedReference("q".to_owned())
+                ),
+                (
+                    7,
+                    10,
+                    "a".to_owned(),
+                    ParseErrorKind::UndefinedReference("a".to_owned())
+                ),
+            ]
+        );
+    }
+}
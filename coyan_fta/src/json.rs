@@ -0,0 +1,295 @@
+use index_vec::IndexVec;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fs::{File, read_to_string};
+use std::io::Write;
+use std::sync::atomic::AtomicUsize;
+
+use crate::errors::{ParseError, ParseErrorKind, ParseErrors, Span};
+use crate::fault_tree::FaultTree;
+use crate::nodes::{Node, NodeId};
+
+/// Version of the JSON model format. It changes when older models can no longer be read,
+/// and models with a different version are rejected. Optional fields, such as the `uncertainty`
+/// of a basic event, are added without changing it.
+pub const JSON_FORMAT_VERSION: u32 = 1;
+
+/// A node of the JSON model, optionally with the name of the gate.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonNode {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    node: Node<String>,
+}
+
+/// JSON model of a FT. The schema is in `coyan_fta/schema/fault_tree.schema.json`.
+/// Nodes refer to their children by their position in `nodes`, starting from 0.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonFaultTree {
+    version: u32,
+    root: NodeId,
+    nodes: Vec<JsonNode>,
+}
+
+impl Serialize for FaultTree<String> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let nodes = self
+            .nodes
+            .iter_enumerated()
+            .map(|(nid, node)| JsonNode {
+                name: match node {
+                    Node::BasicEvent(_, _) => None,
+                    _ => self.names.get(&nid).cloned(),
+                },
+                node: node.clone(),
+            })
+            .collect();
+        JsonFaultTree {
+            version: JSON_FORMAT_VERSION,
+            root: self.root_id,
+            nodes,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for FaultTree<String> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let model = JsonFaultTree::deserialize(deserializer)?;
+        if model.version != JSON_FORMAT_VERSION {
+            return Err(D::Error::custom(format!(
+                "unsupported version {}, expected {}",
+                model.version, JSON_FORMAT_VERSION
+            )));
+        }
+        let n_nodes = model.nodes.len();
+        if model.root.index() >= n_nodes {
+            return Err(D::Error::custom(format!(
+                "root {} is not a node",
+                model.root.index()
+            )));
+        }
+        for (i, json_node) in model.nodes.iter().enumerate() {
+            if let Some(c) = json_node
+                .node
+                .children()
+                .iter()
+                .find(|c| c.index() >= n_nodes)
+            {
+                return Err(D::Error::custom(format!(
+                    "node {i} has the child {}, which is not a node",
+                    c.index()
+                )));
+            }
+            if let Node::And(args) | Node::Or(args) | Node::Xor(args) = &json_node.node
+                && args.is_empty()
+            {
+                return Err(D::Error::custom(format!(
+                    "node {i} is a gate without arguments"
+                )));
+            }
+            if let Node::Vot(k, args) = &json_node.node
+                && (*k < 1 || *k as usize > args.len())
+            {
+                return Err(D::Error::custom(format!(
+                    "node {i} is a VOT gate with K={k} and {} arguments",
+                    args.len()
+                )));
+            }
+        }
+        if let Some(i) = find_cycle(&model.nodes, model.root) {
+            return Err(D::Error::custom(format!(
+                "node {i} is part of a cycle, the model must be acyclic"
+            )));
+        }
+
+        let mut ft = FaultTree::empty();
+        ft.root_id = model.root;
+        ft.node_counter = AtomicUsize::new(n_nodes);
+        let mut nodes = vec![];
+        for (i, json_node) in model.nodes.into_iter().enumerate() {
+            if let Some(name) = json_node.name {
+                ft.names.insert(NodeId::new(i), name);
+            }
+            nodes.push(json_node.node);
+        }
+        ft.nodes = IndexVec::from_vec(nodes);
+        Ok(ft)
+    }
+}
+
+/// Depth-first search from the root, that returns a node on a cycle if there is one.
+/// The children are assumed to be valid indices.
+fn find_cycle(nodes: &[JsonNode], root: NodeId) -> Option<usize> {
+    // 0: not visited, 1: in the current path, 2: finished.
+    let mut state = vec![0u8; nodes.len()];
+    let mut stack = vec![(root.index(), 0)];
+    state[root.index()] = 1;
+    while let Some((i, next)) = stack.pop() {
+        let children = nodes[i].node.children();
+        match children.get(next) {
+            None => state[i] = 2,
+            Some(c) => {
+                stack.push((i, next + 1));
+                match state[c.index()] {
+                    0 => {
+                        state[c.index()] = 1;
+                        stack.push((c.index(), 0));
+                    }
+                    1 => return Some(c.index()),
+                    _ => {}
+                }
+            }
+        }
+    }
+    None
+}
+
+impl FaultTree<String> {
+    /// Generate a FT from a file in the JSON model format. VOT gates are expanded.
+    pub fn new_from_json(filename: &str, negate_or: bool) -> Result<Self, ParseErrors> {
+        let text = read_to_string(filename).map_err(|e| {
+            ParseError::new(
+                filename,
+                Span::default(),
+                filename,
+                ParseErrorKind::Io(e.to_string()),
+            )
+        })?;
+        let mut ft: FaultTree<String> = serde_json::from_str(&text).map_err(|e| {
+            // The position is already in the error, so it is removed from the message.
+            let msg = e.to_string();
+            let position = format!(" at line {} column {}", e.line(), e.column());
+            ParseError::new(
+                filename,
+                Span::new(e.line(), e.column()),
+                "",
+                ParseErrorKind::Syntax(msg.strip_suffix(&position).unwrap_or(&msg).to_owned()),
+            )
+        })?;
+        ft.expand_vot();
+        ft.negate_or = negate_or;
+        Ok(ft)
+    }
+
+    /// Write the FT in the JSON model format.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Error serializing the FT to JSON")
+    }
+
+    /// Save the FT in the JSON model format.
+    pub fn save_to_json(&self, filename: &str) {
+        let mut f = File::create(filename).expect("unable to create file");
+        f.write_all(self.to_json().as_bytes())
+            .expect("Error writing the JSON model to file");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::errors::ParseErrorKind;
+    use crate::fault_tree::FaultTree;
+    use crate::formula::CNFFormat;
+    use crate::solver::{BddSolver, Solver};
+    use crate::test_utils::{ft_from_dft, read_model};
+
+    const MODEL: &str = r#"toplevel "T";
+"T" or "G1" "G2" "G3" "N";
+"G1" and "A" "B";
+"G2" xor "C" "D";
+"G3" 2of3 "A" "E" "F";
+"N" not "G4";
+"G4" or "H" "W";
+"A" prob=0.1 ef=3;
+"B" lambda=0.2 gamma=2;
+"C" lambda=0.3 repair=0.5;
+"D" lambda=0.05 interval=2 repair_time=0.25;
+"E" lambda=0.4 phases=3;
+"F" lambda=0.5 shape=2;
+"H" prob=0.9 low=0.8 high=1;
+"W" scale=49 shape=0.5;
+"#;
+
+    fn tep(ft: &FaultTree<String>) -> f64 {
+        BddSolver::new("bdd")
+            .try_compute(ft, CNFFormat::MC21, 1.5, 60, None, false, false)
+            .unwrap()
+    }
+
+    fn from_json(name: &str, text: &str) -> Result<FaultTree<String>, crate::errors::ParseErrors> {
+        read_model(name, text, |f| FaultTree::new_from_json(f, false))
+    }
+
+    /// Message of the error found while reading a JSON model.
+    fn json_error(name: &str, text: &str) -> String {
+        let errors = from_json(name, text).err().expect("the model has errors");
+        match &errors.errors()[0].kind {
+            ParseErrorKind::Syntax(msg) => msg.clone(),
+            kind => panic!("unexpected error {kind:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_write_parse_keeps_structure_and_tep() {
+        let ft = ft_from_dft("coyan_json_round_trip.dft", MODEL);
+        let text = ft.to_json();
+        assert!(text.contains("uncertainty"));
+        let read = from_json("coyan_json_round_trip.json", &text).unwrap();
+        assert_eq!(read.to_json(), text);
+        assert_eq!(read.to_dft(), ft.to_dft());
+        assert!((tep(&read) - tep(&ft)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn invalid_models_are_rejected() {
+        let model = |version: u32, root: usize, nodes: &str| {
+            format!(
+                r#"{{"version": {version}, "root": {root}, "nodes": [{nodes},
+                {{"node": {{"basic_event": ["A", {{"distribution": {{"discrete": 0.1}}}}]}}}},
+                {{"node": {{"basic_event": ["B", {{"distribution": {{"continuous": 0.2}}}}]}}}}]}}"#
+            )
+        };
+        for (name, text, expected) in [
+            (
+                "version",
+                model(2, 0, r#"{"name": "T", "node": {"or": [1, 2]}}"#),
+                "unsupported version 2, expected 1",
+            ),
+            (
+                "root",
+                model(1, 3, r#"{"name": "T", "node": {"or": [1, 2]}}"#),
+                "root 3 is not a node",
+            ),
+            (
+                "child",
+                model(1, 0, r#"{"name": "T", "node": {"or": [1, 3]}}"#),
+                "node 0 has the child 3, which is not a node",
+            ),
+            (
+                "empty",
+                model(1, 0, r#"{"name": "T", "node": {"and": []}}"#),
+                "node 0 is a gate without arguments",
+            ),
+            (
+                "k",
+                model(1, 0, r#"{"name": "T", "node": {"vot": [3, [1, 2]]}}"#),
+                "node 0 is a VOT gate with K=3 and 2 arguments",
+            ),
+            (
+                "cycle",
+                model(
+                    1,
+                    0,
+                    r#"{"name": "T", "node": {"or": [1, 3]}},
+                    {"name": "G", "node": {"and": [0, 2]}}"#,
+                ),
+                "part of a cycle",
+            ),
+        ] {
+            let msg = json_error(&format!("coyan_json_invalid_{name}.json"), &text);
+            assert!(msg.contains(expected), "{name}: {msg}");
+        }
+    }
+}
@@ -1,7 +1,22 @@
+pub mod bdd;
+pub mod cutsets;
+pub mod dot;
+pub mod errors;
+pub mod expression;
 pub mod fault_tree;
 pub mod fault_tree_normalizer;
 pub mod formula;
+pub mod galileo;
+pub mod json;
+pub mod mef;
 pub mod modularizer;
+pub mod montecarlo;
+pub mod nnf;
 pub mod nodes;
 pub mod preproc;
+pub mod sensitivity;
 pub mod solver;
+#[cfg(test)]
+mod test_utils;
+pub mod uncertainty;
+pub mod wmc;
@@ -0,0 +1,910 @@
+use itertools::Itertools;
+use roxmltree::{Document, Node as XmlNode};
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, read_to_string};
+use std::io::Write;
+
+use crate::errors::{ParseError, ParseErrorKind, ParseErrors, Span};
+use crate::fault_tree::FaultTree;
+use crate::fault_tree_normalizer::{FaultTreeNormalizer, check_gate};
+use crate::montecarlo::normal_quantile;
+use crate::nodes::{BasicEvent, Distribution, Node, NodeId, RepairMode, Uncertainty};
+
+/// Elements of a formula that reference another event by name.
+const EVENT_REFS: [&str; 4] = ["gate", "basic-event", "house-event", "event"];
+
+/// Elements that can be inside a definition, but are not part of the formula.
+const METADATA: [&str; 2] = ["label", "attributes"];
+
+/// Random deviates, that give the uncertainty of the parameter of a basic event.
+const DEVIATES: [&str; 4] = [
+    "lognormal-deviate",
+    "gamma-deviate",
+    "beta-deviate",
+    "uniform-deviate",
+];
+
+/// Max depth when evaluating parameters, to catch cyclic definitions.
+const MAX_DEPTH: usize = 64;
+
+/// First child element of a definition that is not metadata.
+fn formula_child<'a, 'input>(node: XmlNode<'a, 'input>) -> Option<XmlNode<'a, 'input>> {
+    node.children()
+        .find(|c| c.is_element() && !METADATA.contains(&c.tag_name().name()))
+}
+
+/// Child elements of a formula or expression.
+fn element_children<'a, 'input>(node: XmlNode<'a, 'input>) -> Vec<XmlNode<'a, 'input>> {
+    node.children().filter(|c| c.is_element()).collect_vec()
+}
+
+/// Child elements of `node` if it is a `tag` element.
+fn args_of<'a, 'input>(node: XmlNode<'a, 'input>, tag: &str) -> Option<Vec<XmlNode<'a, 'input>>> {
+    (node.tag_name().name() == tag).then(|| element_children(node))
+}
+
+/// Rate and number of phases of the expression written for an Erlang basic event,
+/// `1 - exp(-lambda t) * sum_{n < phases} (lambda t)^n / n!`, see [be_expression].
+fn erlang_expression<'a, 'input>(
+    expr: XmlNode<'a, 'input>,
+) -> Option<(XmlNode<'a, 'input>, usize)> {
+    let [one, product] = args_of(expr, "sub")?[..] else {
+        return None;
+    };
+    let [exp, sum] = args_of(product, "mul")?[..] else {
+        return None;
+    };
+    let [neg] = args_of(exp, "exp")?[..] else {
+        return None;
+    };
+    let [lambda_t] = args_of(neg, "neg")?[..] else {
+        return None;
+    };
+    let [lambda, time] = args_of(lambda_t, "mul")?[..] else {
+        return None;
+    };
+    let terms = args_of(sum, "add")?;
+    let is_term = |n: usize, term: &XmlNode| {
+        args_of(*term, "div")
+            .and_then(|args| args.first().and_then(|pow| args_of(*pow, "pow")))
+            .is_some_and(|args| {
+                args.get(1)
+                    .is_some_and(|e| e.attribute("value") == Some(&n.to_string()))
+            })
+    };
+    let is_erlang = one.attribute("value") == Some("1")
+        && time.tag_name().name() == "system-mission-time"
+        && !terms.is_empty()
+        && terms.iter().enumerate().all(|(n, term)| is_term(n, term));
+    is_erlang.then_some((lambda, terms.len()))
+}
+
+/// Reader of an Open-PSA MEF file. Gates are read into placeholders of the normalizer,
+/// nested formulas become gates named `<gate>.<i>`.
+struct MefReader<'a, 'input> {
+    file: &'a str,
+    doc: &'a Document<'input>,
+    simplify: bool,
+    parameters: HashMap<String, XmlNode<'a, 'input>>,
+    /// Values given by the user to the parameters, replacing their definition.
+    overrides: &'a HashMap<String, f64>,
+    errors: Vec<ParseError>,
+    /// Every name defined in the file, even if its definition has errors.
+    defined: HashSet<String>,
+    /// Names of the gates defined with `define-gate`, candidates for the top event.
+    gates: Vec<String>,
+    references: Vec<(String, Span)>,
+    replace_mapper: HashMap<String, String>,
+}
+
+impl<'a, 'input> MefReader<'a, 'input> {
+    fn span(&self, node: XmlNode) -> Span {
+        let pos = self.doc.text_pos_at(node.range().start);
+        Span::new(pos.row as usize, pos.col as usize)
+    }
+
+    fn error(&mut self, node: XmlNode, token: &str, kind: ParseErrorKind) {
+        self.errors
+            .push(ParseError::new(self.file, self.span(node), token, kind));
+    }
+
+    /// Name attribute of a definition or reference.
+    fn name_of(&mut self, node: XmlNode) -> Option<String> {
+        match node.attribute("name") {
+            Some(name) => Some(name.to_owned()),
+            None => {
+                self.error(
+                    node,
+                    node.tag_name().name(),
+                    ParseErrorKind::Syntax(String::from("missing attribute 'name'")),
+                );
+                None
+            }
+        }
+    }
+
+    fn define(&mut self, node: XmlNode, name: &str) -> bool {
+        if self.defined.insert(name.to_owned()) {
+            true
+        } else {
+            self.error(node, name, ParseErrorKind::DuplicateName(name.to_owned()));
+            false
+        }
+    }
+
+    /// Numeric value of an attribute.
+    fn number(&mut self, node: XmlNode, attr: &str) -> Option<f64> {
+        let value = node.attribute(attr).unwrap_or_default();
+        match value.parse::<f64>() {
+            Ok(v) => Some(v),
+            Err(_) => {
+                self.error(node, value, ParseErrorKind::InvalidNumber(value.to_owned()));
+                None
+            }
+        }
+    }
+
+    /// Evaluate a constant expression: numbers, parameters and arithmetic.
+    fn eval(&mut self, node: XmlNode, depth: usize) -> Option<f64> {
+        let tag = node.tag_name().name();
+        if depth > MAX_DEPTH {
+            self.error(
+                node,
+                tag,
+                ParseErrorKind::UnsupportedExpression(String::from(
+                    "parameters are defined cyclically",
+                )),
+            );
+            return None;
+        }
+        match tag {
+            "float" | "int" => self.number(node, "value"),
+            "bool" => Some(if node.attribute("value") == Some("true") {
+                1.0
+            } else {
+                0.0
+            }),
+            "parameter" => {
+                let name = self.name_of(node)?;
+                if let Some(v) = self.overrides.get(&name) {
+                    return Some(*v);
+                }
+                match self.parameters.get(&name).copied() {
+                    Some(def) => match formula_child(def) {
+                        Some(expr) => self.eval(expr, depth + 1),
+                        None => {
+                            self.error(
+                                def,
+                                &name,
+                                ParseErrorKind::UnsupportedExpression(format!(
+                                    "parameter '{name}' has no value"
+                                )),
+                            );
+                            None
+                        }
+                    },
+                    None => {
+                        self.error(
+                            node,
+                            &name,
+                            ParseErrorKind::UndefinedReference(name.clone()),
+                        );
+                        None
+                    }
+                }
+            }
+            _ if DEVIATES.contains(&tag) => self.deviate(node, depth).map(|(mean, _)| mean),
+            "neg" | "add" | "sub" | "mul" | "div" => {
+                let args = element_children(node)
+                    .into_iter()
+                    .map(|c| self.eval(c, depth + 1))
+                    .collect::<Option<Vec<f64>>>()?;
+                let Some((fst, rest)) = args.split_first() else {
+                    self.error(
+                        node,
+                        tag,
+                        ParseErrorKind::Arity(format!("'{tag}' needs at least one argument")),
+                    );
+                    return None;
+                };
+                Some(match tag {
+                    "neg" => -fst,
+                    "add" => fst + rest.iter().sum::<f64>(),
+                    "sub" => rest.iter().fold(*fst, |acc, v| acc - v),
+                    "mul" => fst * rest.iter().product::<f64>(),
+                    _ => rest.iter().fold(*fst, |acc, v| acc / v),
+                })
+            }
+            _ => {
+                self.error(
+                    node,
+                    tag,
+                    ParseErrorKind::UnsupportedExpression(format!("'{tag}'")),
+                );
+                None
+            }
+        }
+    }
+
+    /// Read a random deviate: its mean, used as the value of the parameter, and its uncertainty.
+    /// The lognormal is given by the mean, the error factor and its confidence level, or by the
+    /// mean and standard deviation of the underlying normal.
+    fn deviate(&mut self, node: XmlNode, depth: usize) -> Option<(f64, Uncertainty)> {
+        let tag = node.tag_name().name();
+        let n_args: &[usize] = if tag == "lognormal-deviate" {
+            &[2, 3]
+        } else {
+            &[2]
+        };
+        let children = element_children(node);
+        if !n_args.contains(&children.len()) {
+            self.error(
+                node,
+                tag,
+                ParseErrorKind::Arity(format!(
+                    "'{tag}' expects {} arguments, found {}",
+                    n_args.iter().join(" or "),
+                    children.len()
+                )),
+            );
+            return None;
+        }
+        let args = children
+            .into_iter()
+            .map(|c| self.eval(c, depth + 1))
+            .collect::<Option<Vec<f64>>>()?;
+        let z_95 = normal_quantile(0.95);
+        let deviate = match (tag, &args[..]) {
+            ("lognormal-deviate", &[mean, ef, level])
+                if mean > 0.0 && ef >= 1.0 && level > 0.5 && level < 1.0 =>
+            {
+                let sigma = ef.ln() / normal_quantile(level);
+                let error_factor = (sigma * z_95).exp();
+                Ok((mean, Uncertainty::Lognormal { error_factor }))
+            }
+            ("lognormal-deviate", &[mu, sigma]) if sigma >= 0.0 => {
+                let error_factor = (sigma * z_95).exp();
+                let mean = (mu + sigma * sigma / 2.0).exp();
+                Ok((mean, Uncertainty::Lognormal { error_factor }))
+            }
+            ("lognormal-deviate", _) => Err(
+                "'lognormal-deviate' needs a positive mean, an error factor of at least 1 and a level in (0.5, 1)",
+            ),
+            ("gamma-deviate", &[k, theta]) if k > 0.0 && theta > 0.0 => {
+                Ok((k * theta, Uncertainty::Gamma { shape: k }))
+            }
+            ("gamma-deviate", _) => Err("'gamma-deviate' needs a positive shape and scale"),
+            ("beta-deviate", &[alpha, beta]) if alpha > 0.0 && beta > 0.0 => {
+                Ok((alpha / (alpha + beta), Uncertainty::Beta { alpha }))
+            }
+            ("beta-deviate", _) => Err("'beta-deviate' needs positive shapes"),
+            (_, &[low, high]) if 0.0 <= low && low <= high => {
+                Ok(((low + high) / 2.0, Uncertainty::Uniform { low, high }))
+            }
+            _ => Err("'uniform-deviate' needs bounds 0 <= min <= max"),
+        };
+        match deviate {
+            Ok(deviate) => Some(deviate),
+            Err(msg) => {
+                self.error(
+                    node,
+                    tag,
+                    ParseErrorKind::InvalidAttribute(String::from(msg)),
+                );
+                None
+            }
+        }
+    }
+
+    /// Evaluate the arguments of a time dependent expression, checking how many there are.
+    fn eval_args(&mut self, node: XmlNode, n_args: &[usize]) -> Option<Vec<f64>> {
+        let tag = node.tag_name().name();
+        let children = element_children(node);
+        if !n_args.contains(&children.len()) {
+            self.error(
+                node,
+                tag,
+                ParseErrorKind::Arity(format!(
+                    "'{tag}' expects {} arguments, found {}",
+                    n_args.iter().join(" or "),
+                    children.len()
+                )),
+            );
+            return None;
+        }
+        // The last argument of the time dependent expressions is the mission time,
+        // which is given to Coyan as the timepoint.
+        children[..children.len() - 1]
+            .iter()
+            .map(|c| self.eval(*c, 0))
+            .collect()
+    }
+
+    /// Translate the expression of a basic event into its distribution. If the parameter of the
+    /// distribution is a random deviate, it gives its uncertainty.
+    fn basic_event(&mut self, def: XmlNode, name: &str) -> Option<BasicEvent> {
+        let mut be = self.distribution(def, name)?;
+        let expr = formula_child(def)?;
+        let parameter = match expr.tag_name().name() {
+            "exponential" | "Weibull" | "periodic-test" => element_children(expr).first().copied(),
+            "glm" => element_children(expr).get(1).copied(),
+            _ => Some(expr),
+        };
+        if let Some(node) = parameter.filter(|n| DEVIATES.contains(&n.tag_name().name())) {
+            let (_, uncertainty) = self.deviate(node, 0)?;
+            if matches!(uncertainty, Uncertainty::Beta { .. })
+                && !matches!(be.distribution(), Distribution::Discrete(_))
+            {
+                self.error(
+                    node,
+                    "beta-deviate",
+                    ParseErrorKind::InvalidAttribute(String::from(
+                        "'beta-deviate' is only supported for probabilities",
+                    )),
+                );
+                return None;
+            }
+            be.with_uncertainty(uncertainty);
+        }
+        Some(be)
+    }
+
+    fn distribution(&mut self, def: XmlNode, name: &str) -> Option<BasicEvent> {
+        let Some(expr) = formula_child(def) else {
+            self.error(
+                def,
+                name,
+                ParseErrorKind::MissingDistribution(name.to_owned()),
+            );
+            return None;
+        };
+        match expr.tag_name().name() {
+            "exponential" => {
+                let args = self.eval_args(expr, &[2])?;
+                Some(BasicEvent::new_with_rate(args[0]))
+            }
+            "glm" => {
+                let args = self.eval_args(expr, &[4])?;
+                if args[0] != 0.0 {
+                    self.error(
+                        expr,
+                        "glm",
+                        ParseErrorKind::UnsupportedExpression(String::from(
+                            "'glm' with a probability of failure on demand",
+                        )),
+                    );
+                    return None;
+                }
+                let mut be = BasicEvent::new_with_rate(args[1]);
+                be.with_repair_mode(RepairMode::Monitored(args[2]));
+                Some(be)
+            }
+            "Weibull" => {
+                let args = self.eval_args(expr, &[4])?;
+                if args[2] != 0.0 {
+                    self.error(
+                        expr,
+                        "Weibull",
+                        ParseErrorKind::UnsupportedExpression(String::from(
+                            "'Weibull' with a time shift",
+                        )),
+                    );
+                    return None;
+                }
+                Some(BasicEvent::new_weibull(args[1], args[0]))
+            }
+            "periodic-test" => {
+                let args = self.eval_args(expr, &[4, 5])?;
+                let (lambda, repair_time, interval) = match args.len() {
+                    3 => (args[0], 0.0, args[1]),
+                    _ => (args[0], 1.0 / args[1], args[2]),
+                };
+                let mut be = BasicEvent::new_with_rate(lambda);
+                be.with_repair_mode(RepairMode::PeriodicallyTested(interval, repair_time));
+                Some(be)
+            }
+            "sub" if erlang_expression(expr).is_some() => {
+                let (lambda, phases) = erlang_expression(expr)?;
+                let lambda = self.eval(lambda, 0)?;
+                Some(BasicEvent::new_erlang(lambda, phases))
+            }
+            _ => self.eval(expr, 0).map(BasicEvent::new_with_prob),
+        }
+    }
+
+    /// Read a formula into a placeholder named `name`. Nested formulas are read recursively.
+    fn formula(&mut self, ft_norm: &mut FaultTreeNormalizer<String>, name: &str, node: XmlNode) {
+        let tag = node.tag_name().name();
+        let op = match tag {
+            "and" | "or" | "xor" | "not" => tag.to_owned(),
+            "atleast" => {
+                let Some(k) = self.number(node, "min") else {
+                    return;
+                };
+                format!("{}of{}", k, element_children(node).len())
+            }
+            "null" => String::from("and"),
+            "constant" => {
+                let value = node.attribute("value") == Some("true");
+                let be = if value {
+                    BasicEvent::const_true()
+                } else {
+                    BasicEvent::const_false()
+                };
+                let nid = ft_norm.new_id();
+                ft_norm.add_node(name.to_owned(), Node::BasicEvent(name.to_owned(), be), nid);
+                return;
+            }
+            _ if EVENT_REFS.contains(&tag) => {
+                // A gate that is just another event.
+                if let Some(arg) = self.name_of(node) {
+                    self.references.push((arg.to_owned(), self.span(node)));
+                    if self.simplify {
+                        self.replace_mapper.insert(name.to_owned(), arg);
+                    } else {
+                        let source = (self.file.to_owned(), self.span(node), vec![self.span(node)]);
+                        ft_norm.add_placeholder(
+                            name.to_owned(),
+                            String::from("and"),
+                            vec![arg],
+                            source,
+                        );
+                    }
+                }
+                return;
+            }
+            _ => {
+                self.error(node, tag, ParseErrorKind::UnsupportedGate(tag.to_owned()));
+                return;
+            }
+        };
+
+        let children = element_children(node);
+        if tag == "null" && self.simplify && children.len() == 1 {
+            return self.formula(ft_norm, name, children[0]);
+        }
+        if let Err(kind) = check_gate(&op, children.len()) {
+            self.error(node, tag, kind);
+            return;
+        }
+
+        let mut args = vec![];
+        let mut arg_spans = vec![];
+        for (i, child) in children.into_iter().enumerate() {
+            let child_tag = child.tag_name().name();
+            let arg = if EVENT_REFS.contains(&child_tag) {
+                let Some(arg) = self.name_of(child) else {
+                    continue;
+                };
+                self.references.push((arg.to_owned(), self.span(child)));
+                arg
+            } else {
+                let arg = format!("{name}.{i}");
+                if !self.define(child, &arg) {
+                    continue;
+                }
+                self.formula(ft_norm, &arg, child);
+                arg
+            };
+            args.push(arg);
+            arg_spans.push(self.span(child));
+        }
+        let source = (self.file.to_owned(), self.span(node), arg_spans);
+        ft_norm.add_placeholder(name.to_owned(), op, args, source);
+    }
+}
+
+impl FaultTreeNormalizer<String> {
+    /// Read a FT in the Open-PSA Model Exchange Format.
+    /// The top event is the only gate that is not used by any other gate.
+    pub fn read_from_mef(&mut self, filename: &str, simplify: bool) -> Result<(), ParseErrors> {
+        let io_error = |msg: String| {
+            ParseError::new(filename, Span::default(), filename, ParseErrorKind::Io(msg))
+        };
+        let text = read_to_string(filename).map_err(|e| io_error(e.to_string()))?;
+        let doc = Document::parse(&text).map_err(|e| {
+            let pos = e.pos();
+            ParseError::new(
+                filename,
+                Span::new(pos.row as usize, pos.col as usize),
+                "",
+                ParseErrorKind::Syntax(e.to_string()),
+            )
+        })?;
+
+        let parameters = doc
+            .descendants()
+            .filter(|n| n.has_tag_name("define-parameter"))
+            .filter_map(|n| n.attribute("name").map(|name| (name.to_owned(), n)))
+            .collect();
+        let overrides = self.parameters().to_owned();
+        let mut reader = MefReader {
+            file: filename,
+            doc: &doc,
+            simplify,
+            parameters,
+            overrides: &overrides,
+            errors: vec![],
+            defined: HashSet::new(),
+            gates: vec![],
+            references: vec![],
+            replace_mapper: HashMap::new(),
+        };
+
+        for def in doc.descendants().filter(|n| n.is_element()) {
+            let tag = def.tag_name().name();
+            if !["define-gate", "define-basic-event", "define-house-event"].contains(&tag) {
+                continue;
+            }
+            let Some(name) = reader.name_of(def) else {
+                continue;
+            };
+            if !reader.define(def, &name) {
+                continue;
+            }
+            match tag {
+                "define-gate" => {
+                    reader.gates.push(name.to_owned());
+                    match formula_child(def) {
+                        Some(formula) => reader.formula(self, &name, formula),
+                        None => reader.error(
+                            def,
+                            &name,
+                            ParseErrorKind::Syntax(format!("gate '{name}' has no formula")),
+                        ),
+                    }
+                }
+                "define-basic-event" => {
+                    if let Some(be) = reader.basic_event(def, &name) {
+                        let nid = self.new_id();
+                        self.add_node(name.to_owned(), Node::BasicEvent(name, be), nid);
+                    }
+                }
+                _ => {
+                    // House events are constant, false if no value is given.
+                    let value =
+                        formula_child(def).is_some_and(|c| c.attribute("value") == Some("true"));
+                    let be = if value {
+                        BasicEvent::const_true()
+                    } else {
+                        BasicEvent::const_false()
+                    };
+                    let nid = self.new_id();
+                    self.add_node(name.to_owned(), Node::BasicEvent(name, be), nid);
+                }
+            }
+        }
+
+        for (arg, span) in reader.references.iter() {
+            if !reader.defined.contains(arg) {
+                reader.errors.push(ParseError::new(
+                    filename,
+                    *span,
+                    arg,
+                    ParseErrorKind::UndefinedReference(arg.to_owned()),
+                ));
+            }
+        }
+
+        let referenced: HashSet<&String> = reader.references.iter().map(|(r, _)| r).collect();
+        let tops = reader
+            .gates
+            .iter()
+            .filter(|g| !referenced.contains(g))
+            .cloned()
+            .collect_vec();
+        if tops.len() != 1 {
+            let msg = if tops.is_empty() {
+                String::from("no top gate found, every gate is used by another gate")
+            } else {
+                format!("several top gates found: {}", tops.join(", "))
+            };
+            reader.errors.push(ParseError::new(
+                filename,
+                Span::default(),
+                filename,
+                ParseErrorKind::Syntax(msg),
+            ));
+        }
+
+        for name in overrides.keys().sorted() {
+            if !reader.parameters.contains_key(name) {
+                reader.errors.push(ParseError::new(
+                    filename,
+                    Span::default(),
+                    name,
+                    ParseErrorKind::UndefinedReference(name.to_owned()),
+                ));
+            }
+        }
+
+        let MefReader {
+            mut errors,
+            replace_mapper,
+            ..
+        } = reader;
+        if !errors.is_empty() {
+            errors.sort_by_key(|e| (e.line, e.column));
+            return Err(ParseErrors(errors));
+        }
+
+        let mut root_name = tops[0].to_owned();
+        while let Some(next) = replace_mapper.get(&root_name) {
+            root_name = next.to_owned();
+        }
+        if simplify {
+            self.preprocess_placeholders(replace_mapper);
+        }
+        self.fill_placeholders(false)?;
+        self.root_id = *self
+            .lookup_table
+            .get(&root_name)
+            .expect("Top gate checked while reading the file");
+        Ok(())
+    }
+}
+
+/// Escape the characters that are not allowed in XML attributes.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// MEF expression of the parameter of a basic event, a random deviate if it is uncertain.
+fn parameter_expression(be: &BasicEvent) -> String {
+    let mean = be.parameter();
+    let floats = |values: &[f64]| {
+        values
+            .iter()
+            .map(|v| format!("<float value=\"{v}\"/>"))
+            .join("")
+    };
+    match be.uncertainty() {
+        None => floats(&[mean]),
+        Some(Uncertainty::Lognormal { error_factor }) => format!(
+            "<lognormal-deviate>{}</lognormal-deviate>",
+            floats(&[mean, *error_factor, 0.95])
+        ),
+        Some(Uncertainty::Gamma { shape }) => format!(
+            "<gamma-deviate>{}</gamma-deviate>",
+            floats(&[*shape, mean / shape])
+        ),
+        Some(Uncertainty::Beta { alpha }) => format!(
+            "<beta-deviate>{}</beta-deviate>",
+            floats(&[*alpha, alpha * (1.0 - mean) / mean])
+        ),
+        Some(Uncertainty::Uniform { low, high }) => {
+            format!(
+                "<uniform-deviate>{}</uniform-deviate>",
+                floats(&[*low, *high])
+            )
+        }
+    }
+}
+
+/// MEF expression of the distribution of a basic event.
+/// The uncertainty of an Erlang is not written, as its rate appears several times in the expression.
+/// The reader recognizes the expression of an Erlang, so it is read back with its phases.
+fn be_expression(be: &BasicEvent) -> String {
+    let parameter = parameter_expression(be);
+    match (be.distribution(), be.repair_mode()) {
+        (Distribution::Discrete(_), _) => parameter,
+        (Distribution::Continuous(_), None) => {
+            format!("<exponential>{parameter}<system-mission-time/></exponential>")
+        }
+        (Distribution::Continuous(_), Some(RepairMode::Monitored(mu))) => format!(
+            "<glm><float value=\"0\"/>{parameter}<float value=\"{mu}\"/><system-mission-time/></glm>"
+        ),
+        (Distribution::Weibull(shape, _), _) => format!(
+            "<Weibull>{parameter}<float value=\"{shape}\"/><float value=\"0\"/><system-mission-time/></Weibull>"
+        ),
+        (Distribution::Erlang(lambda, phases), _) => {
+            // There is no Erlang in the MEF, so its unreliability is written as an expression
+            // 1 - exp(-lambda t) * sum_{n < phases} (lambda t)^n / n!
+            let lambda_t = format!("<mul><float value=\"{lambda}\"/><system-mission-time/></mul>");
+            let terms = (0..*phases)
+                .scan(1.0, |factorial, n| {
+                    if n > 0 {
+                        *factorial *= n as f64;
+                    }
+                    Some(format!(
+                        "<div><pow>{lambda_t}<int value=\"{n}\"/></pow><float value=\"{factorial}\"/></div>"
+                    ))
+                })
+                .join("");
+            format!(
+                "<sub><float value=\"1\"/><mul><exp><neg>{lambda_t}</neg></exp><add>{terms}</add></mul></sub>"
+            )
+        }
+        (Distribution::Continuous(_), Some(RepairMode::PeriodicallyTested(t, t_r))) => {
+            format!(
+                "<periodic-test>{parameter}<float value=\"{}\"/><float value=\"{t}\"/><float value=\"{t}\"/><system-mission-time/></periodic-test>",
+                1.0 / t_r
+            )
+        }
+    }
+}
+
+impl FaultTree<String> {
+    /// MEF reference to a node.
+    fn mef_ref(&self, nid: NodeId) -> String {
+        let tag = if self.nodes[nid].is_gate() {
+            "gate"
+        } else {
+            "basic-event"
+        };
+        format!("<{tag} name=\"{}\"/>", escape(&self.name_of(nid)))
+    }
+
+    /// Write the FT in the Open-PSA Model Exchange Format.
+    /// Only the nodes reachable from the top event are written.
+    pub fn to_mef(&self, ft_name: &str) -> String {
+        let mut gates = vec![];
+        let mut basic_events = vec![];
+        let mut seen = HashSet::from([self.root_id]);
+        let mut to_process = vec![self.root_id];
+        while let Some(nid) = to_process.pop() {
+            let node = &self.nodes[nid];
+            let children = node.children();
+            let args = children.iter().map(|c| self.mef_ref(*c)).join("");
+            let formula = match node {
+                Node::BasicEvent(name, be) => {
+                    basic_events.push(format!(
+                        "    <define-basic-event name=\"{}\">{}</define-basic-event>",
+                        escape(name),
+                        be_expression(be)
+                    ));
+                    None
+                }
+                Node::Not(_) => Some(format!("<not>{args}</not>")),
+                Node::And(_) => Some(format!("<and>{args}</and>")),
+                Node::Or(_) => Some(format!("<or>{args}</or>")),
+                Node::Xor(_) => Some(format!("<xor>{args}</xor>")),
+                Node::Vot(k, _) => Some(format!("<atleast min=\"{k}\">{args}</atleast>")),
+                Node::PlaceHolder(_, _, _) => panic!("Cant write placeholder node to MEF."),
+            };
+            if let Some(formula) = formula {
+                gates.push(format!(
+                    "    <define-gate name=\"{}\">{}</define-gate>",
+                    escape(&self.name_of(nid)),
+                    formula
+                ));
+            }
+            for c in children.into_iter().rev() {
+                if seen.insert(c) {
+                    to_process.push(c);
+                }
+            }
+        }
+
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opsa-mef>\n  <define-fault-tree name=\"{}\">\n{}\n  </define-fault-tree>\n  <model-data>\n{}\n  </model-data>\n</opsa-mef>\n",
+            escape(ft_name),
+            gates.join("\n"),
+            basic_events.join("\n")
+        )
+    }
+
+    /// Save the FT in the Open-PSA Model Exchange Format.
+    pub fn save_to_mef(&self, filename: &str, ft_name: &str) {
+        let mut f = File::create(filename).expect("unable to create file");
+        f.write_all(self.to_mef(ft_name).as_bytes())
+            .expect("Error writing the MEF model to file");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formula::CNFFormat;
+    use crate::solver::{BddSolver, Solver};
+    use crate::test_utils::{ft_from_dft, ft_from_mef};
+
+    const MODEL: &str = r#"toplevel "T";
+"T" or "G1" "G2" "G3" "N";
+"G1" and "A" "B";
+"G2" xor "C" "D";
+"G3" 2of3 "A" "E" "F";
+"N" not "G4";
+"G4" or "H" "W";
+"A" prob=0.1;
+"B" lambda=0.2;
+"C" lambda=0.3 repair=0.5;
+"D" lambda=0.05 interval=2 repair_time=0.25;
+"E" lambda=0.4 phases=3;
+"F" scale=2 shape=2;
+"H" prob=0.9;
+"W" scale=49 shape=0.5;
+"#;
+
+    fn tep(ft: &FaultTree<String>, unav: bool) -> f64 {
+        BddSolver::new("bdd")
+            .try_compute(ft, CNFFormat::MC21, 1.5, 60, None, false, unav)
+            .unwrap()
+    }
+
+    fn lines(text: &str) -> Vec<&str> {
+        text.lines().sorted().collect()
+    }
+
+    #[test]
+    fn parse_write_parse_keeps_structure_and_tep() {
+        let ft = ft_from_dft("coyan_mef_roundtrip.dft", MODEL);
+        let text = ft.to_mef("FT");
+        let read = ft_from_mef("coyan_mef_roundtrip_1.xml", &text);
+        let read_back = ft_from_mef("coyan_mef_roundtrip_2.xml", &read.to_mef("FT"));
+
+        assert_eq!(read.to_mef("FT"), text);
+        assert_eq!(read_back.to_mef("FT"), text);
+        // The nodes are in another order, but the definitions are the same.
+        assert_eq!(lines(&read.to_dft()), lines(&ft.to_dft()));
+        for unav in [false, true] {
+            assert!((tep(&ft, unav) - tep(&read_back, unav)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn erlang_is_read_back_with_its_phases() {
+        let ft = ft_from_dft(
+            "coyan_mef_erlang.dft",
+            "toplevel \"T\";\n\"T\" and \"E\" \"F\";\n\"E\" lambda=0.4 phases=3;\n\"F\" lambda=1.5 phases=1;\n",
+        );
+        let read = ft_from_mef("coyan_mef_erlang.xml", &ft.to_mef("FT"));
+        assert_eq!(lines(&read.to_dft()), lines(&ft.to_dft()));
+    }
+
+    #[test]
+    fn house_events_parameters_and_atleast_are_read() {
+        let mef = r#"<?xml version="1.0"?>
+<opsa-mef>
+  <define-fault-tree name="FT">
+    <define-gate name="T">
+      <or><gate name="G1"/><gate name="G2"/><house-event name="On"/></or>
+    </define-gate>
+    <define-gate name="G1">
+      <atleast min="2"><basic-event name="A"/><basic-event name="B"/><basic-event name="C"/></atleast>
+    </define-gate>
+    <define-gate name="G2">
+      <and><not><basic-event name="A"/></not><basic-event name="D"/><house-event name="Off"/></and>
+    </define-gate>
+  </define-fault-tree>
+  <model-data>
+    <define-parameter name="rate"><float value="0.2"/></define-parameter>
+    <define-basic-event name="A"><float value="0.1"/></define-basic-event>
+    <define-basic-event name="B">
+      <exponential><parameter name="rate"/><system-mission-time/></exponential>
+    </define-basic-event>
+    <define-basic-event name="C">
+      <exponential><float value="0.3"/><system-mission-time/></exponential>
+    </define-basic-event>
+    <define-basic-event name="D"><float value="0.4"/></define-basic-event>
+    <define-house-event name="On"><constant value="false"/></define-house-event>
+    <define-house-event name="Off"><constant value="true"/></define-house-event>
+  </model-data>
+</opsa-mef>
+"#;
+        let galileo = ft_from_dft(
+            "coyan_mef_house_events.dft",
+            r#"toplevel "T";
+"T" or "G1" "G2" "On";
+"G1" 2of3 "A" "B" "C";
+"G2" and "NA" "D" "Off";
+"NA" not "A";
+"A" prob=0.1;
+"B" lambda=0.2;
+"C" lambda=0.3;
+"D" prob=0.4;
+"On" prob=0;
+"Off" prob=1;
+"#,
+        );
+        let ft = ft_from_mef("coyan_mef_house_events.xml", mef);
+        let read = ft_from_mef("coyan_mef_house_events_2.xml", &ft.to_mef("FT"));
+        assert_eq!(read.to_mef("FT"), ft.to_mef("FT"));
+        for ft in [&ft, &read] {
+            assert!((tep(ft, false) - tep(&galileo, false)).abs() < 1e-12);
+        }
+    }
+}
@@ -0,0 +1,470 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::cutsets::CutSet;
+use crate::fault_tree::FaultTree;
+use crate::nodes::{Node, NodeId};
+
+/// Number of samples drawn with the same random generator.
+const BATCH_SIZE: usize = 10_000;
+/// Number of batches run in parallel between two checks of the stopping criterion.
+const BATCHES_PER_ROUND: usize = 16;
+
+/// Gate of the structure function, where the arguments are positions in the evaluation order.
+#[derive(Debug, Clone)]
+pub(crate) enum Gate {
+    BasicEvent(f64),
+    Not(usize),
+    And(Vec<usize>),
+    Or(Vec<usize>),
+    Xor(Vec<usize>),
+    Vot(usize, Vec<usize>),
+}
+
+/// Structure function of the FT, with the nodes reachable from the top event sorted so the
+/// arguments of each gate come before it. The top event is the last one.
+#[derive(Debug, Clone)]
+pub(crate) struct StructureFunction {
+    pub(crate) gates: Vec<Gate>,
+    /// Node of the FT at each position.
+    pub(crate) ids: Vec<NodeId>,
+}
+
+impl StructureFunction {
+    /// Build the structure function with the probability of failure of each basic event at the timepoint.
+    pub(crate) fn new(ft: &FaultTree<String>, timepoint: f64, unav: bool) -> Self {
+        let mut position: HashMap<NodeId, usize> = HashMap::new();
+        let mut gates = vec![];
+        let mut ids = vec![];
+        let mut to_visit = vec![(ft.root_id, false)];
+        while let Some((nid, expanded)) = to_visit.pop() {
+            if position.contains_key(&nid) {
+                continue;
+            }
+            let node = &ft.nodes[nid];
+            if !expanded && node.is_gate() {
+                to_visit.push((nid, true));
+                to_visit.extend(node.children().into_iter().map(|c| (c, false)));
+                continue;
+            }
+            let args = node.children().iter().map(|c| position[c]).collect();
+            let gate = match node {
+                Node::BasicEvent(_, _) => Gate::BasicEvent(
+                    if unav {
+                        node.unavailability(timepoint)
+                    } else {
+                        node.unreliability(timepoint)
+                    }
+                    .expect("Only basic events have a probability"),
+                ),
+                Node::Not(arg) => Gate::Not(position[arg]),
+                Node::And(_) => Gate::And(args),
+                Node::Or(_) => Gate::Or(args),
+                Node::Xor(_) => Gate::Xor(args),
+                Node::Vot(k, _) => Gate::Vot(*k as usize, args),
+                Node::PlaceHolder(_, _, _) => panic!("Cant simulate a placeholder node."),
+            };
+            position.insert(nid, gates.len());
+            gates.push(gate);
+            ids.push(nid);
+        }
+        StructureFunction { gates, ids }
+    }
+
+    /// Evaluate the structure function, where `states` has the state of the basic events and
+    /// gets the state of the gates. Returns whether the top event fails.
+    pub(crate) fn evaluate(&self, states: &mut [bool]) -> bool {
+        for (i, gate) in self.gates.iter().enumerate() {
+            states[i] = match gate {
+                Gate::BasicEvent(_) => states[i],
+                Gate::Not(arg) => !states[*arg],
+                Gate::And(args) => args.iter().all(|a| states[*a]),
+                Gate::Or(args) => args.iter().any(|a| states[*a]),
+                Gate::Xor(args) => args.iter().filter(|a| states[**a]).count() % 2 == 1,
+                Gate::Vot(k, args) => args.iter().filter(|a| states[**a]).count() >= *k,
+            };
+        }
+        states[self.gates.len() - 1]
+    }
+}
+
+/// Quantile of the standard normal distribution, with the rational approximation of
+/// P. J. Acklam (relative error below 1.15e-9).
+pub fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969683028665376e1,
+        2.209460984245205e2,
+        -2.759285104469687e2,
+        1.38357751867269e2,
+        -3.066479806614716e1,
+        2.506628277459239,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e1,
+        1.615858368580409e2,
+        -1.556989798598866e2,
+        6.680131188771972e1,
+        -1.328068155288572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-3,
+        -3.223964580411365e-1,
+        -2.400758277161838,
+        -2.549732539343734,
+        4.374664141464968,
+        2.938163982698783,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-3,
+        3.224671290700398e-1,
+        2.445134137142996,
+        3.754408661907416,
+    ];
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    if p <= 0.0 {
+        f64::NEG_INFINITY
+    } else if p >= 1.0 {
+        f64::INFINITY
+    } else if p < 0.02425 {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - 0.02425 {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+/// Random generator of a batch. Each batch has its own, so the results only depend on the seed
+/// and not on the number of threads.
+pub(crate) fn batch_rng(seed: u64, batch: usize) -> StdRng {
+    StdRng::seed_from_u64(seed ^ (batch as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+/// Configuration of the Monte Carlo simulation.
+#[derive(Debug, Clone, Copy)]
+pub struct MonteCarloConfig {
+    /// Maximum number of samples.
+    pub max_samples: usize,
+    /// Stop when the half width of the confidence interval relative to the estimate is below this value.
+    pub target_rel_error: Option<f64>,
+    /// Confidence level of the interval, in (0, 1).
+    pub confidence: f64,
+    /// Seed of the random generator.
+    pub seed: u64,
+}
+
+/// Biasing of the basic events for importance sampling. The likelihood ratio of each sample
+/// corrects the estimate, so it stays unbiased.
+#[derive(Debug, Clone)]
+pub enum Biasing {
+    /// Failure biasing: each basic event is sampled failing with probability at least the given one.
+    Failure(f64),
+    /// Each sample forces the failure of one of the cut sets, chosen with probability proportional
+    /// to its probability, and samples the other basic events as usual. If the cut sets are truncated,
+    /// it estimates the probability that one of the given cut sets fails, a lower bound of the TEP.
+    CutSets(Vec<CutSet>),
+}
+
+/// Estimate of the TEP given by a simulation.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct MonteCarloEstimate {
+    pub tep: f64,
+    /// Standard error of the estimate.
+    pub std_error: f64,
+    /// Bounds of the confidence interval.
+    pub ci_low: f64,
+    pub ci_high: f64,
+    /// Half width of the confidence interval relative to the estimate.
+    pub rel_error: f64,
+    pub samples: usize,
+}
+
+impl MonteCarloEstimate {
+    /// Estimate from the sum of the weights of the samples and of their squares, with
+    /// the interval given by the normal approximation.
+    fn from_weights(sum: f64, sum_sq: f64, samples: usize, confidence: f64) -> Self {
+        let n = samples as f64;
+        let mean = sum / n;
+        let variance = if samples > 1 {
+            ((sum_sq / n - mean * mean) * n / (n - 1.0)).max(0.0)
+        } else {
+            0.0
+        };
+        let std_error = (variance / n).sqrt();
+        let half_width = normal_quantile(0.5 + confidence / 2.0) * std_error;
+        MonteCarloEstimate {
+            tep: mean,
+            std_error,
+            ci_low: (mean - half_width).max(0.0),
+            ci_high: (mean + half_width).min(1.0),
+            rel_error: half_width / mean,
+            samples,
+        }
+    }
+
+    /// Estimate from the number of samples where the top event failed, with the Wilson score
+    /// interval, that is still meaningful when few or no failures were seen.
+    fn from_failures(failures: usize, samples: usize, confidence: f64) -> Self {
+        let n = samples as f64;
+        let p = failures as f64 / n;
+        let z = normal_quantile(0.5 + confidence / 2.0);
+        let center = (p + z * z / (2.0 * n)) / (1.0 + z * z / n);
+        let half_width = z / (1.0 + z * z / n) * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt();
+        MonteCarloEstimate {
+            tep: p,
+            std_error: (p * (1.0 - p) / n).sqrt(),
+            ci_low: (center - half_width).max(0.0),
+            ci_high: (center + half_width).min(1.0),
+            rel_error: z * (p * (1.0 - p) / n).sqrt() / p,
+            samples,
+        }
+    }
+}
+
+/// Run the simulation in rounds of batches, until the target relative error or the maximum
+/// number of samples is reached. Each batch gives the sum of the weights of its samples and of their squares.
+fn simulate(
+    config: &MonteCarloConfig,
+    sample_batch: impl Fn(&mut StdRng, usize) -> (f64, f64) + Sync,
+    estimate: impl Fn(f64, f64, usize) -> MonteCarloEstimate,
+) -> MonteCarloEstimate {
+    let (mut samples, mut sum, mut sum_sq, mut batch) = (0, 0.0, 0.0, 0);
+    while samples < config.max_samples {
+        let round = (0..BATCHES_PER_ROUND)
+            .map(|i| {
+                let start = samples + i * BATCH_SIZE;
+                (
+                    batch + i,
+                    config.max_samples.saturating_sub(start).min(BATCH_SIZE),
+                )
+            })
+            .filter(|(_, size)| *size > 0)
+            .collect::<Vec<_>>();
+        batch += round.len();
+        samples += round.iter().map(|(_, size)| size).sum::<usize>();
+        // Collected in order, so the sum does not depend on the number of threads.
+        let results = round
+            .into_par_iter()
+            .map(|(b, size)| sample_batch(&mut batch_rng(config.seed, b), size))
+            .collect::<Vec<_>>();
+        for (s, s_sq) in results {
+            sum += s;
+            sum_sq += s_sq;
+        }
+
+        let current = estimate(sum, sum_sq, samples);
+        if config
+            .target_rel_error
+            .is_some_and(|target| current.rel_error <= target)
+        {
+            return current;
+        }
+    }
+    estimate(sum, sum_sq, samples)
+}
+
+impl FaultTree<String> {
+    /// Estimate the TEP by sampling the state of the basic events at the timepoint and evaluating
+    /// the structure function. The samples are drawn in parallel in batches, and the simulation stops
+    /// when the target relative error or the maximum number of samples is reached.
+    pub fn monte_carlo(
+        &self,
+        timepoint: f64,
+        unav: bool,
+        config: MonteCarloConfig,
+    ) -> MonteCarloEstimate {
+        let sf = StructureFunction::new(self, timepoint, unav);
+        let sample_batch = |rng: &mut StdRng, size: usize| {
+            let mut states = vec![false; sf.gates.len()];
+            let failures = (0..size)
+                .filter(|_| {
+                    for (i, gate) in sf.gates.iter().enumerate() {
+                        if let Gate::BasicEvent(p) = gate {
+                            states[i] = rng.r#gen::<f64>() < *p;
+                        }
+                    }
+                    sf.evaluate(&mut states)
+                })
+                .count() as f64;
+            (failures, failures)
+        };
+        simulate(&config, sample_batch, |sum, _, samples| {
+            MonteCarloEstimate::from_failures(sum as usize, samples, config.confidence)
+        })
+    }
+
+    /// Estimate the TEP by importance sampling: the basic events are sampled with the biased
+    /// probabilities, and each sample where the top event fails is weighted by its likelihood ratio.
+    pub fn importance_sampling(
+        &self,
+        timepoint: f64,
+        unav: bool,
+        config: MonteCarloConfig,
+        biasing: &Biasing,
+    ) -> MonteCarloEstimate {
+        let sf = StructureFunction::new(self, timepoint, unav);
+        let estimate = |sum, sum_sq, samples| {
+            MonteCarloEstimate::from_weights(sum, sum_sq, samples, config.confidence)
+        };
+        match biasing {
+            Biasing::Failure(bias) => {
+                // Biased probability and log of the likelihood ratio when failed and when working.
+                let biased = sf
+                    .gates
+                    .iter()
+                    .map(|gate| match gate {
+                        Gate::BasicEvent(p) => {
+                            let q = p.max(*bias);
+                            Some((q, (p / q).ln(), ((1.0 - p) / (1.0 - q)).ln()))
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                let sample_batch = |rng: &mut StdRng, size: usize| {
+                    let mut states = vec![false; sf.gates.len()];
+                    let (mut sum, mut sum_sq) = (0.0, 0.0);
+                    for _ in 0..size {
+                        let mut log_ratio = 0.0;
+                        for (i, b) in biased.iter().enumerate() {
+                            if let Some((q, failed, working)) = b {
+                                states[i] = rng.r#gen::<f64>() < *q;
+                                log_ratio += if states[i] { failed } else { working };
+                            }
+                        }
+                        if sf.evaluate(&mut states) {
+                            let weight = f64::exp(log_ratio);
+                            sum += weight;
+                            sum_sq += weight * weight;
+                        }
+                    }
+                    (sum, sum_sq)
+                };
+                simulate(&config, sample_batch, estimate)
+            }
+            Biasing::CutSets(cut_sets) => {
+                let position: HashMap<String, usize> = sf
+                    .ids
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, nid)| match &self.nodes[*nid] {
+                        Node::BasicEvent(name, _) => Some((name.to_owned(), i)),
+                        _ => None,
+                    })
+                    .collect();
+                let events = cut_sets
+                    .iter()
+                    .map(|cs| cs.events.iter().map(|e| position[e]).collect::<Vec<_>>())
+                    .collect::<Vec<_>>();
+                let cumulative = cut_sets
+                    .iter()
+                    .scan(0.0, |acc, cs| {
+                        *acc += cs.probability;
+                        Some(*acc)
+                    })
+                    .collect::<Vec<_>>();
+                let total = cumulative.last().copied().unwrap_or(0.0);
+                if total == 0.0 {
+                    return estimate(0.0, 0.0, config.max_samples);
+                }
+                // The proposal is P(x) * (number of cut sets failed in x) / total, so the
+                // likelihood ratio is total / (number of cut sets failed in x).
+                let sample_batch = |rng: &mut StdRng, size: usize| {
+                    let mut states = vec![false; sf.gates.len()];
+                    let (mut sum, mut sum_sq) = (0.0, 0.0);
+                    for _ in 0..size {
+                        let u = rng.r#gen::<f64>() * total;
+                        let chosen = cumulative
+                            .partition_point(|c| *c <= u)
+                            .min(events.len() - 1);
+                        for (i, gate) in sf.gates.iter().enumerate() {
+                            if let Gate::BasicEvent(p) = gate {
+                                states[i] = rng.r#gen::<f64>() < *p;
+                            }
+                        }
+                        for e in &events[chosen] {
+                            states[*e] = true;
+                        }
+                        let failed_cut_sets = events
+                            .iter()
+                            .filter(|cs| cs.iter().all(|e| states[*e]))
+                            .count();
+                        if sf.evaluate(&mut states) {
+                            let weight = total / failed_cut_sets as f64;
+                            sum += weight;
+                            sum_sq += weight * weight;
+                        }
+                    }
+                    (sum, sum_sq)
+                };
+                simulate(&config, sample_batch, estimate)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cutsets::CutSetLimits;
+    use crate::test_utils::ft_from_dft;
+
+    /// TEP of the 2of3 gate: `ab + ac + bc - 2abc = 0.098`.
+    const VOTING: &str = r#"toplevel "T";
+"T" or "G" "H";
+"G" and "A" "B";
+"H" 2of3 "A" "B" "C";
+"A" prob=0.1;
+"B" prob=0.2;
+"C" prob=0.3;
+"#;
+    const TEP: f64 = 0.098;
+
+    const CONFIG: MonteCarloConfig = MonteCarloConfig {
+        max_samples: 100_000,
+        target_rel_error: None,
+        confidence: 0.99,
+        seed: 7,
+    };
+
+    fn assert_covers(estimate: MonteCarloEstimate) {
+        assert!(estimate.ci_low <= TEP && TEP <= estimate.ci_high);
+        assert!(estimate.rel_error < 0.05);
+    }
+
+    #[test]
+    fn quantiles_of_the_normal() {
+        assert!((normal_quantile(0.5)).abs() < 1e-9);
+        assert!((normal_quantile(0.975) - 1.959963985).abs() < 1e-8);
+        assert!((normal_quantile(0.005) + 2.575829304).abs() < 1e-8);
+    }
+
+    #[test]
+    fn monte_carlo_covers_the_tep() {
+        let ft = ft_from_dft("coyan_montecarlo_crude.dft", VOTING);
+        let estimate = ft.monte_carlo(1.0, false, CONFIG);
+        assert_eq!(estimate.samples, CONFIG.max_samples);
+        assert_covers(estimate);
+        // The samples only depend on the seed.
+        assert_eq!(estimate.tep, ft.monte_carlo(1.0, false, CONFIG).tep);
+    }
+
+    #[test]
+    fn importance_sampling_covers_the_tep() {
+        let ft = ft_from_dft("coyan_montecarlo_biased.dft", VOTING);
+        let cut_sets = ft
+            .minimal_cut_sets(1.0, false, CutSetLimits::default())
+            .unwrap();
+        for biasing in [Biasing::Failure(0.4), Biasing::CutSets(cut_sets)] {
+            assert_covers(ft.importance_sampling(1.0, false, CONFIG, &biasing));
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::fs;
+
+use crate::wmc::Literal;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NnfNode {
+    True,
+    False,
+    Literal(Literal),
+    And(Vec<usize>),
+    Or(Vec<usize>),
+}
+
+/// Circuit in deterministic Decomposable Negation Normal Form, as given by a knowledge compiler
+/// over the CNF of the FT. Once compiled, the weighted model count for any weights of the
+/// literals takes a single pass over the circuit.
+#[derive(Debug, Clone)]
+pub struct Nnf {
+    /// Nodes of the circuit, the children of a node are always before it.
+    nodes: Vec<NnfNode>,
+    pub root: usize,
+}
+
+fn parse_usize(token: Option<&str>, line: &str) -> Result<usize, String> {
+    token
+        .and_then(|t| t.parse().ok())
+        .ok_or(format!("invalid NNF line '{line}'"))
+}
+
+fn parse_literal(token: &str, line: &str) -> Result<Literal, String> {
+    token
+        .parse()
+        .map_err(|_| format!("invalid literal in NNF line '{line}'"))
+}
+
+impl Nnf {
+    /// Read the output of a knowledge compiler, either in the c2d format (starting with the
+    /// `nnf` header) or in the d4 format.
+    pub fn from_file(filename: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(filename)
+            .map_err(|e| format!("unable to read the NNF file '{filename}': {e}"))?;
+        let is_c2d = text
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty() && !l.starts_with('c'))
+            .is_some_and(|l| l.starts_with("nnf"));
+        if is_c2d {
+            Nnf::from_c2d(&text)
+        } else {
+            Nnf::from_d4(&text)
+        }
+    }
+
+    /// Read a circuit in the c2d format: after the header `nnf <nodes> <edges> <vars>`, each
+    /// line is a node `L <lit>`, `A <c> <children>` or `O <var> <c> <children>`, where the
+    /// children are given by their line number. The root is the last node.
+    pub fn from_c2d(text: &str) -> Result<Self, String> {
+        let mut nodes = vec![];
+        for line in text.lines().map(str::trim) {
+            let mut tokens = line.split_whitespace();
+            let node = match tokens.next() {
+                None | Some("c") | Some("nnf") => continue,
+                Some("L") => {
+                    let lit = tokens.next().ok_or(format!("invalid NNF line '{line}'"))?;
+                    NnfNode::Literal(parse_literal(lit, line)?)
+                }
+                Some(kind @ ("A" | "O")) => {
+                    if kind == "O" {
+                        // Variable the children disagree on, not needed for the evaluation.
+                        parse_usize(tokens.next(), line)?;
+                    }
+                    let n_children = parse_usize(tokens.next(), line)?;
+                    let children = tokens
+                        .map(|t| parse_usize(Some(t), line))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if children.len() != n_children || children.iter().any(|c| *c >= nodes.len()) {
+                        return Err(format!("invalid NNF line '{line}'"));
+                    }
+                    match (kind, n_children) {
+                        ("A", 0) => NnfNode::True,
+                        ("O", 0) => NnfNode::False,
+                        ("A", _) => NnfNode::And(children),
+                        _ => NnfNode::Or(children),
+                    }
+                }
+                Some(_) => return Err(format!("invalid NNF line '{line}'")),
+            };
+            nodes.push(node);
+        }
+        if nodes.is_empty() {
+            return Err(String::from("the NNF file has no nodes"));
+        }
+        let root = nodes.len() - 1;
+        Ok(Nnf { nodes, root })
+    }
+
+    /// Read a circuit in the d4 format: each node is a line `o <id> 0`, `a <id> 0`, `t <id> 0` or
+    /// `f <id> 0`, and each edge is a line `<parent> <child> <lits> 0`, that takes the child
+    /// conjoined with the literals. The root is the node `1`.
+    pub fn from_d4(text: &str) -> Result<Self, String> {
+        // Nodes and edges are read first, as the edges can refer to nodes defined later.
+        let mut kinds: HashMap<usize, &str> = HashMap::new();
+        let mut edges: HashMap<usize, Vec<(usize, Vec<Literal>)>> = HashMap::new();
+        for line in text.lines().map(str::trim) {
+            let tokens = line.split_whitespace().collect_vec();
+            match tokens[..] {
+                [] | ["c", ..] => {}
+                [kind @ ("o" | "a" | "t" | "f"), id, "0"] => {
+                    kinds.insert(parse_usize(Some(id), line)?, kind);
+                }
+                [parent, child, ref lits @ .., "0"] => {
+                    let lits = lits
+                        .iter()
+                        .map(|l| parse_literal(l, line))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    edges
+                        .entry(parse_usize(Some(parent), line)?)
+                        .or_default()
+                        .push((parse_usize(Some(child), line)?, lits));
+                }
+                _ => return Err(format!("invalid NNF line '{line}'")),
+            }
+        }
+        if !kinds.contains_key(&1) {
+            return Err(String::from("the NNF file has no root node"));
+        }
+
+        // Post order traversal, so the children are added before their parents.
+        let mut nodes = vec![];
+        let mut index: HashMap<usize, usize> = HashMap::new();
+        let mut to_visit = vec![(1, false)];
+        while let Some((id, expanded)) = to_visit.pop() {
+            if index.contains_key(&id) {
+                continue;
+            }
+            let kind = *kinds
+                .get(&id)
+                .ok_or(format!("the NNF node {id} is not defined"))?;
+            let node_edges = edges.get(&id).map(Vec::as_slice).unwrap_or_default();
+            if !expanded && !node_edges.is_empty() {
+                to_visit.push((id, true));
+                to_visit.extend(node_edges.iter().map(|(child, _)| (*child, false)));
+                continue;
+            }
+            let mut children = vec![];
+            for (child, lits) in node_edges {
+                let mut conjuncts = vec![index[child]];
+                for lit in lits {
+                    conjuncts.push(nodes.len());
+                    nodes.push(NnfNode::Literal(*lit));
+                }
+                if conjuncts.len() == 1 {
+                    children.push(conjuncts[0]);
+                } else {
+                    children.push(nodes.len());
+                    nodes.push(NnfNode::And(conjuncts));
+                }
+            }
+            let node = match kind {
+                "t" => NnfNode::True,
+                "f" => NnfNode::False,
+                "a" => NnfNode::And(children),
+                _ => NnfNode::Or(children),
+            };
+            index.insert(id, nodes.len());
+            nodes.push(node);
+        }
+        let root = index[&1];
+        Ok(Nnf { nodes, root })
+    }
+
+    /// Number of nodes of the circuit.
+    pub fn size(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Weighted model count of the circuit, where `weights[i]` are the weights of the positive and
+    /// negative literal of the variable `i + 1`. The circuit does not need to be smooth: variables
+    /// missing from a node are taken with weight 1. This is exact for the CNF of the FT, since the
+    /// variables of the gates are determined by the basic events and the weights of each basic event add up to 1.
+    pub fn evaluate(&self, weights: &[(f64, f64)]) -> f64 {
+        self.values(weights)[self.root]
+    }
+
+    /// Weighted model count of each node of the circuit.
+    fn values(&self, weights: &[(f64, f64)]) -> Vec<f64> {
+        let mut values: Vec<f64> = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let value = match node {
+                NnfNode::True => 1.0,
+                NnfNode::False => 0.0,
+                NnfNode::Literal(lit) => {
+                    let (pos, neg) = weights[lit.unsigned_abs() as usize - 1];
+                    if *lit > 0 { pos } else { neg }
+                }
+                NnfNode::And(children) => children.iter().map(|c| values[*c]).product(),
+                NnfNode::Or(children) => children.iter().map(|c| values[*c]).sum(),
+            };
+            values.push(value);
+        }
+        values
+    }
+
+    /// Partial derivatives of the weighted model count with respect to the weights of the positive
+    /// and the negative literal of each variable, in a single backward pass from the root.
+    /// The circuit does not need to be smooth for the difference between both derivatives:
+    /// a missing variable is a factor `pos + neg` that adds the same to both.
+    pub fn derivatives(&self, weights: &[(f64, f64)]) -> Vec<(f64, f64)> {
+        let values = self.values(weights);
+        let mut d = vec![0.0; self.nodes.len()];
+        d[self.root] = 1.0;
+        let mut derivatives = vec![(0.0, 0.0); weights.len()];
+        // Children are always before their parents, so the parents are visited first.
+        for (n, node) in self.nodes.iter().enumerate().rev() {
+            if d[n] == 0.0 {
+                continue;
+            }
+            match node {
+                NnfNode::True | NnfNode::False => {}
+                NnfNode::Literal(lit) => {
+                    let (pos, neg) = &mut derivatives[lit.unsigned_abs() as usize - 1];
+                    if *lit > 0 {
+                        *pos += d[n];
+                    } else {
+                        *neg += d[n];
+                    }
+                }
+                NnfNode::Or(children) => {
+                    for c in children {
+                        d[*c] += d[n];
+                    }
+                }
+                NnfNode::And(children) => {
+                    // Product of the other children, with the products of the children before
+                    // and after each one, so a child with value 0 needs no division.
+                    let mut after = vec![1.0; children.len() + 1];
+                    for (i, c) in children.iter().enumerate().rev() {
+                        after[i] = after[i + 1] * values[*c];
+                    }
+                    let mut before = 1.0;
+                    for (i, c) in children.iter().enumerate() {
+                        d[*c] += d[n] * before * after[i + 1];
+                        before *= values[*c];
+                    }
+                }
+            }
+        }
+        derivatives
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Weights of `x1` and `x2`, where the count of `x1 or x2` is `0.1 + 0.9 * 0.2 = 0.28`.
+    const WEIGHTS: [(f64, f64); 2] = [(0.1, 0.9), (0.2, 0.8)];
+
+    #[test]
+    fn evaluate_c2d() {
+        // x1 or (not x1 and x2).
+        let nnf = Nnf::from_c2d("nnf 5 4 2\nL 1\nL -1\nL 2\nA 2 1 2\nO 1 2 0 3\n").unwrap();
+        assert!((nnf.evaluate(&WEIGHTS) - 0.28).abs() < 1e-12);
+
+        let derivatives = nnf.derivatives(&WEIGHTS);
+        let expected = [(1.0, 0.2), (0.9, 0.0)];
+        for ((pos, neg), (e_pos, e_neg)) in derivatives.into_iter().zip(expected) {
+            assert!((pos - e_pos).abs() < 1e-12 && (neg - e_neg).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn evaluate_d4() {
+        // The same circuit, with the literals on the edges to the true node.
+        let nnf = Nnf::from_d4("o 1 0\nt 2 0\n1 2 1 0\n1 2 -1 2 0\n").unwrap();
+        assert!((nnf.evaluate(&WEIGHTS) - 0.28).abs() < 1e-12);
+    }
+
+    #[test]
+    fn reject_invalid_circuits() {
+        assert!(Nnf::from_c2d("nnf 1 0 1\nA 1 3\n").is_err());
+        assert!(Nnf::from_d4("t 2 0\n").is_err());
+    }
+}
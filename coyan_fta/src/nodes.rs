@@ -1,5 +1,6 @@
 use crate::formula::Formula;
 use itertools::Itertools;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{
     collections::HashMap,
     fmt::{self, Debug, Display},
@@ -17,15 +18,34 @@ impl Display for NodeId {
         write!(f, "{}", self._raw + 1)
     }
 }
-#[derive(Debug, Clone)]
+
+/// NodeIds are serialized as their index in the nodes vector, starting from 0.
+impl Serialize for NodeId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.index() as u64)
+    }
+}
+
+impl<'de> Deserialize<'de> for NodeId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        usize::deserialize(deserializer).map(NodeId::new)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Distribution {
     /// Parameter Lambda of the exponential governing the distribution.
-    // TODO: Add usize parameter for phases if Erlang.
     Continuous(f64),
     /// Discrete probability.
     Discrete(f64),
+    /// Erlang distribution: the rate Lambda of each phase, and the number of phases.
+    Erlang(f64, usize),
+    /// Weibull distribution: the shape and the scale.
+    Weibull(f64, f64),
 }
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum RepairMode {
     /// Parameter is the rate governing the repair time.
     Monitored(f64),
@@ -33,10 +53,29 @@ pub enum RepairMode {
     PeriodicallyTested(f64, f64),
 }
 
-#[derive(Debug, Clone)]
+/// Epistemic uncertainty of the parameter of a basic event: the probability, the rate Lambda,
+/// or the scale of a Weibull. Except for the uniform, the value of the parameter is the mean.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Uncertainty {
+    /// Lognormal, with the error factor: the ratio between the 95th percentile and the median.
+    Lognormal { error_factor: f64 },
+    /// Gamma, with the shape.
+    Gamma { shape: f64 },
+    /// Beta, with the first shape Alpha. Only for probabilities.
+    Beta { alpha: f64 },
+    /// Uniform between the bounds.
+    Uniform { low: f64, high: f64 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BasicEvent {
+    #[serde(rename = "distribution")]
     dist: Distribution,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     repair_mode: Option<RepairMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uncertainty: Option<Uncertainty>,
 }
 
 impl Default for BasicEvent {
@@ -44,22 +83,33 @@ impl Default for BasicEvent {
         BasicEvent {
             dist: Distribution::Discrete(0.0),
             repair_mode: None,
+            uncertainty: None,
         }
     }
 }
 
 impl Display for BasicEvent {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match (&self.dist, &self.repair_mode) {
-            (Distribution::Discrete(prob), _) => write!(f, "prob={prob}"),
-            (Distribution::Continuous(lambda), None) => write!(f, "lambda={lambda}"),
-            (Distribution::Continuous(lambda), Some(RepairMode::Monitored(t_d))) => {
-                write!(f, "lambda={lambda} repair={t_d}")
-            }
-            (Distribution::Continuous(lambda), Some(RepairMode::PeriodicallyTested(t, t_r))) => {
-                write!(f, "lambda={lambda} interval={t} repair={t_r}")
+        match &self.dist {
+            Distribution::Discrete(prob) => write!(f, "prob={prob}")?,
+            Distribution::Continuous(lambda) => write!(f, "lambda={lambda}")?,
+            Distribution::Erlang(lambda, phases) => write!(f, "lambda={lambda} phases={phases}")?,
+            Distribution::Weibull(shape, scale) => write!(f, "scale={scale} shape={shape}")?,
+        }
+        match &self.repair_mode {
+            None => {}
+            Some(RepairMode::Monitored(t_d)) => write!(f, " repair={t_d}")?,
+            Some(RepairMode::PeriodicallyTested(t, t_r)) => {
+                write!(f, " interval={t} repair_time={t_r}")?
             }
         }
+        match &self.uncertainty {
+            None => Ok(()),
+            Some(Uncertainty::Lognormal { error_factor }) => write!(f, " ef={error_factor}"),
+            Some(Uncertainty::Gamma { shape }) => write!(f, " gamma={shape}"),
+            Some(Uncertainty::Beta { alpha }) => write!(f, " beta={alpha}"),
+            Some(Uncertainty::Uniform { low, high }) => write!(f, " low={low} high={high}"),
+        }
     }
 }
 
@@ -68,6 +118,7 @@ impl BasicEvent {
         Self {
             dist: Distribution::Discrete(1.0),
             repair_mode: None,
+            uncertainty: None,
         }
     }
 
@@ -75,6 +126,7 @@ impl BasicEvent {
         Self {
             dist: Distribution::Discrete(0.0),
             repair_mode: None,
+            uncertainty: None,
         }
     }
 
@@ -82,6 +134,7 @@ impl BasicEvent {
         Self {
             dist: Distribution::Discrete(prob),
             repair_mode: None,
+            uncertainty: None,
         }
     }
 
@@ -93,6 +146,58 @@ impl BasicEvent {
         Self {
             dist: Distribution::Continuous(prob),
             repair_mode: None,
+            uncertainty: None,
+        }
+    }
+
+    pub fn new_erlang(lambda: f64, phases: usize) -> Self {
+        Self {
+            dist: Distribution::Erlang(lambda, phases),
+            repair_mode: None,
+            uncertainty: None,
+        }
+    }
+
+    pub fn new_weibull(shape: f64, scale: f64) -> Self {
+        Self {
+            dist: Distribution::Weibull(shape, scale),
+            repair_mode: None,
+            uncertainty: None,
+        }
+    }
+
+    pub fn distribution(&self) -> &Distribution {
+        &self.dist
+    }
+
+    pub fn repair_mode(&self) -> Option<&RepairMode> {
+        self.repair_mode.as_ref()
+    }
+
+    pub fn with_uncertainty(&mut self, uncertainty: Uncertainty) {
+        self.uncertainty = Some(uncertainty);
+    }
+
+    pub fn uncertainty(&self) -> Option<&Uncertainty> {
+        self.uncertainty.as_ref()
+    }
+
+    /// Parameter of the distribution that carries the uncertainty: the probability, the rate
+    /// Lambda, or the scale of a Weibull.
+    pub fn parameter(&self) -> f64 {
+        match &self.dist {
+            Distribution::Discrete(prob) => *prob,
+            Distribution::Continuous(lambda) | Distribution::Erlang(lambda, _) => *lambda,
+            Distribution::Weibull(_, scale) => *scale,
+        }
+    }
+
+    /// Set the parameter that carries the uncertainty. Probabilities are capped at 1.
+    pub fn set_parameter(&mut self, value: f64) {
+        match &mut self.dist {
+            Distribution::Discrete(prob) => *prob = value.min(1.0),
+            Distribution::Continuous(lambda) | Distribution::Erlang(lambda, _) => *lambda = value,
+            Distribution::Weibull(_, scale) => *scale = value,
         }
     }
 
@@ -100,15 +205,29 @@ impl BasicEvent {
         match &self.dist {
             Distribution::Discrete(prob) => *prob,
             Distribution::Continuous(lambda) => 1.0 - (-lambda * timepoint).exp(),
+            Distribution::Erlang(lambda, phases) => {
+                // 1 - sum_{n < phases} e^{-lambda t} (lambda t)^n / n!
+                let x = lambda * timepoint;
+                let (_, survival) = (1..*phases).fold((1.0, 1.0), |(term, sum), n| {
+                    let term = term * x / n as f64;
+                    (term, sum + term)
+                });
+                1.0 - (-x).exp() * survival
+            }
+            Distribution::Weibull(shape, scale) => 1.0 - (-(timepoint / scale).powf(*shape)).exp(),
         }
     }
 
     /// As taken from Table XI-2 of W. E. Vesely, F. F. Goldberg, N. H. Roberts, and D. F. Haasl,
     /// Fault Tree Handbook. U.S. Nuclear Regulatory Commission, 1981.
+    /// Repairs are only modelled for exponential failure times, other distributions give their unreliability.
     pub fn unavailability(&self, timepoint: f64) -> f64 {
         match (&self.dist, &self.repair_mode) {
             (_, None) => self.unreliability(timepoint),
             (Distribution::Discrete(prob), _) => *prob,
+            (Distribution::Erlang(_, _) | Distribution::Weibull(_, _), _) => {
+                self.unreliability(timepoint)
+            }
             (Distribution::Continuous(lambda), Some(RepairMode::Monitored(l_d))) => {
                 (lambda * (1.0 / l_d)) / (1.0 + (lambda * (1.0 / l_d)))
             }
@@ -117,10 +236,72 @@ impl BasicEvent {
             }
         }
     }
+
+    /// Failure rate Lambda of the basic event, if its failure time is given by one.
+    /// The rate of a Weibull is the inverse of its scale.
+    pub fn rate(&self) -> Option<f64> {
+        match &self.dist {
+            Distribution::Discrete(_) => None,
+            Distribution::Continuous(lambda) | Distribution::Erlang(lambda, _) => Some(*lambda),
+            Distribution::Weibull(_, scale) => Some(1.0 / scale),
+        }
+    }
+
+    /// Derivative of the unreliability with respect to the failure rate Lambda.
+    /// None if the basic event has a constant probability.
+    pub fn unreliability_derivative(&self, timepoint: f64) -> Option<f64> {
+        match &self.dist {
+            Distribution::Discrete(_) => None,
+            Distribution::Continuous(lambda) => Some(timepoint * (-lambda * timepoint).exp()),
+            Distribution::Erlang(lambda, phases) => {
+                // t e^{-lambda t} (lambda t)^{phases - 1} / (phases - 1)!
+                let x = lambda * timepoint;
+                let term = (1..*phases).fold(1.0, |term, n| term * x / n as f64);
+                Some(timepoint * (-x).exp() * term)
+            }
+            Distribution::Weibull(shape, scale) => {
+                let x = (timepoint / scale).powf(*shape);
+                Some((-x).exp() * shape * x * scale)
+            }
+        }
+    }
+
+    /// Derivative of the unavailability with respect to the failure rate Lambda, from the
+    /// formulas of [BasicEvent::unavailability].
+    pub fn unavailability_derivative(&self, timepoint: f64) -> Option<f64> {
+        match (&self.dist, &self.repair_mode) {
+            (Distribution::Continuous(lambda), Some(RepairMode::Monitored(l_d))) => {
+                Some(l_d / (lambda + l_d).powi(2))
+            }
+            (Distribution::Continuous(_), Some(RepairMode::PeriodicallyTested(t, t_r))) => {
+                Some(t / 2.0 + t_r)
+            }
+            _ => self.unreliability_derivative(timepoint),
+        }
+    }
+}
+
+/// Clauses of the encoding of a `<K>of<N>` gate as an AND of OR gates.
+/// Each clause takes `N - K + 1` of the arguments, so the gate fails if at least `K` of them fail.
+pub fn vot_clauses(k: usize, args: &[NodeId]) -> Vec<Vec<NodeId>> {
+    let clause_size = args.len() - k;
+    let mut roots = args.to_vec();
+    let mut clauses = vec![];
+    while roots.len() > clause_size {
+        let elem = roots.pop().unwrap();
+        for subset in roots.iter().combinations(clause_size) {
+            let mut clause = vec![elem];
+            clause.extend(subset.into_iter().copied());
+            clauses.push(clause);
+        }
+    }
+    clauses
 }
 
 /// Enum representing the Types of nodes we can have.
-#[derive(Debug, Clone)]
+/// Placeholders only exist while reading a model, so they are not serialized.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Node<T> {
     BasicEvent(T, BasicEvent),
     Not(NodeId),
@@ -128,6 +309,7 @@ pub enum Node<T> {
     Or(Vec<NodeId>),
     Xor(Vec<NodeId>),
     Vot(i64, Vec<NodeId>),
+    #[serde(skip)]
     PlaceHolder(T, String, Vec<T>),
 }
 
@@ -0,0 +1,183 @@
+use itertools::Itertools;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::fault_tree::FaultTree;
+use crate::formula::CNFFormat;
+use crate::nodes::Node;
+use crate::solver::Solver;
+
+/// Local sensitivity of the TEP to a basic event, at a given timepoint.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Sensitivity {
+    pub event: String,
+    /// Probability of failure of the basic event.
+    pub probability: f64,
+    /// Derivative of the TEP with respect to the probability of the basic event, which is
+    /// its Birnbaum measure since the TEP is linear in each probability.
+    pub d_tep_d_p: f64,
+    /// Relative change of the TEP by relative change of the probability, `p / TEP * dTEP/dp`.
+    pub elasticity_p: f64,
+    /// Failure rate Lambda of the basic event, none for constant probabilities.
+    pub rate: Option<f64>,
+    /// Derivative of the TEP with respect to the failure rate, `dTEP/dp * dp/dLambda`.
+    pub d_tep_d_lambda: Option<f64>,
+    /// Relative change of the TEP by relative change of the rate, `Lambda / TEP * dTEP/dLambda`.
+    pub elasticity_lambda: Option<f64>,
+}
+
+impl FaultTree<String> {
+    /// Compute the derivatives of the TEP with respect to the probability and the failure rate
+    /// of every basic event, with their elasticities. As in the importance measures, the
+    /// derivative with respect to the probability comes from the TEPs of the FT with the basic
+    /// event conditioned to fail and to not fail, and it is chained with the derivative of the
+    /// unreliability, or unavailability, with respect to the rate.
+    /// Returns the TEP and the sensitivities sorted by decreasing elasticity, or the error of
+    /// the solver if it fails on any of the FTs, like a timeout.
+    #[allow(clippy::too_many_arguments)]
+    pub fn sensitivities(
+        &self,
+        solver: &(dyn Solver + Sync),
+        format: CNFFormat,
+        timepoint: f64,
+        timeout_s: u64,
+        preprocess: Option<String>,
+        negate_or: bool,
+        unav: bool,
+    ) -> Result<(f64, Vec<Sensitivity>), &'static str> {
+        let compute = |ft: &FaultTree<String>| {
+            solver.try_compute(
+                ft,
+                format,
+                timepoint,
+                timeout_s,
+                preprocess.clone(),
+                negate_or,
+                unav,
+            )
+        };
+        let tep = compute(self)?;
+
+        let sensitivities = self
+            .nodes
+            .iter_enumerated()
+            .filter_map(|(nid, n)| match n {
+                Node::BasicEvent(name, be) => Some((nid, name.to_owned(), be)),
+                _ => None,
+            })
+            .collect_vec()
+            .into_par_iter()
+            .map(|(nid, event, be)| {
+                let mut ft = self.clone();
+                ft.condition_be(nid, true);
+                let pos_tep = compute(&ft)?;
+                ft.condition_be(nid, false);
+                let neg_tep = compute(&ft)?;
+
+                let (probability, dp_dlambda) = if unav {
+                    (
+                        be.unavailability(timepoint),
+                        be.unavailability_derivative(timepoint),
+                    )
+                } else {
+                    (
+                        be.unreliability(timepoint),
+                        be.unreliability_derivative(timepoint),
+                    )
+                };
+                let d_tep_d_p = pos_tep - neg_tep;
+                let rate = be.rate();
+                let d_tep_d_lambda = dp_dlambda.map(|d| d_tep_d_p * d);
+                Ok(Sensitivity {
+                    event,
+                    probability,
+                    d_tep_d_p,
+                    elasticity_p: d_tep_d_p * probability / tep,
+                    rate,
+                    d_tep_d_lambda,
+                    elasticity_lambda: rate.zip(d_tep_d_lambda).map(|(l, d)| d * l / tep),
+                })
+            })
+            .collect::<Result<Vec<Sensitivity>, &'static str>>()?
+            .into_iter()
+            .sorted_by(|a, b| {
+                let elasticity = |s: &Sensitivity| s.elasticity_lambda.unwrap_or(s.elasticity_p);
+                elasticity(b)
+                    .abs()
+                    .total_cmp(&elasticity(a).abs())
+                    .then(a.event.cmp(&b.event))
+            })
+            .collect();
+        Ok((tep, sensitivities))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fault_tree::FaultTree;
+    use crate::formula::CNFFormat;
+    use crate::nodes::{Distribution, Node};
+    use crate::solver::{BddSolver, Solver};
+    use crate::test_utils::ft_from_dft;
+
+    const MODEL: &str = r#"toplevel "T";
+"T" or "G1" "G2" "F";
+"G1" and "A" "B" "C";
+"G2" 2of3 "B" "D" "E";
+"A" prob=0.3;
+"B" lambda=0.2;
+"C" lambda=0.5 repair=2;
+"D" lambda=0.1 interval=1 repair_time=0.5;
+"E" lambda=0.4 phases=3;
+"F" scale=4 shape=2;
+"#;
+
+    /// Copy of the FT where the basic event `event` has the failure rate `rate`.
+    fn with_rate(ft: &FaultTree<String>, event: &str, rate: f64) -> FaultTree<String> {
+        let mut ft = ft.clone();
+        for node in ft.nodes.iter_mut() {
+            if let Node::BasicEvent(name, be) = node
+                && name == event
+            {
+                match be.distribution() {
+                    Distribution::Weibull(_, _) => be.set_parameter(1.0 / rate),
+                    _ => be.set_parameter(rate),
+                }
+            }
+        }
+        ft
+    }
+
+    #[test]
+    fn rate_derivatives_match_finite_differences() {
+        let ft = ft_from_dft("coyan_sensitivity.dft", MODEL);
+        let solver = BddSolver::new("bdd");
+        for unav in [false, true] {
+            let tep = |ft: &FaultTree<String>| {
+                solver
+                    .try_compute(ft, CNFFormat::MC21, 1.5, 60, None, false, unav)
+                    .unwrap()
+            };
+            let (_, sensitivities) = ft
+                .sensitivities(&solver, CNFFormat::MC21, 1.5, 60, None, false, unav)
+                .unwrap();
+            assert_eq!(sensitivities.len(), 6);
+            for s in sensitivities {
+                let Some(rate) = s.rate else {
+                    assert_eq!(s.event, "A");
+                    continue;
+                };
+                let h = rate * 1e-5;
+                let difference = (tep(&with_rate(&ft, &s.event, rate + h))
+                    - tep(&with_rate(&ft, &s.event, rate - h)))
+                    / (2.0 * h);
+                let derivative = s.d_tep_d_lambda.unwrap();
+                assert!(
+                    (derivative - difference).abs() < 1e-6 * difference.abs().max(1.0),
+                    "{} (unav={unav}): {derivative} != {difference}",
+                    s.event
+                );
+            }
+        }
+    }
+}
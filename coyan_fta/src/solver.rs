@@ -1,15 +1,25 @@
+use crate::bdd::{Bdd, VariableOrdering};
 use crate::fault_tree::FaultTree;
 use crate::formula::CNFFormat;
+use crate::nnf::Nnf;
+use crate::nodes::{Node, NodeId};
+use crate::wmc::{Cnf, ModelCounter};
 use itertools::Itertools;
 use rand::Rng;
 use rand::distributions::Alphanumeric;
+use rayon::prelude::*;
+use std::collections::HashMap;
 use std::fs;
 use std::io::Write;
-use std::process::{Command, Output, Stdio};
-use std::time::Instant;
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Error of the solvers when they reach the timeout.
+pub const TIMEOUT: &str = "Execution timeout.";
 
 pub struct Config {
-    /// Max cache size to distribute between the threads in KB. [default: 3500]
+    /// Max cache size to distribute between the threads in KB. [default: 3584000]
     pub max_cache_size: usize,
     /// Negate top gate if is an OR, to favor UnitPropagation. [default: false]
     pub negate_or: bool,
@@ -29,7 +39,7 @@ pub struct Config {
     pub preprocess: Option<String>,
 }
 
-pub trait Solver {
+pub trait Solver: Sync {
     fn _name(&self) -> String;
 
     fn get_command(&self, timeout_s: u64) -> String;
@@ -44,6 +54,12 @@ pub trait Solver {
         unav: bool,
     ) -> Result<Output, &'static str>;
 
+    /// Run the solver over a CNF in DIMACS format, with the weights. Only the solvers that read
+    /// the CNF support it, the ones that run in process override [Solver::compute_timepoints].
+    fn run_cnf(&self, _model_text: &str, _timeout_s: u64) -> Result<Output, &'static str> {
+        Err("The solver does not read a CNF in DIMACS format.")
+    }
+
     fn get_tep(&self, result: Output) -> f64;
 
     #[allow(clippy::too_many_arguments)]
@@ -57,36 +73,534 @@ pub trait Solver {
         negate_top_or: bool,
         unav: bool,
     ) -> f64 {
+        match self.try_compute(
+            ft,
+            format,
+            timepoint,
+            timeout_s,
+            preprocess,
+            negate_top_or,
+            unav,
+        ) {
+            Ok(tep) => tep,
+            Err(msg) => panic!("{:?}", msg),
+        }
+    }
+
+    /// Same as [Solver::compute], but returns the error of the solver, like a timeout, instead of panicking.
+    #[allow(clippy::too_many_arguments)]
+    fn try_compute(
+        &self,
+        ft: &FaultTree<String>,
+        format: CNFFormat,
+        timepoint: f64,
+        timeout_s: u64,
+        preprocess: Option<String>,
+        negate_top_or: bool,
+        unav: bool,
+    ) -> Result<f64, &'static str> {
         if !unav && let Some(unreliability) = ft.nodes[ft.root_id].unreliability(timepoint) {
-            unreliability
+            Ok(unreliability)
         } else if unav && let Some(unavailability) = ft.nodes[ft.root_id].unavailability(timepoint)
         {
-            unavailability
+            Ok(unavailability)
         } else {
             let top_is_or = ft.nodes[ft.root_id].is_or();
-            match self.run_model(ft, format, timepoint, timeout_s, preprocess, unav) {
-                Ok(value) => {
-                    let wmc_res = self.get_tep(value);
-                    if top_is_or && negate_top_or {
-                        1.0 - wmc_res
-                    } else {
-                        wmc_res
-                    }
-                }
-                Err(msg) => panic!("{:?}", msg),
+            let wmc_res =
+                self.get_tep(self.run_model(ft, format, timepoint, timeout_s, preprocess, unav)?);
+            if top_is_or && negate_top_or {
+                Ok(1.0 - wmc_res)
+            } else {
+                Ok(wmc_res)
             }
         }
     }
-    fn _set_cache_size(&mut self, new_cs: usize);
+
+    /// Compute the TEP at each of the timepoints, in parallel. The structure of the FT is the same
+    /// at every timepoint, so the CNF is built (and preprocessed) once and only the weights change.
+    /// Each timepoint has its own result, so an error like a timeout only loses that timepoint.
+    #[allow(clippy::too_many_arguments)]
+    fn compute_timepoints(
+        &self,
+        ft: &FaultTree<String>,
+        format: CNFFormat,
+        timepoints: &[f64],
+        timeout_s: u64,
+        preprocess: Option<String>,
+        negate_top_or: bool,
+        unav: bool,
+    ) -> Vec<Result<f64, &'static str>> {
+        if !ft.nodes[ft.root_id].is_gate() {
+            return timepoints
+                .par_iter()
+                .map(|t| self.try_compute(ft, format, *t, timeout_s, None, negate_top_or, unav))
+                .collect();
+        }
+        let top_is_or = ft.nodes[ft.root_id].is_or();
+        let formula = ft.dump_cnf_formula(format, preprocess);
+        timepoints
+            .par_iter()
+            .map(|t| {
+                let model_text = format!("{}{}", formula, ft.dump_cnf_weights(format, *t, unav));
+                let wmc_res = self.get_tep(self.run_cnf(&model_text, timeout_s)?);
+                if top_is_or && negate_top_or {
+                    Ok(1.0 - wmc_res)
+                } else {
+                    Ok(wmc_res)
+                }
+            })
+            .collect()
+    }
+
+    /// Compute the TEP and its derivative with respect to the probability of each basic event,
+    /// which is its Birnbaum Measure, with a single compilation of the FT and a backward pass over it.
+    /// Only the solvers that compile the FT support it.
+    fn birnbaum_measures(
+        &self,
+        _ft: &FaultTree<String>,
+        _timepoint: f64,
+        _timeout_s: u64,
+        _unav: bool,
+    ) -> Result<(f64, HashMap<NodeId, f64>), &'static str> {
+        Err("The solver does not compile the FT, use bdd, d4 or c2d.")
+    }
+
+    /// Limit the cache of the solver, in MB. The solvers without a cache limit ignore it.
+    fn set_cache_size(&mut self, new_cs: usize);
 }
 
 pub fn get_solver_from_path(path: &str) -> Box<dyn Solver + Sync> {
     match path.to_ascii_lowercase() {
+        x if x.split_whitespace().next() == Some("bdd") => Box::new(BddSolver::new(path)),
+        x if x.split_whitespace().next() == Some("native") => Box::new(NativeSolver::new()),
         x if x.contains("sharpsat") => Box::new(SharpsatTDSolver::new(path)),
         x if x.contains("addmc") => Box::new(ADDMCSolver::new(path)),
         x if x.contains("gpmc") => Box::new(GPMCSolver::new(path)),
         x if x.contains("dmc") => Box::new(DMCSolver::new(path)),
-        _ => panic!("Solver not supported. Supported solves: ADDMC - GPMC - SharpSAT-TD"),
+        x if x.contains("d4") => Box::new(CompilerSolver::new(path, KnowledgeCompiler::D4)),
+        x if x.contains("c2d") => Box::new(CompilerSolver::new(path, KnowledgeCompiler::C2d)),
+        _ => panic!(
+            "Solver not supported. Supported solves: ADDMC - GPMC - SharpSAT-TD - d4 - c2d - bdd - native"
+        ),
+    }
+}
+
+/// Output in the same way as an external solver, for the solvers that run in process.
+fn exact_double_output(value: f64) -> Output {
+    Output {
+        status: ExitStatus::default(),
+        stdout: format!("c s exact double {}\n", value).into_bytes(),
+        stderr: vec![],
+    }
+}
+
+/// Read the value written by [`exact_double_output`].
+fn parse_exact_double(result: Output) -> f64 {
+    let stdout =
+        String::from_utf8(result.stdout).expect("failed to produce the stdout of the solver");
+    stdout
+        .trim()
+        .split(" ")
+        .last()
+        .expect("Something went wrong while reading solver output")
+        .parse()
+        .expect("Error while parsing value to float")
+}
+
+/// Native solver, that builds a BDD of the FT instead of running an external model counter.
+/// The path is `bdd`, optionally followed by `--order dfs` or `--order weight` to choose the
+/// variable ordering heuristic.
+pub struct BddSolver {
+    ordering: VariableOrdering,
+}
+
+impl BddSolver {
+    pub fn new(path: &str) -> Self {
+        let args = path.split_whitespace().collect_vec();
+        let ordering = match args.iter().position(|a| *a == "--order") {
+            Some(i) => match args.get(i + 1).map(|a| a.to_ascii_lowercase()).as_deref() {
+                Some("dfs") => VariableOrdering::Dfs,
+                Some("weight") => VariableOrdering::Weight,
+                _ => panic!("Unsupported variable ordering. Try dfs or weight."),
+            },
+            None => VariableOrdering::default(),
+        };
+        BddSolver { ordering }
+    }
+}
+
+impl Solver for BddSolver {
+    fn _name(&self) -> String {
+        String::from("BDD")
+    }
+
+    fn set_cache_size(&mut self, _new_cs: usize) {}
+
+    fn get_command(&self, _timeout_s: u64) -> String {
+        format!("bdd --order {:?}", self.ordering).to_lowercase()
+    }
+
+    /// Build the BDD and compute the TEP. The result is given in the same way as an external solver.
+    fn run_model(
+        &self,
+        ft: &FaultTree<String>,
+        _format: CNFFormat,
+        timebound: f64,
+        timeout_s: u64,
+        _preprocess: Option<String>,
+        unav: bool,
+    ) -> Result<Output, &'static str> {
+        let deadline = Instant::now() + Duration::from_secs(timeout_s);
+        let bdd = Bdd::from_fault_tree(ft, self.ordering, Some(deadline))?;
+        let tep = bdd.probability(&bdd.var_probabilities(ft, timebound, unav));
+        Ok(exact_double_output(tep))
+    }
+
+    fn get_tep(&self, result: Output) -> f64 {
+        parse_exact_double(result)
+    }
+
+    /// The BDD is built from the FT and not from the CNF, so the top gate is never negated.
+    fn try_compute(
+        &self,
+        ft: &FaultTree<String>,
+        format: CNFFormat,
+        timepoint: f64,
+        timeout_s: u64,
+        preprocess: Option<String>,
+        _negate_top_or: bool,
+        unav: bool,
+    ) -> Result<f64, &'static str> {
+        let value = self.run_model(ft, format, timepoint, timeout_s, preprocess, unav)?;
+        Ok(self.get_tep(value))
+    }
+
+    fn birnbaum_measures(
+        &self,
+        ft: &FaultTree<String>,
+        timepoint: f64,
+        timeout_s: u64,
+        unav: bool,
+    ) -> Result<(f64, HashMap<NodeId, f64>), &'static str> {
+        let deadline = Instant::now() + Duration::from_secs(timeout_s);
+        let bdd = Bdd::from_fault_tree(ft, self.ordering, Some(deadline))?;
+        let probs = bdd.var_probabilities(ft, timepoint, unav);
+        let birnbaum = bdd.vars.iter().copied().zip(bdd.derivatives(&probs));
+        Ok((bdd.probability(&probs), birnbaum.collect()))
+    }
+
+    /// The BDD is built once and evaluated with the probabilities of each timepoint.
+    fn compute_timepoints(
+        &self,
+        ft: &FaultTree<String>,
+        _format: CNFFormat,
+        timepoints: &[f64],
+        timeout_s: u64,
+        _preprocess: Option<String>,
+        _negate_top_or: bool,
+        unav: bool,
+    ) -> Vec<Result<f64, &'static str>> {
+        let deadline = Instant::now() + Duration::from_secs(timeout_s);
+        let bdd = match Bdd::from_fault_tree(ft, self.ordering, Some(deadline)) {
+            Ok(bdd) => bdd,
+            Err(msg) => return vec![Err(msg); timepoints.len()],
+        };
+        timepoints
+            .par_iter()
+            .map(|t| Ok(bdd.probability(&bdd.var_probabilities(ft, *t, unav))))
+            .collect()
+    }
+}
+
+/// Native weighted model counter over the CNF of the Tseitin transformation, so no external
+/// binary is needed. The path is `native`. The CNF is not passed through the preprocessor.
+pub struct NativeSolver {
+    /// Limit of the component cache in MB. The cache is emptied when it gets full.
+    cs: Option<usize>,
+}
+
+impl NativeSolver {
+    pub fn new() -> Self {
+        NativeSolver { cs: None }
+    }
+
+    /// Run the model counter on the calling thread.
+    fn count(
+        &self,
+        cnf: &Cnf,
+        weights: &[(f64, f64)],
+        timeout_s: u64,
+    ) -> Result<f64, &'static str> {
+        let deadline = Instant::now() + Duration::from_secs(timeout_s);
+        ModelCounter::new(cnf, weights, self.cs, Some(deadline)).count()
+    }
+}
+
+impl Default for NativeSolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Solver for NativeSolver {
+    fn _name(&self) -> String {
+        String::from("Native")
+    }
+
+    fn set_cache_size(&mut self, new_cs: usize) {
+        self.cs = Some(new_cs)
+    }
+
+    fn get_command(&self, _timeout_s: u64) -> String {
+        String::from("native")
+    }
+
+    fn run_model(
+        &self,
+        ft: &FaultTree<String>,
+        _format: CNFFormat,
+        timebound: f64,
+        timeout_s: u64,
+        _preprocess: Option<String>,
+        unav: bool,
+    ) -> Result<Output, &'static str> {
+        let cnf = Cnf::from_formula(&ft.apply_tseitin(), ft.get_count());
+        let wmc = self.count(&cnf, &ft.literal_weights(timebound, unav), timeout_s)?;
+        Ok(exact_double_output(wmc))
+    }
+
+    fn get_tep(&self, result: Output) -> f64 {
+        parse_exact_double(result)
+    }
+
+    /// The CNF is built once and counted with the weights of each timepoint.
+    fn compute_timepoints(
+        &self,
+        ft: &FaultTree<String>,
+        _format: CNFFormat,
+        timepoints: &[f64],
+        timeout_s: u64,
+        _preprocess: Option<String>,
+        negate_top_or: bool,
+        unav: bool,
+    ) -> Vec<Result<f64, &'static str>> {
+        let top_is_or = ft.nodes[ft.root_id].is_or();
+        let cnf = Cnf::from_formula(&ft.apply_tseitin(), ft.get_count());
+        timepoints
+            .par_iter()
+            .map(|t| {
+                let wmc_res = self.count(&cnf, &ft.literal_weights(*t, unav), timeout_s)?;
+                if top_is_or && negate_top_or {
+                    Ok(1.0 - wmc_res)
+                } else {
+                    Ok(wmc_res)
+                }
+            })
+            .collect()
+    }
+}
+
+/// Knowledge compilers supported by [CompilerSolver].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KnowledgeCompiler {
+    /// [d4](https://github.com/crillab/d4v2), run as a d-DNNF compiler.
+    D4,
+    /// [c2d](http://reasoning.cs.ucla.edu/c2d/), writes the circuit next to the input file.
+    C2d,
+}
+
+/// Compiled circuits of [CompilerSolver], by CNF. Each one is compiled only once, even if several
+/// threads ask for it at the same time. The threads waiting on a failed compilation get its error.
+type CircuitCache = Mutex<HashMap<Cnf, Arc<OnceLock<Result<Arc<Nnf>, &'static str>>>>>;
+
+/// Solver that compiles the CNF of the FT into a d-DNNF circuit with a knowledge compiler, and
+/// evaluates the circuit with the weights of the basic events. The circuits are kept, so the
+/// FTs that only differ in the weights, like the same FT at other timepoints or the FTs of the
+/// importance measures, are compiled once. The CNF is not passed through the preprocessor.
+pub struct CompilerSolver {
+    /// Path to the compiler
+    path: String,
+    compiler: KnowledgeCompiler,
+    ///tpmdir -> the directory to store the CNF and the compiled circuit.
+    tmpdir: String,
+    circuits: CircuitCache,
+}
+
+impl CompilerSolver {
+    pub fn new(path: &str, compiler: KnowledgeCompiler) -> Self {
+        CompilerSolver {
+            path: String::from(path),
+            compiler,
+            tmpdir: String::from(".tmp"),
+            circuits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get the compiled circuit of the CNF, compiling it if it is the first time.
+    /// Failed compilations, like a timeout, are not kept, so the next call compiles it again.
+    pub fn circuit(&self, cnf: &Cnf, timeout_s: u64) -> Result<Arc<Nnf>, &'static str> {
+        let cell = self
+            .circuits
+            .lock()
+            .map_err(|_| "The cache of circuits is poisoned.")?
+            .entry(cnf.clone())
+            .or_default()
+            .clone();
+        let result = cell
+            .get_or_init(|| self.compile(cnf, timeout_s).map(Arc::new))
+            .clone();
+        if result.is_err() {
+            let mut circuits = self
+                .circuits
+                .lock()
+                .map_err(|_| "The cache of circuits is poisoned.")?;
+            // Another thread may have already replaced the failed entry.
+            if circuits.get(cnf).is_some_and(|c| Arc::ptr_eq(c, &cell)) {
+                circuits.remove(cnf);
+            }
+        }
+        result
+    }
+
+    fn compile(&self, cnf: &Cnf, timeout_s: u64) -> Result<Nnf, &'static str> {
+        let rnd_ft_file: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(5)
+            .map(char::from)
+            .collect();
+        let cnf_file = format!("{}/{}.cnf", self.tmpdir, rnd_ft_file);
+        let nnf_file = format!("{}.nnf", cnf_file);
+        fs::create_dir_all(&self.tmpdir).map_err(|_| "Unable to create the tmp directory.")?;
+        fs::write(&cnf_file, cnf.to_dimacs()).map_err(|_| "Unable to write the CNF file.")?;
+
+        let compiler_cmd = match self.compiler {
+            KnowledgeCompiler::D4 => format!(
+                "{} -i {} -m ddnnf-compiler --dump-ddnnf {}",
+                self.get_command(timeout_s),
+                cnf_file,
+                nnf_file
+            ),
+            KnowledgeCompiler::C2d => format!("{} -in {}", self.get_command(timeout_s), cnf_file),
+        };
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(compiler_cmd)
+            .stdin(Stdio::null())
+            .stderr(Stdio::piped())
+            .stdout(Stdio::piped())
+            .output();
+        let _ = fs::remove_file(&cnf_file);
+
+        let result = match output {
+            Ok(out) => {
+                let stderr = String::from_utf8_lossy(&out.stderr).to_lowercase();
+                if stderr.eq("killed\n") {
+                    Err(TIMEOUT)
+                } else {
+                    Nnf::from_file(&nnf_file).map_err(|msg| {
+                        eprintln!("{}", msg);
+                        "Something went wrong."
+                    })
+                }
+            }
+            Err(_err) => Err("Compiler Process had an error."),
+        };
+        let _ = fs::remove_file(&nnf_file);
+        result
+    }
+}
+
+impl Solver for CompilerSolver {
+    fn _name(&self) -> String {
+        match self.compiler {
+            KnowledgeCompiler::D4 => String::from("d4"),
+            KnowledgeCompiler::C2d => String::from("c2d"),
+        }
+    }
+
+    fn set_cache_size(&mut self, _new_cs: usize) {}
+
+    fn get_command(&self, timeout_s: u64) -> String {
+        format!("timeout -s KILL {}s {}", timeout_s, self.path)
+    }
+
+    fn run_model(
+        &self,
+        ft: &FaultTree<String>,
+        _format: CNFFormat,
+        timebound: f64,
+        timeout_s: u64,
+        _preprocess: Option<String>,
+        unav: bool,
+    ) -> Result<Output, &'static str> {
+        let cnf = Cnf::from_formula(&ft.apply_tseitin(), ft.get_count());
+        let nnf = self.circuit(&cnf, timeout_s)?;
+        let wmc = nnf.evaluate(&ft.literal_weights(timebound, unav));
+        Ok(exact_double_output(wmc))
+    }
+
+    fn get_tep(&self, result: Output) -> f64 {
+        parse_exact_double(result)
+    }
+
+    /// The derivative with respect to the probability `p` of a basic event is the difference of the
+    /// derivatives with respect to the weights of its literals, `p` and `1 - p`.
+    fn birnbaum_measures(
+        &self,
+        ft: &FaultTree<String>,
+        timepoint: f64,
+        timeout_s: u64,
+        unav: bool,
+    ) -> Result<(f64, HashMap<NodeId, f64>), &'static str> {
+        let cnf = Cnf::from_formula(&ft.apply_tseitin(), ft.get_count());
+        let nnf = self.circuit(&cnf, timeout_s)?;
+        let weights = ft.literal_weights(timepoint, unav);
+        // The CNF has the negated top event if the top OR is negated.
+        let sign = if ft.nodes[ft.root_id].is_or() && ft.negate_or {
+            -1.0
+        } else {
+            1.0
+        };
+        let wmc = nnf.evaluate(&weights);
+        let tep = if sign < 0.0 { 1.0 - wmc } else { wmc };
+        let birnbaum = nnf
+            .derivatives(&weights)
+            .into_iter()
+            .enumerate()
+            .map(|(i, (pos, neg))| (NodeId::new(i), sign * (pos - neg)))
+            .filter(|(nid, _)| matches!(ft.nodes.get(*nid), Some(Node::BasicEvent(_, _))))
+            .collect();
+        Ok((tep, birnbaum))
+    }
+
+    /// The CNF is compiled once and the circuit is evaluated with the weights of each timepoint.
+    fn compute_timepoints(
+        &self,
+        ft: &FaultTree<String>,
+        _format: CNFFormat,
+        timepoints: &[f64],
+        timeout_s: u64,
+        _preprocess: Option<String>,
+        negate_top_or: bool,
+        unav: bool,
+    ) -> Vec<Result<f64, &'static str>> {
+        let top_is_or = ft.nodes[ft.root_id].is_or();
+        let cnf = Cnf::from_formula(&ft.apply_tseitin(), ft.get_count());
+        let nnf = match self.circuit(&cnf, timeout_s) {
+            Ok(nnf) => nnf,
+            Err(msg) => return vec![Err(msg); timepoints.len()],
+        };
+        timepoints
+            .par_iter()
+            .map(|t| {
+                let wmc_res = nnf.evaluate(&ft.literal_weights(*t, unav));
+                if top_is_or && negate_top_or {
+                    Ok(1.0 - wmc_res)
+                } else {
+                    Ok(wmc_res)
+                }
+            })
+            .collect()
     }
 }
 
@@ -139,7 +653,7 @@ impl Solver for SharpsatTDSolver {
         }
     }
 
-    fn _set_cache_size(&mut self, new_cs: usize) {
+    fn set_cache_size(&mut self, new_cs: usize) {
         self.cs = Some(new_cs)
     }
 
@@ -152,6 +666,10 @@ impl Solver for SharpsatTDSolver {
         preprocess: Option<String>,
         unav: bool,
     ) -> Result<Output, &'static str> {
+        self.run_cnf(&ft.dump_cnf(format, timebound, preprocess, unav), timeout_s)
+    }
+
+    fn run_cnf(&self, model_text: &str, timeout_s: u64) -> Result<Output, &'static str> {
         // Set unique tmp name for each thread. With 5 char the chance of taking a name in use is 26⁵.
         let rnd_ft_file: String = rand::thread_rng()
             .sample_iter(&Alphanumeric)
@@ -160,14 +678,7 @@ impl Solver for SharpsatTDSolver {
             .collect();
         let tmp_ft_file = format!("{}/{}", self.tmpdir, rnd_ft_file);
 
-        ft.dump_cnf_to_file(
-            tmp_ft_file.clone(),
-            format,
-            timebound,
-            None,
-            preprocess,
-            unav,
-        );
+        fs::write(&tmp_ft_file, model_text).expect("unable to create file");
         let solver_cmd = format!("{} ./{}", self.get_command(timeout_s), tmp_ft_file);
 
         let child = Command::new("sh")
@@ -190,7 +701,7 @@ impl Solver for SharpsatTDSolver {
                     Ok(out)
                 // If it has something, check if is the killed signal
                 } else if stderr.eq("killed\n") {
-                    Err("Execution timeout.")
+                    Err(TIMEOUT)
                 // Something else failed, print error.
                 } else {
                     // ft.dump_cnf_to_file(String::from("failed.dft"), format, timebound, None, None);
@@ -249,7 +760,7 @@ impl Solver for GPMCSolver {
         String::from("GPMC")
     }
 
-    fn _set_cache_size(&mut self, new_cs: usize) {
+    fn set_cache_size(&mut self, new_cs: usize) {
         self.cs = Some(new_cs)
     }
 
@@ -275,8 +786,11 @@ impl Solver for GPMCSolver {
         preprocess: Option<String>,
         unav: bool,
     ) -> Result<Output, &'static str> {
+        self.run_cnf(&ft.dump_cnf(format, timebound, preprocess, unav), timeout_s)
+    }
+
+    fn run_cnf(&self, model_text: &str, timeout_s: u64) -> Result<Output, &'static str> {
         let solver_cmd = self.get_command(timeout_s);
-        let model_text = ft.dump_cnf(format, timebound, preprocess, unav);
         let mut child = Command::new("sh")
             .arg("-c")
             .arg(solver_cmd)
@@ -300,7 +814,7 @@ impl Solver for GPMCSolver {
                     Ok(out)
                 } else if stderr.eq("killed\n") {
                     // If it has something, check if is the killed signal
-                    Err("Execution timeout.")
+                    Err(TIMEOUT)
                 } else {
                     println!("{:?}", stderr);
                     Err("Something went wrong.")
@@ -377,7 +891,7 @@ impl Solver for DMCSolver {
     fn _name(&self) -> String {
         String::from("DMC")
     }
-    fn _set_cache_size(&mut self, _new_cs: usize) {
+    fn set_cache_size(&mut self, _new_cs: usize) {
         println!("WARNING!: Limit on memory consumption of the DMC solver is not implemented.")
     }
 
@@ -394,20 +908,17 @@ impl Solver for DMCSolver {
         preprocess: Option<String>,
         unav: bool,
     ) -> Result<Output, &'static str> {
+        self.run_cnf(&ft.dump_cnf(format, timebound, preprocess, unav), timeout_s)
+    }
+
+    fn run_cnf(&self, model_text: &str, timeout_s: u64) -> Result<Output, &'static str> {
         let rnd_ft_file: String = rand::thread_rng()
             .sample_iter(&Alphanumeric)
             .take(5)
             .map(char::from)
             .collect();
         let tmp_ft_file = format!("{}/{}", self.tmpdir, rnd_ft_file);
-        ft.dump_cnf_to_file(
-            tmp_ft_file.clone(),
-            format,
-            timebound,
-            None,
-            preprocess,
-            unav,
-        );
+        fs::write(&tmp_ft_file, model_text).expect("unable to create file");
         let (heuristic_tree, remaining_s) = self.compute_joint_tree(timeout_s, &tmp_ft_file);
 
         let solver_cmd: String = format!("{} --cf {}", self.get_command(remaining_s), tmp_ft_file);
@@ -437,7 +948,7 @@ impl Solver for DMCSolver {
                     Ok(out)
                 } else if stderr.eq("killed\n") {
                     // If it has something, check if is the killed signal
-                    Err("Execution timeout.")
+                    Err(TIMEOUT)
                 } else {
                     println!("{:?}", stderr);
                     Err("Something went wrong.")
@@ -484,7 +995,7 @@ impl Solver for ADDMCSolver {
         String::from("ADDMC")
     }
 
-    fn _set_cache_size(&mut self, _new_cs: usize) {
+    fn set_cache_size(&mut self, _new_cs: usize) {
         println!(
             "WARNING!: ADDMC solver does not have any parameter to regulate the cache size or any memory consumption."
         )
@@ -503,8 +1014,11 @@ impl Solver for ADDMCSolver {
         preprocess: Option<String>,
         unav: bool,
     ) -> Result<Output, &'static str> {
+        self.run_cnf(&ft.dump_cnf(format, timebound, preprocess, unav), timeout_s)
+    }
+
+    fn run_cnf(&self, model_text: &str, timeout_s: u64) -> Result<Output, &'static str> {
         let solver_cmd = self.get_command(timeout_s);
-        let model_text = ft.dump_cnf(format, timebound, preprocess, unav);
 
         let mut child = Command::new("sh")
             .arg("-c")
@@ -530,7 +1044,7 @@ impl Solver for ADDMCSolver {
                     Ok(out)
                 } else if stderr.eq("killed\n") {
                     // If it has something, check if is the killed signal
-                    Err("Execution timeout.")
+                    Err(TIMEOUT)
                 } else {
                     println!("{:?}", stderr);
                     Err("Something went wrong.")
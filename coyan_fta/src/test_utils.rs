@@ -0,0 +1,30 @@
+use std::collections::HashMap;
+
+use crate::fault_tree::FaultTree;
+
+/// Write `text` to a temporary file named `name` and read it with `read`. The name must be
+/// unique between tests, as they run in parallel, and its extension is given to the reader.
+pub fn read_model<T>(name: &str, text: &str, read: impl FnOnce(&str) -> T) -> T {
+    let filename = std::env::temp_dir().join(name);
+    std::fs::write(&filename, text).expect("unable to write the test model");
+    let filename = filename.to_str().unwrap();
+    let result = read(filename);
+    let _ = std::fs::remove_file(filename);
+    result
+}
+
+/// Read a FT from the text of a GALILEO model, without simplifying it.
+pub fn ft_from_dft(name: &str, text: &str) -> FaultTree<String> {
+    read_model(name, text, |f| {
+        FaultTree::new_from_file(f, false, false, &HashMap::new())
+    })
+    .expect("the test model is valid")
+}
+
+/// Read a FT from the text of an Open-PSA MEF model, without simplifying it.
+pub fn ft_from_mef(name: &str, text: &str) -> FaultTree<String> {
+    read_model(name, text, |f| {
+        FaultTree::new_from_mef(f, false, false, &HashMap::new())
+    })
+    .expect("the test model is valid")
+}
@@ -0,0 +1,580 @@
+use itertools::Itertools;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::f64::consts::PI;
+
+use crate::fault_tree::FaultTree;
+use crate::formula::CNFFormat;
+use crate::montecarlo::normal_quantile;
+use crate::nodes::{Node, NodeId, Uncertainty};
+use crate::solver::Solver;
+
+/// Smallest value kept in the continued fractions, to avoid dividing by zero.
+const FPMIN: f64 = 1e-300;
+/// Maximum number of terms of the series and continued fractions.
+const MAX_TERMS: usize = 500;
+
+/// Sampling of the uncertain parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Sampling {
+    /// Simple random sampling.
+    #[default]
+    Random,
+    /// Latin hypercube sampling: the range of each parameter is split in as many intervals of
+    /// equal probability as samples, and each interval is sampled once.
+    LatinHypercube,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct UncertaintyConfig {
+    /// Number of parameter sets sampled.
+    pub samples: usize,
+    pub sampling: Sampling,
+    pub seed: u64,
+    /// Number of bins of the histogram of the TEP.
+    pub bins: usize,
+}
+
+/// Bin of the histogram of the TEP, with the number of samples in `[low, high)`.
+/// The last bin also contains the samples equal to its upper bound.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct HistogramBin {
+    pub low: f64,
+    pub high: f64,
+    pub count: usize,
+}
+
+/// Distribution of the TEP over the sampled parameter sets.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UncertaintyResult {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub median: f64,
+    pub p5: f64,
+    pub p95: f64,
+    pub min: f64,
+    pub max: f64,
+    pub histogram: Vec<HistogramBin>,
+    pub samples: usize,
+}
+
+impl UncertaintyResult {
+    pub fn from_samples(teps: &[f64], bins: usize) -> Self {
+        let sorted = teps.iter().copied().sorted_by(f64::total_cmp).collect_vec();
+        let n = sorted.len() as f64;
+        let mean = sorted.iter().sum::<f64>() / n;
+        let variance = sorted.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0);
+        let (min, max) = (sorted[0], sorted[sorted.len() - 1]);
+
+        let bins = bins.max(1);
+        let width = (max - min) / bins as f64;
+        let mut histogram = (0..bins)
+            .map(|i| HistogramBin {
+                low: min + i as f64 * width,
+                high: if i + 1 == bins {
+                    max
+                } else {
+                    min + (i + 1) as f64 * width
+                },
+                count: 0,
+            })
+            .collect_vec();
+        for tep in &sorted {
+            let bin = if width > 0.0 {
+                (((tep - min) / width) as usize).min(bins - 1)
+            } else {
+                0
+            };
+            histogram[bin].count += 1;
+        }
+
+        UncertaintyResult {
+            mean,
+            std_dev: variance.sqrt(),
+            median: percentile(&sorted, 0.5),
+            p5: percentile(&sorted, 0.05),
+            p95: percentile(&sorted, 0.95),
+            min,
+            max,
+            histogram,
+            samples: sorted.len(),
+        }
+    }
+}
+
+/// Sobol indices of the parameter of a basic event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SobolIndex {
+    pub event: String,
+    /// Fraction of the variance of the TEP due to the parameter alone.
+    pub first_order: f64,
+    /// Fraction of the variance of the TEP due to the parameter, including its interactions
+    /// with the other parameters.
+    pub total_effect: f64,
+}
+
+/// Variance-based sensitivity of the TEP to the uncertain parameters.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SobolResult {
+    pub mean: f64,
+    pub variance: f64,
+    /// Number of TEPs computed, `samples * (events + 2)`.
+    pub evaluations: usize,
+    /// Indices of each basic event, sorted by decreasing total effect.
+    pub indices: Vec<SobolIndex>,
+}
+
+/// Percentile of the sorted values, interpolating linearly between the closest ranks.
+fn percentile(sorted: &[f64], q: f64) -> f64 {
+    let h = (sorted.len() - 1) as f64 * q;
+    let lo = h.floor() as usize;
+    let hi = (lo + 1).min(sorted.len() - 1);
+    sorted[lo] + (h - lo as f64) * (sorted[hi] - sorted[lo])
+}
+
+/// Logarithm of the gamma function, with the Lanczos approximation.
+fn ln_gamma(x: f64) -> f64 {
+    const COEF: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let a = COEF[1..]
+        .iter()
+        .enumerate()
+        .fold(COEF[0], |acc, (i, c)| acc + c / (x + (i + 1) as f64));
+    let t = x + 7.5;
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + a.ln()
+}
+
+/// Regularized lower incomplete gamma function `P(a, x)`, by its series for small `x` and by
+/// the continued fraction of `Q(a, x) = 1 - P(a, x)` otherwise.
+fn gamma_p(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    let prefactor = (-x + a * x.ln() - ln_gamma(a)).exp();
+    if x < a + 1.0 {
+        let (mut term, mut sum) = (1.0 / a, 1.0 / a);
+        for n in 1..MAX_TERMS {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * f64::EPSILON {
+                break;
+            }
+        }
+        sum * prefactor
+    } else {
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / FPMIN;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..MAX_TERMS {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            d = if d.abs() < FPMIN { FPMIN } else { d };
+            c = b + an / c;
+            c = if c.abs() < FPMIN { FPMIN } else { c };
+            d = 1.0 / d;
+            h *= d * c;
+            if (d * c - 1.0).abs() < f64::EPSILON {
+                break;
+            }
+        }
+        1.0 - prefactor * h
+    }
+}
+
+/// Continued fraction of the incomplete beta function, evaluated with the Lentz method.
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    let clamp = |v: f64| if v.abs() < FPMIN { FPMIN } else { v };
+    let mut c = 1.0;
+    let mut d = 1.0 / clamp(1.0 - (a + b) * x / (a + 1.0));
+    let mut h = d;
+    for m in 1..MAX_TERMS {
+        let m = m as f64;
+        let even = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        d = 1.0 / clamp(1.0 + even * d);
+        c = clamp(1.0 + even / c);
+        h *= d * c;
+        let odd = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        d = 1.0 / clamp(1.0 + odd * d);
+        c = clamp(1.0 + odd / c);
+        h *= d * c;
+        if (d * c - 1.0).abs() < f64::EPSILON {
+            break;
+        }
+    }
+    h
+}
+
+/// Regularized incomplete beta function `I_x(a, b)`.
+fn beta_i(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let prefactor =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (-x).ln_1p()).exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        prefactor * beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - prefactor * beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+/// Invert an increasing CDF by bisection in `[lo, hi]`.
+fn invert(cdf: impl Fn(f64) -> f64, u: f64, mut lo: f64, mut hi: f64) -> f64 {
+    for _ in 0..200 {
+        let mid = 0.5 * (lo + hi);
+        if mid <= lo || mid >= hi {
+            break;
+        }
+        if cdf(mid) < u {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    0.5 * (lo + hi)
+}
+
+impl Uncertainty {
+    /// Value of the parameter at the quantile `u`, where `mean` is the value given to the
+    /// parameter in the model. Both samplings draw the parameters through their quantiles.
+    pub fn quantile(&self, mean: f64, u: f64) -> f64 {
+        let u = u.clamp(f64::EPSILON, 1.0 - f64::EPSILON);
+        match *self {
+            Uncertainty::Lognormal { error_factor } => {
+                let sigma = error_factor.ln() / normal_quantile(0.95);
+                let mu = mean.ln() - sigma * sigma / 2.0;
+                (mu + sigma * normal_quantile(u)).exp()
+            }
+            Uncertainty::Gamma { shape } => {
+                let mut hi = shape.max(1.0);
+                while gamma_p(shape, hi) < u {
+                    hi *= 2.0;
+                }
+                mean / shape * invert(|x| gamma_p(shape, x), u, 0.0, hi)
+            }
+            Uncertainty::Beta { alpha } => {
+                let beta = alpha * (1.0 - mean) / mean;
+                invert(|x| beta_i(alpha, beta, x), u, 0.0, 1.0)
+            }
+            Uncertainty::Uniform { low, high } => low + u * (high - low),
+        }
+    }
+}
+
+/// Points of the unit hypercube, a row for each sample with a value in `[0, 1)` for each parameter.
+pub fn unit_samples(samples: usize, dims: usize, sampling: Sampling, seed: u64) -> Vec<Vec<f64>> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    match sampling {
+        Sampling::Random => (0..samples)
+            .map(|_| (0..dims).map(|_| rng.r#gen::<f64>()).collect())
+            .collect(),
+        Sampling::LatinHypercube => {
+            let mut points = vec![vec![0.0; dims]; samples];
+            for d in 0..dims {
+                let mut strata = (0..samples).collect_vec();
+                strata.shuffle(&mut rng);
+                for (point, stratum) in points.iter_mut().zip(strata) {
+                    point[d] = (stratum as f64 + rng.r#gen::<f64>()) / samples as f64;
+                }
+            }
+            points
+        }
+    }
+}
+
+impl FaultTree<String> {
+    /// Basic events whose parameter has an uncertainty distribution.
+    pub fn uncertain_events(&self) -> Vec<NodeId> {
+        self.nodes
+            .iter_enumerated()
+            .filter(|(_, n)| matches!(n, Node::BasicEvent(_, be) if be.uncertainty().is_some()))
+            .map(|(nid, _)| nid)
+            .collect()
+    }
+
+    /// Copy of the FT where the parameter of each of the `events` takes the value at the quantile
+    /// `u` of the same position.
+    pub fn with_quantiles(&self, events: &[NodeId], u: &[f64]) -> FaultTree<String> {
+        let mut ft = self.clone();
+        for (nid, u) in events.iter().zip(u) {
+            if let Node::BasicEvent(_, be) = &mut ft.nodes[*nid]
+                && let Some(uncertainty) = be.uncertainty().copied()
+            {
+                be.set_parameter(uncertainty.quantile(be.parameter(), *u));
+            }
+        }
+        ft
+    }
+
+    /// Propagate the uncertainty of the parameters of the basic events to the TEP: sample
+    /// parameter sets and compute the TEP of each one with the solver, in parallel.
+    #[allow(clippy::too_many_arguments)]
+    pub fn propagate_uncertainty(
+        &self,
+        solver: &(dyn Solver + Sync),
+        format: CNFFormat,
+        timepoint: f64,
+        timeout_s: u64,
+        preprocess: Option<String>,
+        negate_or: bool,
+        unav: bool,
+        config: UncertaintyConfig,
+    ) -> Result<UncertaintyResult, String> {
+        let events = self.uncertain_events();
+        if events.is_empty() {
+            return Err(String::from(
+                "no basic event of the model has an uncertainty distribution",
+            ));
+        }
+        let teps = unit_samples(config.samples, events.len(), config.sampling, config.seed)
+            .par_iter()
+            .map(|u| {
+                let ft = self.with_quantiles(&events, u);
+                solver.try_compute(
+                    &ft,
+                    format,
+                    timepoint,
+                    timeout_s,
+                    preprocess.clone(),
+                    negate_or,
+                    unav,
+                )
+            })
+            .collect::<Result<Vec<f64>, &'static str>>()
+            .map_err(|msg| format!("the solver failed on a parameter set: {msg}"))?;
+        Ok(UncertaintyResult::from_samples(&teps, config.bins))
+    }
+
+    /// Compute the first order and total effect Sobol indices of the uncertain parameters, with the
+    /// Saltelli sampling scheme: two independent matrices `A` and `B` of parameter sets are sampled,
+    /// and for each parameter `i` the matrix `AB_i` takes the column `i` from `B` and the rest from `A`.
+    /// The indices are given by the estimators of Saltelli (first order) and Jansen (total effect).
+    /// The TEPs of all the parameter sets are computed with the solver, in parallel. The bins of
+    /// the configuration are not used.
+    #[allow(clippy::too_many_arguments)]
+    pub fn sobol_indices(
+        &self,
+        solver: &(dyn Solver + Sync),
+        format: CNFFormat,
+        timepoint: f64,
+        timeout_s: u64,
+        preprocess: Option<String>,
+        negate_or: bool,
+        unav: bool,
+        config: UncertaintyConfig,
+    ) -> Result<SobolResult, String> {
+        let events = self.uncertain_events();
+        if events.is_empty() {
+            return Err(String::from(
+                "no basic event of the model has an uncertainty distribution",
+            ));
+        }
+        let dims = events.len();
+        let n = config.samples;
+        // Each row holds a row of A followed by the same row of B.
+        let rows = unit_samples(n, 2 * dims, config.sampling, config.seed);
+
+        // Parameter sets in the order A, B, AB_0, ..., AB_{dims - 1}.
+        let mut points = Vec::with_capacity(n * (dims + 2));
+        points.extend(rows.iter().map(|r| r[..dims].to_vec()));
+        points.extend(rows.iter().map(|r| r[dims..].to_vec()));
+        for i in 0..dims {
+            points.extend(rows.iter().map(|r| {
+                let mut point = r[..dims].to_vec();
+                point[i] = r[dims + i];
+                point
+            }));
+        }
+        let teps = points
+            .par_iter()
+            .map(|u| {
+                let ft = self.with_quantiles(&events, u);
+                solver.try_compute(
+                    &ft,
+                    format,
+                    timepoint,
+                    timeout_s,
+                    preprocess.clone(),
+                    negate_or,
+                    unav,
+                )
+            })
+            .collect::<Result<Vec<f64>, &'static str>>()
+            .map_err(|msg| format!("the solver failed on a parameter set: {msg}"))?;
+
+        let (f_a, f_b) = (&teps[..n], &teps[n..2 * n]);
+        let mean = teps[..2 * n].iter().sum::<f64>() / (2 * n) as f64;
+        let variance = teps[..2 * n]
+            .iter()
+            .map(|t| (t - mean).powi(2))
+            .sum::<f64>()
+            / (2 * n) as f64;
+        if variance <= 0.0 {
+            return Err(String::from(
+                "the TEP does not vary with the uncertain parameters",
+            ));
+        }
+
+        let indices = events
+            .iter()
+            .enumerate()
+            .map(|(i, nid)| {
+                let f_ab = &teps[(i + 2) * n..(i + 3) * n];
+                let first = (0..n).map(|j| f_b[j] * (f_ab[j] - f_a[j])).sum::<f64>() / n as f64;
+                let total =
+                    (0..n).map(|j| (f_a[j] - f_ab[j]).powi(2)).sum::<f64>() / (2 * n) as f64;
+                SobolIndex {
+                    event: self.name_of(*nid),
+                    first_order: first / variance,
+                    total_effect: total / variance,
+                }
+            })
+            .sorted_by(|a, b| {
+                b.total_effect
+                    .total_cmp(&a.total_effect)
+                    .then(b.first_order.total_cmp(&a.first_order))
+            })
+            .collect();
+        Ok(SobolResult {
+            mean,
+            variance,
+            evaluations: teps.len(),
+            indices,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::BddSolver;
+    use crate::test_utils::ft_from_dft;
+
+    fn assert_close(value: f64, expected: f64) {
+        assert!((value - expected).abs() < 1e-9, "{value} != {expected}");
+    }
+
+    #[test]
+    fn special_functions() {
+        assert_close(ln_gamma(5.0), 24f64.ln());
+        assert_close(ln_gamma(0.5), PI.sqrt().ln());
+        // P(2, x) = 1 - e^-x (1 + x).
+        assert_close(gamma_p(2.0, 1.5), 1.0 - (-1.5f64).exp() * 2.5);
+        // I_x(2, 1) = x^2.
+        assert_close(beta_i(2.0, 1.0, 0.3), 0.09);
+    }
+
+    #[test]
+    fn quantiles() {
+        // Uniform between the bounds.
+        let uniform = Uncertainty::Uniform {
+            low: 1.0,
+            high: 3.0,
+        };
+        assert_close(uniform.quantile(2.0, 0.25), 1.5);
+
+        // The 95th percentile of the lognormal is the median times the error factor, and the
+        // median is the mean times exp(-sigma^2 / 2).
+        let lognormal = Uncertainty::Lognormal { error_factor: 3.0 };
+        let sigma = 3f64.ln() / 1.6448536269514722;
+        let median = lognormal.quantile(0.01, 0.5);
+        assert_close(median, 0.01 * (-sigma * sigma / 2.0).exp());
+        assert_close(lognormal.quantile(0.01, 0.95) / median, 3.0);
+
+        // A gamma of shape 1 is an exponential: -mean * ln(1 - u).
+        let gamma = Uncertainty::Gamma { shape: 1.0 };
+        assert_close(gamma.quantile(2.0, 0.5), 2.0 * 2f64.ln());
+
+        // A beta with alpha 2 and mean 2/3 has the CDF x^2.
+        let beta = Uncertainty::Beta { alpha: 2.0 };
+        assert_close(beta.quantile(2.0 / 3.0, 0.25), 0.5);
+    }
+
+    #[test]
+    fn latin_hypercube_samples_each_stratum_once() {
+        let points = unit_samples(10, 3, Sampling::LatinHypercube, 1);
+        for d in 0..3 {
+            let strata = points
+                .iter()
+                .map(|p| (p[d] * 10.0) as usize)
+                .sorted()
+                .collect_vec();
+            assert_eq!(strata, (0..10).collect_vec());
+        }
+    }
+
+    #[test]
+    fn sobol_indices_of_an_additive_model() {
+        // X and not X split the TEP in 0.5 pA + 0.5 pB, with pA and pB uniform, so each index is
+        // the variance of its term over the sum: 0.1^2 / (0.1^2 + 0.2^2) = 0.2 for A.
+        let ft = ft_from_dft(
+            "coyan_uncertainty_sobol.dft",
+            r#"toplevel "T";
+"T" or "G1" "G2";
+"G1" and "A" "X";
+"G2" and "B" "NX";
+"NX" not "X";
+"A" prob=0.1 low=0.05 high=0.15;
+"B" prob=0.2 low=0.1 high=0.3;
+"X" prob=0.5;
+"#,
+        );
+        let config = UncertaintyConfig {
+            samples: 2000,
+            sampling: Sampling::LatinHypercube,
+            seed: 7,
+            bins: 10,
+        };
+        let result = ft
+            .sobol_indices(
+                &BddSolver::new("bdd"),
+                CNFFormat::MC21,
+                1.0,
+                60,
+                None,
+                false,
+                false,
+                config,
+            )
+            .unwrap();
+        assert_eq!(result.evaluations, 2000 * 4);
+        assert!((result.mean - 0.15).abs() < 1e-3);
+        assert!((result.variance - 0.25 * 0.05 / 12.0).abs() < 1e-4);
+        let events = result
+            .indices
+            .iter()
+            .map(|i| i.event.as_str())
+            .collect_vec();
+        assert_eq!(events, ["B", "A"]);
+        for (index, expected) in result.indices.iter().zip([0.8, 0.2]) {
+            for value in [index.first_order, index.total_effect] {
+                assert!(
+                    (value - expected).abs() < 0.05,
+                    "{}: {value} != {expected}",
+                    index.event
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,438 @@
+use itertools::Itertools;
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+use crate::formula::Formula;
+use crate::nodes::NodeId;
+use crate::solver::TIMEOUT;
+
+/// Literal of the CNF, the variable `v` (starting from 1) if positive or its negation otherwise.
+pub type Literal = i32;
+
+fn var(lit: Literal) -> usize {
+    lit.unsigned_abs() as usize
+}
+
+/// Formula in CNF over the variables `1..=n_vars`, as in the DIMACS format.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cnf {
+    pub n_vars: usize,
+    pub clauses: Vec<Vec<Literal>>,
+}
+
+impl Cnf {
+    /// Read the formula given by the Tseitin transformation, where the node with NodeId `i`
+    /// is the variable `i + 1`. Repeated literals are removed and tautologies are dropped.
+    pub fn from_formula(formula: &Formula<NodeId>, n_vars: usize) -> Self {
+        fn literal(f: &Formula<NodeId>) -> Literal {
+            match f {
+                Formula::Atom(nid) => (nid.index() + 1) as Literal,
+                Formula::Not(inner) => -literal(inner),
+                _ => panic!("Formula is not in CNF."),
+            }
+        }
+        let Formula::And(args) = formula else {
+            panic!("Top gate must be an AND to translate to CNF.")
+        };
+        let clauses = args
+            .iter()
+            .map(|clause| match clause {
+                Formula::Or(lits) => lits.iter().map(literal).unique().collect_vec(),
+                lit => vec![literal(lit)],
+            })
+            .filter(|clause| !clause.iter().any(|l| clause.contains(&-l)))
+            .collect_vec();
+        Cnf { n_vars, clauses }
+    }
+
+    /// Write the formula in DIMACS format, without weights.
+    pub fn to_dimacs(&self) -> String {
+        let mut text = format!("p cnf {} {}\n", self.n_vars, self.clauses.len());
+        for clause in &self.clauses {
+            text.push_str(&clause.iter().join(" "));
+            text.push_str(" 0\n");
+        }
+        text
+    }
+}
+
+/// Pending step of the search of [ModelCounter], kept in an explicit stack so the depth of the
+/// search is not limited by the stack of the thread.
+enum Frame {
+    /// Count of a connected component, branching on the variable `v`. `branch` is the number of
+    /// literals of `v` already tried, and `start` the position of the trail of the last one.
+    Component {
+        key: (Vec<usize>, Vec<usize>),
+        v: usize,
+        branch: u8,
+        start: usize,
+        count: f64,
+    },
+    /// Product of the count of the components that remain after an assignment, kept in
+    /// reverse order.
+    Assigned {
+        components: Vec<(Vec<usize>, Vec<usize>)>,
+        count: f64,
+    },
+}
+
+/// Exact weighted model counter. It runs a DPLL search with unit propagation, splitting the
+/// remaining clauses in connected components after each decision, and counting each
+/// component only once by keeping their results in a cache.
+pub struct ModelCounter<'a> {
+    cnf: &'a Cnf,
+    /// Weight of the positive and negative literal of each variable, where position `i` is the variable `i + 1`.
+    weights: &'a [(f64, f64)],
+    /// Clauses where each variable appears.
+    occurrences: Vec<Vec<usize>>,
+    /// 1 if the variable is true, -1 if false and 0 if unassigned.
+    assignment: Vec<i8>,
+    /// Assigned literals, in order.
+    trail: Vec<Literal>,
+    /// Count of each component, given by its variables and its clauses.
+    cache: HashMap<(Vec<usize>, Vec<usize>), f64>,
+    /// Approximate size of the cache in bytes and its limit. The cache is emptied when full.
+    cache_bytes: usize,
+    max_cache_bytes: Option<usize>,
+    deadline: Option<Instant>,
+}
+
+impl<'a> ModelCounter<'a> {
+    /// The limit of the cache is given in MB.
+    pub fn new(
+        cnf: &'a Cnf,
+        weights: &'a [(f64, f64)],
+        cache_size: Option<usize>,
+        deadline: Option<Instant>,
+    ) -> Self {
+        let mut occurrences = vec![vec![]; cnf.n_vars + 1];
+        for (c, clause) in cnf.clauses.iter().enumerate() {
+            for lit in clause {
+                occurrences[var(*lit)].push(c);
+            }
+        }
+        ModelCounter {
+            cnf,
+            weights,
+            occurrences,
+            assignment: vec![0; cnf.n_vars + 1],
+            trail: vec![],
+            cache: HashMap::new(),
+            cache_bytes: 0,
+            max_cache_bytes: cache_size.map(|mb| mb * 1024 * 1024),
+            deadline,
+        }
+    }
+
+    /// Weighted model count of the formula. Returns an error if the deadline is reached.
+    pub fn count(&mut self) -> Result<f64, &'static str> {
+        for c in 0..self.cnf.clauses.len() {
+            if let [lit] = self.cnf.clauses[c][..] {
+                match self.value(lit) {
+                    0 => self.assign(lit),
+                    -1 => return Ok(0.0),
+                    _ => {}
+                }
+            }
+        }
+        if !self.propagate(0) {
+            return Ok(0.0);
+        }
+        let vars = (1..=self.cnf.n_vars).collect_vec();
+        let clauses = (0..self.cnf.clauses.len()).collect_vec();
+        self.search(self.assigned(&vars, &clauses, 0))
+    }
+
+    fn value(&self, lit: Literal) -> i8 {
+        let value = self.assignment[var(lit)];
+        if lit > 0 { value } else { -value }
+    }
+
+    fn weight(&self, lit: Literal) -> f64 {
+        let (pos, neg) = self.weights[var(lit) - 1];
+        if lit > 0 { pos } else { neg }
+    }
+
+    fn assign(&mut self, lit: Literal) {
+        self.assignment[var(lit)] = if lit > 0 { 1 } else { -1 };
+        self.trail.push(lit);
+    }
+
+    /// Unassign the literals of the trail from position `start`.
+    fn undo(&mut self, start: usize) {
+        for lit in self.trail.drain(start..) {
+            self.assignment[var(lit)] = 0;
+        }
+    }
+
+    /// Unit propagation of the literals of the trail from position `start`.
+    /// Returns false if a clause gets falsified.
+    fn propagate(&mut self, start: usize) -> bool {
+        let cnf = self.cnf;
+        let mut next = start;
+        while next < self.trail.len() {
+            let v = var(self.trail[next]);
+            next += 1;
+            for i in 0..self.occurrences[v].len() {
+                let clause = &cnf.clauses[self.occurrences[v][i]];
+                let mut unassigned = vec![];
+                let mut satisfied = false;
+                for lit in clause {
+                    match self.value(*lit) {
+                        1 => {
+                            satisfied = true;
+                            break;
+                        }
+                        0 => unassigned.push(*lit),
+                        _ => {}
+                    }
+                }
+                match unassigned[..] {
+                    _ if satisfied => {}
+                    [] => return false,
+                    [lit] => self.assign(lit),
+                    _ => {}
+                }
+            }
+        }
+        true
+    }
+
+    /// Split the clauses not yet satisfied in connected components, given by their unassigned
+    /// variables and their clauses, both sorted.
+    fn components(&self, clauses: &[usize]) -> Vec<(Vec<usize>, Vec<usize>)> {
+        fn find(parent: &mut HashMap<usize, usize>, v: usize) -> usize {
+            let mut root = v;
+            while parent[&root] != root {
+                root = parent[&root];
+            }
+            let mut u = v;
+            while u != root {
+                u = parent.insert(u, root).expect("The variable has a parent");
+            }
+            root
+        }
+
+        let mut parent: HashMap<usize, usize> = HashMap::new();
+        let mut active = vec![];
+        for c in clauses {
+            let clause = &self.cnf.clauses[*c];
+            if clause.iter().any(|lit| self.value(*lit) == 1) {
+                continue;
+            }
+            let vars = clause
+                .iter()
+                .filter(|lit| self.value(**lit) == 0)
+                .map(|lit| var(*lit))
+                .collect_vec();
+            for v in &vars {
+                parent.entry(*v).or_insert(*v);
+            }
+            let root = find(&mut parent, vars[0]);
+            for v in &vars[1..] {
+                let other = find(&mut parent, *v);
+                parent.insert(other, root);
+            }
+            active.push((*c, vars[0]));
+        }
+
+        let mut components: HashMap<usize, (Vec<usize>, Vec<usize>)> = HashMap::new();
+        for v in parent.keys().copied().collect_vec() {
+            let root = find(&mut parent, v);
+            components.entry(root).or_default().0.push(v);
+        }
+        for (c, v) in active {
+            let root = find(&mut parent, v);
+            components.get_mut(&root).unwrap().1.push(c);
+        }
+        components
+            .into_values()
+            .map(|(mut vars, clauses)| {
+                vars.sort();
+                (vars, clauses)
+            })
+            .collect_vec()
+    }
+
+    /// Frame of the count of the component after the literals of the trail from position `start`
+    /// were assigned: the weight of those literals, times the count of the new components and
+    /// the weight of the variables of the component that are no longer constrained.
+    fn assigned(&self, vars: &[usize], clauses: &[usize], start: usize) -> Frame {
+        let mut count: f64 = self.trail[start..]
+            .iter()
+            .map(|l| self.weight(*l))
+            .product();
+        let mut components = self.components(clauses);
+        let constrained: HashSet<usize> =
+            components.iter().flat_map(|(vs, _)| vs).copied().collect();
+        for v in vars {
+            if self.assignment[*v] == 0 && !constrained.contains(v) {
+                let (pos, neg) = self.weights[v - 1];
+                count *= pos + neg;
+            }
+        }
+        components.reverse();
+        Frame::Assigned { components, count }
+    }
+
+    /// Frame of the count of a connected component, that branches on the variable that appears
+    /// in more clauses. Gives the count directly if the component is in the cache.
+    fn component(&self, vars: Vec<usize>, clauses: Vec<usize>) -> Result<Frame, f64> {
+        let key = (vars, clauses);
+        if let Some(count) = self.cache.get(&key) {
+            return Err(*count);
+        }
+        let v = *key
+            .0
+            .iter()
+            .max_by_key(|v| self.occurrences[**v].len())
+            .expect("Components have at least one variable");
+        Ok(Frame::Component {
+            key,
+            v,
+            branch: 0,
+            start: 0,
+            count: 0.0,
+        })
+    }
+
+    fn insert_cache(&mut self, key: (Vec<usize>, Vec<usize>), count: f64) {
+        let entry_bytes = (key.0.len() + key.1.len()) * size_of::<usize>() + 64;
+        if self
+            .max_cache_bytes
+            .is_some_and(|max| self.cache_bytes + entry_bytes > max)
+        {
+            self.cache.clear();
+            self.cache_bytes = 0;
+        }
+        self.cache_bytes += entry_bytes;
+        self.cache.insert(key, count);
+    }
+
+    /// Run the search from the frame until it is finished. Each frame is resumed with the count
+    /// of the frame it started, if any, and gives its own count when it is popped.
+    fn search(&mut self, frame: Frame) -> Result<f64, &'static str> {
+        let mut stack = vec![frame];
+        let mut result = None;
+        while let Some(frame) = stack.last_mut() {
+            let next = match frame {
+                Frame::Component {
+                    key,
+                    v,
+                    branch,
+                    start,
+                    count,
+                } => {
+                    if let Some(value) = result.take() {
+                        *count += value;
+                        self.undo(*start);
+                    }
+                    if *branch == 2 {
+                        let count = *count;
+                        let Some(Frame::Component { key, .. }) = stack.pop() else {
+                            unreachable!()
+                        };
+                        self.insert_cache(key, count);
+                        result = Some(count);
+                        continue;
+                    }
+                    let lit = if *branch == 0 {
+                        *v as Literal
+                    } else {
+                        -(*v as Literal)
+                    };
+                    *branch += 1;
+                    *start = self.trail.len();
+                    self.assign(lit);
+                    if !self.propagate(*start) {
+                        self.undo(*start);
+                        continue;
+                    }
+                    self.assigned(&key.0, &key.1, *start)
+                }
+                Frame::Assigned { components, count } => {
+                    if let Some(value) = result.take() {
+                        *count *= value;
+                    }
+                    let component = components.pop();
+                    match component {
+                        Some((vars, clauses)) if *count != 0.0 => {
+                            if self.deadline.is_some_and(|d| Instant::now() > d) {
+                                return Err(TIMEOUT);
+                            }
+                            match self.component(vars, clauses) {
+                                Ok(frame) => frame,
+                                Err(cached) => {
+                                    result = Some(cached);
+                                    continue;
+                                }
+                            }
+                        }
+                        _ => {
+                            result = Some(*count);
+                            stack.pop();
+                            continue;
+                        }
+                    }
+                }
+            };
+            stack.push(next);
+        }
+        Ok(result.expect("The search gives a count"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::ft_from_dft;
+
+    #[test]
+    fn count_of_clauses() {
+        // (x1 or x2) and (not x1 or x3): x1 x3 + (not x1) x2.
+        let cnf = Cnf {
+            n_vars: 3,
+            clauses: vec![vec![1, 2], vec![-1, 3]],
+        };
+        let weights = [(0.1, 0.9), (0.2, 0.8), (0.3, 0.7)];
+        let count = ModelCounter::new(&cnf, &weights, None, None)
+            .count()
+            .unwrap();
+        assert!((count - (0.1 * 0.3 + 0.9 * 0.2)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn count_of_fault_tree() {
+        // TEP of the 2of3 gate: ab + ac + bc - 2abc = 0.098.
+        let ft = ft_from_dft(
+            "coyan_wmc_voting.dft",
+            r#"toplevel "T";
+"T" or "G" "H";
+"G" and "A" "B";
+"H" 2of3 "A" "B" "C";
+"A" prob=0.1;
+"B" prob=0.2;
+"C" prob=0.3;
+"#,
+        );
+        let cnf = Cnf::from_formula(&ft.apply_tseitin(), ft.get_count());
+        let weights = ft.literal_weights(1.0, false);
+        for cache_size in [None, Some(0)] {
+            let count = ModelCounter::new(&cnf, &weights, cache_size, None)
+                .count()
+                .unwrap();
+            assert!((count - 0.098).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn count_reaches_deadline() {
+        let cnf = Cnf {
+            n_vars: 2,
+            clauses: vec![vec![1, 2]],
+        };
+        let weights = [(0.5, 0.5), (0.5, 0.5)];
+        let result = ModelCounter::new(&cnf, &weights, None, Some(Instant::now())).count();
+        assert_eq!(result, Err(TIMEOUT));
+    }
+}
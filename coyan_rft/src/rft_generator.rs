@@ -5,7 +5,7 @@ use rand::{
     rngs::StdRng,
     seq::{IteratorRandom, SliceRandom},
 };
-use std::{collections::HashMap, fs::File, io::Write, ops::Index};
+use std::ops::Index;
 const EPSILON: f64 = f64::EPSILON; // 2.2204460492503131E-16f64
 
 /// Configuration for the random FT. Each value represent the proportion of each type of node.
@@ -203,7 +203,9 @@ impl RFaultTree<String> {
             .collect_vec();
 
         // Fill placeholders re-arranges the gates and set correct types.
-        ft_norm.fill_placeholders(true);
+        ft_norm
+            .fill_placeholders(true)
+            .expect("Generated gates must refer to existing nodes.");
 
         RFaultTree {
             ft: ft_norm,
@@ -212,72 +214,8 @@ impl RFaultTree<String> {
         }
     }
 
-    /// Save the fault tree CNF formula into a .dft.
+    /// Save the fault tree into a .dft.
     pub fn save_to_dft(&self, filename: String) {
-        let reverse_lookup_table: HashMap<NodeId, String> = self
-            .ft
-            .lookup_table
-            .iter()
-            .map(|(k, v)| (*v, k.clone()))
-            .collect();
-
-        let top_line = format!(
-            "toplevel {};",
-            reverse_lookup_table.get(&self.ft.root_id).unwrap()
-        );
-
-        let gates = self
-            .ft
-            .nodes
-            .iter_enumerated()
-            .filter_map(|(i, n)| match &n {
-                Node::And(args) => Some(format!(
-                    "{} and {};",
-                    reverse_lookup_table.get(&i).unwrap(),
-                    args.iter()
-                        .map(|c_id| reverse_lookup_table.get(c_id).unwrap())
-                        .join(" ")
-                )),
-                Node::Or(args) => Some(format!(
-                    "{} or {};",
-                    reverse_lookup_table.get(&i).unwrap(),
-                    args.iter()
-                        .map(|c_id| reverse_lookup_table.get(c_id).unwrap())
-                        .join(" ")
-                )),
-                Node::Vot(k, args) => Some(format!(
-                    "{} {}of{} {};",
-                    reverse_lookup_table.get(&i).unwrap(),
-                    k,
-                    args.len(),
-                    args.iter()
-                        .map(|c_id| reverse_lookup_table.get(c_id).unwrap())
-                        .join(" ")
-                )),
-                _ => None,
-            })
-            .join("\n");
-
-        let be = self
-            .ft
-            .nodes
-            .iter()
-            .filter_map(|n| match &n {
-                Node::BasicEvent(name, be) => Some(format!("{} {};", name, be)),
-                _ => None,
-            })
-            .join("\n");
-
-        let mut f = File::create(filename).expect("unable to create file");
-        f.write_all(top_line.as_bytes())
-            .expect("Error writing problem line to file");
-        f.write_all("\n".as_bytes())
-            .expect("Error writing the formula to file");
-        f.write_all(gates.as_bytes())
-            .expect("Error writing the Gate weights to file");
-        f.write_all("\n".as_bytes())
-            .expect("Error writing . to file");
-        f.write_all(be.as_bytes())
-            .expect("Error writing the BE weights to file");
+        FaultTree::from(self.ft.clone()).save_to_dft(&filename);
     }
 }